@@ -0,0 +1,448 @@
+//! Creates and extracts archives. This module is only available when the `archive` feature is
+//! enabled.
+//!
+//! Zip files are the format Windows users can open without extra software, so they suit backups
+//! and bug reports meant for people. `.tar.gz` files keep Unix permissions and links, and are how
+//! most Linux software is distributed.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    fs_utils::{safe_join, walk},
+    progress::{Progress, ProgressTracker},
+    temp::TempFile,
+};
+
+/// A filter called with paths relative to the root of an archive.
+type PathFilter = Box<dyn Fn(&Path) -> bool>;
+
+/// Options for [`zip_dir`].
+pub struct ZipOptions {
+    compression_level: Option<u32>,
+    exclude: Option<PathFilter>,
+}
+
+impl ZipOptions {
+    /// Constructs a new `ZipOptions` with the default values.
+    /// The default values are:
+    /// * `compression_level`: `None`, which uses deflate's default level of 6
+    /// * `exclude`: `None`
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::archive::ZipOptions;
+    ///
+    /// let options = ZipOptions::new();
+    /// assert_eq!(options.get_compression_level(), None);
+    /// assert!(!options.is_excluded(std::path::Path::new("anything")));
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            compression_level: None,
+            exclude: None,
+        }
+    }
+
+    /// Gets the compression level, if set.
+    pub fn get_compression_level(&self) -> Option<u32> {
+        self.compression_level
+    }
+
+    /// Sets the compression level, from 0 to 9. Level 0 stores files without compressing them,
+    /// which is fastest for contents which are already compressed, like images or jars. Higher
+    /// levels are clamped to 9.
+    ///
+    /// # Arguments
+    /// * `level` - The compression level.
+    pub fn compression_level(mut self, level: u32) -> Self {
+        self.compression_level = Some(level.min(9));
+        self
+    }
+
+    /// Sets a filter for entries to leave out of the archive. It is called with the path of each
+    /// entry relative to the source directory; excluding a directory excludes everything in it.
+    ///
+    /// # Arguments
+    /// * `exclude` - Returns `true` for entries to leave out.
+    pub fn exclude<F: Fn(&Path) -> bool + 'static>(mut self, exclude: F) -> Self {
+        self.exclude = Some(Box::new(exclude));
+        self
+    }
+
+    /// Checks if an entry is excluded by the [`exclude`](ZipOptions::exclude) filter.
+    ///
+    /// # Arguments
+    /// * `relative` - The path of the entry relative to the source directory.
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude(relative))
+    }
+
+    /// Gets the `zip` options for an entry with the given metadata.
+    fn file_options(&self, metadata: &fs::Metadata) -> FileOptions {
+        let mut options = match self.compression_level {
+            Some(0) => FileOptions::default().compression_method(CompressionMethod::Stored),
+            level => FileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .compression_level(level.and_then(|level| i32::try_from(level).ok())),
+        };
+        if let Some(modified) = metadata.modified().ok().and_then(zip_time) {
+            options = options.last_modified_time(modified);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            options = options.unix_permissions(metadata.permissions().mode());
+        }
+        options.large_file(metadata.len() >= u64::from(u32::MAX))
+    }
+}
+
+impl Default for ZipOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ZipOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZipOptions")
+            .field("compression_level", &self.compression_level)
+            .field("exclude", &self.exclude.is_some())
+            .finish()
+    }
+}
+
+/// Zips a directory tree. Entries are stored relative to `src_dir`, with their modification times
+/// and, on Unix, their permissions. Symbolic links are followed, and links which lead back into a
+/// directory being zipped are skipped. The zip file is written atomically, so a failed zip never
+/// leaves a partial file at `dest_zip`.
+///
+/// # Arguments
+///
+/// * `src_dir` - The directory to zip.
+/// * `dest_zip` - The path of the zip file. It is overwritten if it exists.
+/// * `options` - The compression level and entries to exclude.
+///
+/// # Errors
+///
+/// An error is returned if an entry could not be read or the zip file could not be written.
+///
+/// # Examples
+/// ```
+/// use dablenutil::archive::{zip_dir, ZipOptions};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_zip_dir");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("server/world"))?;
+/// fs::write(dir.join("server/world/level.dat"), "level")?;
+/// fs::write(dir.join("server/server.log"), "log")?;
+///
+/// let options = ZipOptions::new()
+///     .compression_level(9)
+///     .exclude(|path| path.extension().is_some_and(|ext| ext == "log"));
+/// zip_dir(&dir.join("server"), &dir.join("backup.zip"), &options)?;
+///
+/// let archive = zip::ZipArchive::new(fs::File::open(dir.join("backup.zip"))?).unwrap();
+/// let mut names: Vec<_> = archive.file_names().collect();
+/// names.sort_unstable();
+/// assert_eq!(names, ["world/", "world/level.dat"]);
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn zip_dir(src_dir: &Path, dest_zip: &Path, options: &ZipOptions) -> crate::Result<()> {
+    if !src_dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            format!("{} is not a directory", src_dir.display()),
+        )
+        .into());
+    }
+    let parent = match dest_zip.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let temp = TempFile::new_in(parent, ".zip")?;
+    // zipping into the source directory must not add the zip to itself
+    let canonical_parent = parent.canonicalize()?;
+    let outputs: Vec<PathBuf> = [temp.path(), dest_zip]
+        .iter()
+        .filter_map(|output| Some(canonical_parent.join(output.file_name()?)))
+        .collect();
+    let mut zip = ZipWriter::new(temp);
+    let mut excluded: HashSet<PathBuf> = HashSet::new();
+    for entry in walk(src_dir).follow_symlinks(true) {
+        let entry = entry?;
+        let Ok(relative) = entry.path().strip_prefix(src_dir) else {
+            continue;
+        };
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        if relative
+            .ancestors()
+            .skip(1)
+            .any(|dir| excluded.contains(dir))
+        {
+            continue;
+        }
+        if options.is_excluded(relative) {
+            excluded.insert(relative.to_path_buf());
+            continue;
+        }
+        let is_output = outputs
+            .iter()
+            .any(|output| output.file_name() == entry.path().file_name())
+            && entry
+                .path()
+                .canonicalize()
+                .is_ok_and(|path| outputs.contains(&path));
+        if is_output {
+            continue;
+        }
+        let name = entry_name(relative);
+        let file_options = options.file_options(entry.metadata());
+        if entry.metadata().is_dir() {
+            zip.add_directory(name, file_options)?;
+        } else {
+            zip.start_file(name, file_options)?;
+            io::copy(&mut File::open(entry.path())?, &mut zip)?;
+        }
+    }
+    zip.finish()?.persist(dest_zip)?;
+    Ok(())
+}
+
+/// Extracts a zip file into a directory, creating it if needed. See [`unzip_with_progress`] for
+/// details.
+///
+/// # Arguments
+///
+/// * `zip_path` - The zip file to extract.
+/// * `dest_dir` - The directory to extract into.
+///
+/// # Errors
+///
+/// An error is returned if the zip file is invalid, has an unsafe entry name, or an entry could
+/// not be written.
+///
+/// # Examples
+/// ```
+/// use dablenutil::archive::{unzip, zip_dir, ZipOptions};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_unzip");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("modpack/mods"))?;
+/// fs::write(dir.join("modpack/mods/sodium.jar"), "jar")?;
+/// zip_dir(&dir.join("modpack"), &dir.join("modpack.zip"), &ZipOptions::new())?;
+///
+/// let extracted = unzip(&dir.join("modpack.zip"), &dir.join("instance"))?;
+/// assert_eq!(extracted, [dir.join("instance/mods/sodium.jar")]);
+/// assert_eq!(fs::read_to_string(dir.join("instance/mods/sodium.jar"))?, "jar");
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn unzip(zip_path: &Path, dest_dir: &Path) -> crate::Result<Vec<PathBuf>> {
+    unzip_with_progress(zip_path, dest_dir, |_| {})
+}
+
+/// Extracts a zip file into a directory, creating it if needed, and returns the paths of the
+/// extracted files. Existing files are overwritten.
+///
+/// Entry names are untrusted: every name is checked with [`safe_join`] before anything is
+/// extracted, so a zip with an absolute path or one climbing out of `dest_dir` with `..` (a "zip
+/// slip") is rejected without writing anything. On Unix, files stored with an execute bit are
+/// made executable.
+///
+/// # Arguments
+///
+/// * `zip_path` - The zip file to extract.
+/// * `dest_dir` - The directory to extract into.
+/// * `on_progress` - Called as bytes are extracted, with the progress through the total
+///   uncompressed size.
+///
+/// # Errors
+///
+/// An error is returned if the zip file is invalid, has an unsafe entry name, or an entry could
+/// not be written. Entries extracted before a write error are left in place.
+pub fn unzip_with_progress<F: FnMut(Progress)>(
+    zip_path: &Path,
+    dest_dir: &Path,
+    mut on_progress: F,
+) -> crate::Result<Vec<PathBuf>> {
+    let mut archive = ZipArchive::new(io::BufReader::new(File::open(zip_path)?))?;
+    let mut targets = Vec::with_capacity(archive.len());
+    let mut total = 0;
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        // zips made on Windows sometimes use backslashes
+        targets.push(safe_join(dest_dir, entry.name().replace('\\', "/"))?);
+        total += entry.size();
+    }
+
+    crate::create_dir_if_not_exists(dest_dir)?;
+    let mut tracker = ProgressTracker::new(total);
+    let mut done = 0;
+    let mut extracted = Vec::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    for (i, target) in targets.into_iter().enumerate() {
+        let mut entry = archive.by_index(i)?;
+        if entry.is_dir() {
+            crate::create_dir_if_not_exists(&target)?;
+            continue;
+        }
+        crate::ensure_parent_dir(&target)?;
+        let mut file = File::create(&target)?;
+        loop {
+            let read = match entry.read(&mut buf) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            file.write_all(&buf[..read])?;
+            done += read as u64;
+            on_progress(tracker.update(done));
+        }
+        if entry.unix_mode().is_some_and(|mode| mode & 0o111 != 0) {
+            crate::fs_utils::set_executable(&target)?;
+        }
+        extracted.push(target);
+    }
+    Ok(extracted)
+}
+
+/// Packs a file or directory into a `.tar.gz` file, like `tar -czf dest src`. A directory is
+/// stored under its own name, so extracting the archive recreates it. Symbolic links are stored
+/// as links. The archive is written atomically, so a failure never leaves a partial file at
+/// `dest`.
+///
+/// # Arguments
+///
+/// * `src` - The file or directory to pack.
+/// * `dest` - The path of the archive, usually ending in `.tar.gz`. It is overwritten if it
+///   exists.
+///
+/// # Errors
+///
+/// An error is returned if `src` could not be read or the archive could not be written.
+///
+/// # Examples
+/// ```
+/// use dablenutil::archive::{create_tar_gz, extract_tar_gz};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_tar_gz");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("server-1.20/config"))?;
+/// fs::write(dir.join("server-1.20/config/server.properties"), "motd=hi")?;
+///
+/// create_tar_gz(&dir.join("server-1.20"), &dir.join("server.tar.gz"))?;
+/// extract_tar_gz(&dir.join("server.tar.gz"), &dir.join("extracted"))?;
+/// assert_eq!(
+///     fs::read_to_string(dir.join("extracted/server-1.20/config/server.properties"))?,
+///     "motd=hi"
+/// );
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_tar_gz(src: &Path, dest: &Path) -> crate::Result<()> {
+    let name = src.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} has no file name", src.display()),
+        )
+    })?;
+    let parent = match dest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let temp = TempFile::new_in(parent, ".tar.gz")?;
+    let mut builder = tar::Builder::new(GzEncoder::new(temp, Compression::default()));
+    builder.follow_symlinks(false);
+    if fs::metadata(src)?.is_dir() {
+        builder.append_dir_all(name, src)?;
+    } else {
+        builder.append_path_with_name(src, name)?;
+    }
+    let mut temp = builder.into_inner()?.finish()?;
+    temp.flush()?;
+    temp.persist(dest)?;
+    Ok(())
+}
+
+/// Extracts a `.tar.gz` file into a directory, creating it if needed, like `tar -xzf src -C
+/// dest`. Permissions and links are restored. Absolute entry paths are extracted relative to
+/// `dest_dir`, entries with `..` components are skipped, and links can't be used to write outside
+/// `dest_dir`.
+///
+/// # Arguments
+///
+/// * `src` - The archive to extract.
+/// * `dest_dir` - The directory to extract into.
+///
+/// # Errors
+///
+/// An error is returned if the archive is invalid or an entry could not be written. Entries
+/// extracted before the error are left in place.
+pub fn extract_tar_gz(src: &Path, dest_dir: &Path) -> crate::Result<()> {
+    crate::create_dir_if_not_exists(dest_dir)?;
+    let mut archive = tar::Archive::new(GzDecoder::new(io::BufReader::new(File::open(src)?)));
+    archive.unpack(dest_dir)?;
+    Ok(())
+}
+
+/// Gets the name of a zip entry, which always uses `/` as the separator.
+fn entry_name(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Converts a point in time to a zip timestamp, in UTC since zip files have no time zone. Returns
+/// `None` for times zip files can't represent, before 1980 or after 2107.
+fn zip_time(time: SystemTime) -> Option<zip::DateTime> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let days = i64::try_from(secs / 86_400).ok()?;
+    let secs_of_day = secs % 86_400;
+    // civil date from days since the epoch, from Howard Hinnant's date algorithms
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(year).ok()?,
+        u8::try_from(month).ok()?,
+        u8::try_from(day).ok()?,
+        u8::try_from(secs_of_day / 3600).ok()?,
+        u8::try_from(secs_of_day % 3600 / 60).ok()?,
+        u8::try_from(secs_of_day % 60).ok()?,
+    )
+    .ok()
+}
@@ -0,0 +1,190 @@
+//! Ships files like default configs and templates inside the binary, and writes them out at
+//! runtime.
+//!
+//! [`embed_assets!`](crate::embed_assets) embeds files at compile time, and [`extract_if_changed`]
+//! writes them to a directory. Each extraction records the hashes of the assets it wrote, so a
+//! file is only rewritten when the embedded version changes, which keeps user edits to extracted
+//! files until an update ships a new default.
+//!
+//! # Examples
+//! ```
+//! use dablenutil::assets::{self, Asset};
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! static ASSETS: &[Asset] = &[
+//!     Asset::new("config.toml", b"volume = 11\n"),
+//!     Asset::new("templates/motd.txt", b"Welcome!\n"),
+//! ];
+//!
+//! let dir = std::env::temp_dir().join("dablenutil_assets");
+//! # let _ = std::fs::remove_dir_all(&dir);
+//! assert_eq!(assets::extract_if_changed(ASSETS, &dir)?.len(), 2);
+//! assert!(assets::extract_if_changed(ASSETS, &dir)?.is_empty());
+//! assert_eq!(std::fs::read_to_string(dir.join("templates/motd.txt"))?, "Welcome!\n");
+//! # std::fs::remove_dir_all(&dir)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::fs_utils::atomic_write;
+
+/// The file in the destination directory recording the hashes of the extracted assets.
+const HASHES_FILE: &str = ".assets";
+
+/// A file embedded in the binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Asset {
+    path: &'static str,
+    contents: &'static [u8],
+    hash: u64,
+}
+
+impl Asset {
+    /// Constructs a new `Asset`, hashing its contents. This is a `const fn`, so the hash of an
+    /// asset in a `static` is computed at compile time.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The `/`-separated path to extract the asset to, relative to the destination.
+    /// * `contents` - The contents of the asset.
+    pub const fn new(path: &'static str, contents: &'static [u8]) -> Self {
+        Self {
+            path,
+            contents,
+            hash: fnv1a(contents),
+        }
+    }
+
+    /// Gets the path the asset is extracted to, relative to the destination.
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Gets the contents of the asset.
+    pub fn contents(&self) -> &'static [u8] {
+        self.contents
+    }
+
+    /// Gets the contents of the asset as a string, if they are valid UTF-8.
+    pub fn as_str(&self) -> Option<&'static str> {
+        std::str::from_utf8(self.contents).ok()
+    }
+
+    /// Gets the hash of the contents of the asset.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Embeds files into the binary as a `&'static [Asset]`. Paths are relative to the directory of
+/// the `Cargo.toml` of the crate using the macro, and are also the paths the files are extracted
+/// to.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{assets::Asset, embed_assets};
+///
+/// static ASSETS: &[Asset] = embed_assets!["README.md", "Cargo.toml"];
+///
+/// assert_eq!(ASSETS[1].path(), "Cargo.toml");
+/// assert!(ASSETS[1].as_str().unwrap().contains("[package]"));
+/// ```
+#[macro_export]
+macro_rules! embed_assets {
+    ($($path:literal),* $(,)?) => {
+        &[$(
+            $crate::assets::Asset::new(
+                $path,
+                include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)),
+            )
+        ),*]
+    };
+}
+
+/// Writes `assets` into `dest_dir`, skipping those which haven't changed since they were last
+/// extracted there. An asset is written if it was never extracted, its embedded hash differs from
+/// the one recorded at the last extraction, or its file was deleted.
+///
+/// Files are written atomically, and parent directories are created as needed.
+///
+/// # Arguments
+///
+/// * `assets` - The assets to extract, usually from [`embed_assets!`](crate::embed_assets).
+/// * `dest_dir` - The directory to extract the assets to.
+///
+/// # Errors
+///
+/// An error is returned if an asset or the record of hashes could not be read or written. Assets
+/// written before the error are recorded, so they aren't written again.
+pub fn extract_if_changed(assets: &[Asset], dest_dir: &Path) -> crate::Result<Vec<PathBuf>> {
+    crate::create_dir_if_not_exists(dest_dir)?;
+    let hashes_path = dest_dir.join(HASHES_FILE);
+    let mut hashes = match fs::read_to_string(&hashes_path) {
+        Ok(contents) => parse_hashes(&contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut written = Vec::new();
+    let mut result = Ok(());
+    for asset in assets {
+        let path = dest_dir.join(asset.path);
+        let unchanged = hashes.get(asset.path) == Some(&asset.hash);
+        match path.try_exists() {
+            Ok(true) if unchanged => continue,
+            Ok(_) => {}
+            Err(e) => {
+                result = Err(e.into());
+                break;
+            }
+        }
+        if let Err(e) = crate::ensure_parent_dir(&path)
+            .and_then(|()| atomic_write(&path, asset.contents, false))
+        {
+            result = Err(e);
+            break;
+        }
+        hashes.insert(asset.path.to_string(), asset.hash);
+        written.push(path);
+    }
+    if !written.is_empty() {
+        let mut entries: Vec<_> = hashes.into_iter().collect();
+        entries.sort();
+        let contents = entries
+            .into_iter()
+            .map(|(path, hash)| format!("{:016x}\t{}\n", hash, path))
+            .collect::<Vec<_>>()
+            .concat();
+        atomic_write(&hashes_path, contents, false)?;
+    }
+    result.map(|()| written)
+}
+
+/// Parses the record of hashes, skipping malformed lines so they are rewritten.
+fn parse_hashes(contents: &str) -> HashMap<String, u64> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (hash, path) = line.split_once('\t')?;
+            Some((path.to_string(), u64::from_str_radix(hash, 16).ok()?))
+        })
+        .collect()
+}
+
+/// Hashes bytes with 64-bit FNV-1a, which is stable across builds and simple enough to run at
+/// compile time.
+const fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
@@ -0,0 +1,386 @@
+//! Registers an application as the handler for a file extension, so editor-style apps can claim
+//! their file types. This module is only available when the `associations` feature is enabled.
+//!
+//! What [`register`] does depends on the platform:
+//! * On Windows, it writes a `ProgID` under `HKEY_CURRENT_USER\Software\Classes`, points the
+//!   extension at it, and notifies the shell.
+//! * On Linux and other Unix desktops, it writes a `.desktop` entry and a shared MIME-info package
+//!   under `$XDG_DATA_HOME`, refreshes their databases, and sets the app as the default handler
+//!   with `xdg-mime`.
+//! * On macOS, file types are declared in the `Info.plist` of the app bundle and picked up by
+//!   Launch Services when the app is installed, so [`register`] returns an `Unsupported` error.
+//!   Use [`info_plist_entry`] to generate the declaration instead.
+//!
+//! Everything is registered for the current user only, so no elevated rights are needed.
+
+#[cfg(not(windows))]
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::{fs, process::Command};
+
+/// Information about the application registering a file type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AppInfo {
+    id: String,
+    name: String,
+    exec: PathBuf,
+    mime_type: Option<String>,
+    description: Option<String>,
+    icon: Option<PathBuf>,
+}
+
+impl AppInfo {
+    /// Constructs a new `AppInfo` with the default values for the optional fields.
+    /// The default values are:
+    /// * `mime_type`: `None`, which uses `application/x-<extension>`
+    /// * `description`: `None`, which uses `<name> <EXTENSION> file`
+    /// * `icon`: `None`
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - A unique, reverse-DNS identifier for the app, like `com.dablenparty.editor`.
+    /// * `name` - The display name of the app.
+    /// * `exec` - The path to the executable, which is run with the file to open as its only
+    ///   argument.
+    pub fn new<I: Into<String>, N: Into<String>, P: Into<PathBuf>>(
+        id: I,
+        name: N,
+        exec: P,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            exec: exec.into(),
+            mime_type: None,
+            description: None,
+            icon: None,
+        }
+    }
+
+    /// Gets the identifier of the app.
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    /// Gets the display name of the app.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the path to the executable.
+    pub fn get_exec(&self) -> &Path {
+        &self.exec
+    }
+
+    /// Gets the MIME type of the file type, if one was set.
+    pub fn get_mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+
+    /// Sets the MIME type of the file type, like `text/markdown`.
+    ///
+    /// # Arguments
+    /// * `mime_type` - The MIME type.
+    pub fn mime_type<S: Into<String>>(mut self, mime_type: S) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    /// Gets the description of the file type, if one was set.
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Sets the description of the file type shown by file managers, like `Markdown document`.
+    ///
+    /// # Arguments
+    /// * `description` - The description.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Gets the icon of the file type, if one was set.
+    pub fn get_icon(&self) -> Option<&Path> {
+        self.icon.as_deref()
+    }
+
+    /// Sets the icon of the file type. On Windows, this is an `.ico` file or an executable; on
+    /// Linux, an icon path or icon theme name; and on macOS, the name of an `.icns` file in the
+    /// bundle's resources.
+    ///
+    /// # Arguments
+    /// * `icon` - The icon.
+    pub fn icon<P: Into<PathBuf>>(mut self, icon: P) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    fn mime_type_for(&self, extension: &str) -> String {
+        self.mime_type
+            .clone()
+            .unwrap_or_else(|| format!("application/x-{}", extension))
+    }
+
+    fn description_for(&self, extension: &str) -> String {
+        self.description
+            .clone()
+            .unwrap_or_else(|| format!("{} {} file", self.name, extension.to_uppercase()))
+    }
+}
+
+/// Registers `app` as the default handler for files with `extension`, for the current user. See
+/// the [module documentation](self) for what this does on each platform. Registering the same app
+/// for several extensions is fine.
+///
+/// # Arguments
+///
+/// * `extension` - The file extension, with or without the leading dot.
+/// * `app` - The app to register.
+///
+/// # Errors
+///
+/// An error is returned if the registration could not be written or one of the helper programs on
+/// Linux fails, or on macOS and other unsupported platforms.
+///
+/// # Examples
+/// ```no_run
+/// use dablenutil::associations::{self, AppInfo};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let app = AppInfo::new("com.dablenparty.editor", "Editor", "/opt/editor/editor")
+///     .mime_type("text/x-editor-project")
+///     .description("Editor project");
+/// associations::register("edproj", &app)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn register(extension: &str, app: &AppInfo) -> crate::Result<()> {
+    let extension = extension.trim_start_matches('.');
+    #[cfg(windows)]
+    {
+        register_windows(extension, app)
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        register_xdg(extension, app)
+    }
+    #[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+    {
+        let _ = (extension, app);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "file types must be declared in Info.plist, see associations::info_plist_entry",
+        )
+        .into())
+    }
+}
+
+#[cfg(windows)]
+fn register_windows(extension: &str, app: &AppInfo) -> crate::Result<()> {
+    use crate::registry::{self, Hive};
+    use windows_sys::Win32::UI::Shell::{SHChangeNotify, SHCNF_IDLIST};
+
+    const SHCNE_ASSOCCHANGED: i32 = 0x0800_0000;
+    const CLASSES: &str = r"Software\Classes";
+
+    let prog_id = format!("{}.{}", app.id, extension);
+    let extension_key = format!(r"{}\.{}", CLASSES, extension);
+    registry::write_string(Hive::CurrentUser, &extension_key, "", &prog_id)?;
+    registry::write_string(
+        Hive::CurrentUser,
+        &format!(r"{}\OpenWithProgids", extension_key),
+        &prog_id,
+        "",
+    )?;
+    let prog_id_key = format!(r"{}\{}", CLASSES, prog_id);
+    registry::write_string(
+        Hive::CurrentUser,
+        &prog_id_key,
+        "",
+        &app.description_for(extension),
+    )?;
+    if let Some(icon) = &app.icon {
+        registry::write_string(
+            Hive::CurrentUser,
+            &format!(r"{}\DefaultIcon", prog_id_key),
+            "",
+            &icon.to_string_lossy(),
+        )?;
+    }
+    registry::write_string(
+        Hive::CurrentUser,
+        &format!(r"{}\shell\open\command", prog_id_key),
+        "",
+        &format!("\"{}\" \"%1\"", app.exec.display()),
+    )?;
+    // SAFETY: SHCNE_ASSOCCHANGED takes no items, so both may be null
+    unsafe {
+        SHChangeNotify(
+            SHCNE_ASSOCCHANGED,
+            SHCNF_IDLIST,
+            std::ptr::null(),
+            std::ptr::null(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn register_xdg(extension: &str, app: &AppInfo) -> crate::Result<()> {
+    let data_dir = crate::xdg_dir("XDG_DATA_HOME", ".local/share")?;
+    let mime_type = app.mime_type_for(extension);
+
+    let applications = data_dir.join("applications");
+    fs::create_dir_all(&applications)?;
+    let desktop_name = format!("{}.desktop", app.id);
+    let desktop_path = applications.join(&desktop_name);
+    // keep the MIME types of earlier registrations
+    let mut mime_types: Vec<String> = fs::read_to_string(&desktop_path)
+        .ok()
+        .and_then(|entry| {
+            entry.lines().find_map(|line| {
+                line.strip_prefix("MimeType=").map(|types| {
+                    types
+                        .split(';')
+                        .filter(|t| !t.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+            })
+        })
+        .unwrap_or_default();
+    if !mime_types.contains(&mime_type) {
+        mime_types.push(mime_type.clone());
+    }
+    fs::write(&desktop_path, desktop_entry(app, &mime_types))?;
+
+    let mime_dir = data_dir.join("mime");
+    let packages = mime_dir.join("packages");
+    fs::create_dir_all(&packages)?;
+    fs::write(
+        packages.join(format!("{}-{}.xml", app.id, extension)),
+        mime_package(extension, &mime_type, &app.description_for(extension)),
+    )?;
+
+    run_helper(Command::new("update-mime-database").arg(&mime_dir))?;
+    run_helper(Command::new("update-desktop-database").arg(&applications))?;
+    run_helper(
+        Command::new("xdg-mime")
+            .args(["default", &desktop_name])
+            .arg(&mime_type),
+    )
+}
+
+/// Builds the `.desktop` entry for `app`.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop_entry(app: &AppInfo, mime_types: &[String]) -> String {
+    let mut entry = format!(
+        r"[Desktop Entry]
+Type=Application
+Name={}
+Exec={} %f
+MimeType={};
+Terminal=false
+",
+        app.name,
+        crate::desktop_exec_arg(&app.exec.to_string_lossy()),
+        mime_types.join(";")
+    );
+    if let Some(icon) = &app.icon {
+        entry.push_str("Icon=");
+        entry.push_str(&icon.to_string_lossy());
+        entry.push('\n');
+    }
+    entry
+}
+
+/// Builds a shared MIME-info package mapping `extension` to `mime_type`.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn mime_package(extension: &str, mime_type: &str, description: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<mime-info xmlns="http://www.freedesktop.org/standards/shared-mime-info">
+  <mime-type type="{}">
+    <comment>{}</comment>
+    <glob pattern="*.{}"/>
+  </mime-type>
+</mime-info>
+"#,
+        crate::xml_escape(mime_type),
+        crate::xml_escape(description),
+        crate::xml_escape(extension)
+    )
+}
+
+/// Runs a desktop database helper. Helpers which aren't installed are skipped, since the files
+/// they index are picked up on the next login anyway.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn run_helper(command: &mut Command) -> crate::Result<()> {
+    match command.output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(io::Error::other(format!(
+            "{} failed with {}: {}",
+            command.get_program().to_string_lossy(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Builds the `CFBundleDocumentTypes` entry declaring `app` as the owner of files with
+/// `extension`, for the `Info.plist` of a macOS app bundle. The entry is a `<dict>`, to be placed
+/// in the `<array>` of the `CFBundleDocumentTypes` key.
+///
+/// # Arguments
+///
+/// * `extension` - The file extension, with or without the leading dot.
+/// * `app` - The app declaring the file type.
+///
+/// # Examples
+/// ```
+/// use dablenutil::associations::{info_plist_entry, AppInfo};
+///
+/// let app = AppInfo::new("com.dablenparty.editor", "Editor", "Editor.app")
+///     .mime_type("text/x-editor-project");
+/// let entry = info_plist_entry(".edproj", &app);
+/// assert!(entry.contains("<string>edproj</string>"));
+/// assert!(entry.contains("<string>Editor EDPROJ file</string>"));
+/// assert!(entry.contains("<string>text/x-editor-project</string>"));
+/// ```
+pub fn info_plist_entry(extension: &str, app: &AppInfo) -> String {
+    let extension = extension.trim_start_matches('.');
+    let mut entry = format!(
+        r"<dict>
+  <key>CFBundleTypeExtensions</key>
+  <array>
+    <string>{}</string>
+  </array>
+  <key>CFBundleTypeMIMETypes</key>
+  <array>
+    <string>{}</string>
+  </array>
+  <key>CFBundleTypeName</key>
+  <string>{}</string>
+  <key>CFBundleTypeRole</key>
+  <string>Editor</string>
+  <key>LSHandlerRank</key>
+  <string>Owner</string>
+",
+        crate::xml_escape(extension),
+        crate::xml_escape(&app.mime_type_for(extension)),
+        crate::xml_escape(&app.description_for(extension))
+    );
+    if let Some(icon) = &app.icon {
+        let icon = icon.file_name().unwrap_or(icon.as_os_str());
+        entry.push_str("  <key>CFBundleTypeIconFile</key>\n  <string>");
+        entry.push_str(&crate::xml_escape(&icon.to_string_lossy()));
+        entry.push_str("</string>\n");
+    }
+    entry.push_str("</dict>\n");
+    entry
+}
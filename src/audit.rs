@@ -0,0 +1,316 @@
+//! An audit trail of user-visible actions, like deleted files or changed settings, kept apart from
+//! the diagnostic log. This module is only available when the `audit` feature is enabled.
+//!
+//! Records are appended to a file as JSON lines:
+//!
+//! ```json
+//! {"prev":"…","seq":1,"time":1700000000,"action":"file_deleted","details":{"path":"world.zip"},"hash":"…"}
+//! ```
+//!
+//! `time` is in seconds since the Unix epoch, and every detail is a string. Each record is
+//! hash-chained to the one before it: `hash` is the SHA-256 digest of the record without its
+//! `hash` field, and `prev` is the `hash` of the previous record, or 64 zeros for the first one.
+//! Editing, removing, or reordering records breaks the chain, which [`verify`] detects. The chain
+//! continues across rotated files.
+
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::hash::sha256;
+
+/// The `prev` of the first record in a chain.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Appends hash-chained audit records to a file. Only one `Logger` should write to a file at a
+/// time, since each one tracks the end of the chain itself.
+///
+/// # Examples
+/// ```
+/// use dablenutil::audit::{self, Logger};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let path = std::env::temp_dir().join("dablenutil_audit.log");
+/// # let _ = std::fs::remove_file(&path);
+/// let mut logger = Logger::open(&path)?;
+/// logger.record("file_deleted", &[("path", &"world.zip")])?;
+/// logger.record("setting_changed", &[("key", &"volume"), ("value", &11)])?;
+/// assert_eq!(audit::verify(&path)?, 2);
+///
+/// let tampered = std::fs::read_to_string(&path)?.replace("world.zip", "other.zip");
+/// std::fs::write(&path, tampered)?;
+/// assert!(audit::verify(&path).is_err());
+/// # std::fs::remove_file(&path)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Logger {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    last_hash: String,
+    seq: u64,
+    max_size: Option<u64>,
+    max_files: usize,
+}
+
+impl Logger {
+    /// Opens an audit log, continuing the chain of any records already in it. The log is never
+    /// rotated until [`max_size`](Logger::max_size) is set.
+    /// The default values are:
+    /// * `max_size`: `None`
+    /// * `max_files`: `5`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the log file. Rotated files get `.1`, `.2`, and so on appended.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the log could not be opened or read, or its last record is
+    /// malformed.
+    pub fn open<P: Into<PathBuf>>(path: P) -> crate::Result<Self> {
+        let path = path.into();
+        crate::ensure_parent_dir(&path)?;
+        // a fresh log continues the chain of the newest rotated one
+        let (last_hash, seq) = match last_record(&path)? {
+            Some(last) => last,
+            None => last_record(&rotated_path(&path, 1))?.unwrap_or((GENESIS.to_string(), 0)),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            last_hash,
+            seq,
+            max_size: None,
+            max_files: 5,
+        })
+    }
+
+    /// Gets the path of the log file.
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the size the log is rotated at, if set.
+    pub fn get_max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
+    /// Sets the size in bytes the log file is rotated at. This is independent of the diagnostic
+    /// log, since audit trails usually need keeping for longer.
+    ///
+    /// # Arguments
+    /// * `max_size` - The maximum size of the log file.
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Gets the number of rotated files kept.
+    pub fn get_max_files(&self) -> usize {
+        self.max_files
+    }
+
+    /// Sets the number of rotated files kept. Older ones are deleted.
+    ///
+    /// # Arguments
+    /// * `max_files` - The number of rotated files to keep.
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Appends a record, rotating the log first if it has reached its maximum size.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - What happened, like `file_deleted`.
+    /// * `details` - Named details of the action, recorded as strings.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the log could not be rotated or written.
+    pub fn record(
+        &mut self,
+        action: &str,
+        details: &[(&str, &dyn fmt::Display)],
+    ) -> crate::Result<()> {
+        if self.max_size.is_some_and(|max_size| self.size >= max_size) {
+            self.rotate()?;
+        }
+        let details = details
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "{}:{}",
+                    crate::json_string(name),
+                    crate::json_string(&value.to_string())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let seq = self.seq + 1;
+        let body = format!(
+            "{{\"prev\":\"{}\",\"seq\":{},\"time\":{},\"action\":{},\"details\":{{{}}}}}",
+            self.last_hash,
+            seq,
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            crate::json_string(action),
+            details
+        );
+        let hash = sha256(&body);
+        let line = format!("{},\"hash\":\"{}\"}}\n", &body[..body.len() - 1], hash);
+        // one write per record, so a crash can't leave half a record followed by another
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        self.seq = seq;
+        self.last_hash = hash;
+        Ok(())
+    }
+
+    /// Shifts the rotated files up by one, deleting the oldest, and starts a new log file.
+    fn rotate(&mut self) -> crate::Result<()> {
+        if self.max_files == 0 {
+            crate::remove_file_if_exists(&self.path)?;
+        } else {
+            crate::remove_file_if_exists(&rotated_path(&self.path, self.max_files))?;
+            for n in (1..self.max_files).rev() {
+                match fs::rename(rotated_path(&self.path, n), rotated_path(&self.path, n + 1)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Verifies the hash chain of an audit log and its rotated files, oldest first, and returns the
+/// number of records verified. The first remaining record may follow records which were rotated
+/// away, so its `prev` isn't checked.
+///
+/// # Arguments
+///
+/// * `path` - The path of the log file, as passed to [`Logger::open`].
+///
+/// # Errors
+///
+/// An `InvalidData` error naming the first bad record is returned if a record is malformed, was
+/// modified, or is out of order. An error is also returned if a file could not be read.
+pub fn verify(path: &Path) -> crate::Result<u64> {
+    let mut files = Vec::new();
+    for n in 1.. {
+        let rotated = rotated_path(path, n);
+        if !rotated.try_exists()? {
+            break;
+        }
+        files.push(rotated);
+    }
+    files.reverse();
+    files.push(path.to_path_buf());
+
+    let mut last: Option<(String, u64)> = None;
+    let mut count = 0;
+    for file in files {
+        let reader = match File::open(&file) {
+            Ok(reader) => BufReader::new(reader),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let invalid = |reason: &str| -> crate::Error {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", file.display(), i + 1, reason),
+                )
+                .into()
+            };
+            let record = Record::parse(&line).ok_or_else(|| invalid("malformed record"))?;
+            if sha256(&record.body) != record.hash {
+                return Err(invalid("record was modified"));
+            }
+            if let Some((last_hash, last_seq)) = &last {
+                if record.prev != last_hash || record.seq != last_seq + 1 {
+                    return Err(invalid("record is out of order"));
+                }
+            }
+            last = Some((record.hash.to_string(), record.seq));
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// The parts of a record needed to follow the chain.
+struct Record<'a> {
+    prev: &'a str,
+    seq: u64,
+    body: String,
+    hash: &'a str,
+}
+
+impl<'a> Record<'a> {
+    /// Splits a line into its parts, or returns `None` if it is malformed.
+    fn parse(line: &'a str) -> Option<Self> {
+        let rest = line.strip_prefix("{\"prev\":\"")?;
+        let prev = rest.get(..64)?;
+        let rest = rest.get(64..)?.strip_prefix("\",\"seq\":")?;
+        let seq_len = rest.find(|c: char| !c.is_ascii_digit())?;
+        let seq = rest[..seq_len].parse().ok()?;
+        let without_end = line.strip_suffix("\"}")?;
+        let hash_start = without_end.len().checked_sub(64)?;
+        let hash = without_end.get(hash_start..)?;
+        let fields = without_end[..hash_start].strip_suffix(",\"hash\":\"")?;
+        Some(Self {
+            prev,
+            seq,
+            body: format!("{}}}", fields),
+            hash,
+        })
+    }
+}
+
+/// Reads the hash and sequence number of the last record of a log file, if it exists and has any.
+fn last_record(path: &Path) -> crate::Result<Option<(String, u64)>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let Some(line) = contents.lines().rev().find(|line| !line.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let record = Record::parse(line).ok_or_else(|| -> crate::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: malformed last record", path.display()),
+        )
+        .into()
+    })?;
+    Ok(Some((record.hash.to_string(), record.seq)))
+}
+
+/// Gets the path of the `n`th rotated file.
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
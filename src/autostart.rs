@@ -0,0 +1,243 @@
+//! Starts an application when the user logs in, for tray utilities offering "start with system".
+//! This module is only available when the `autostart` feature is enabled.
+//!
+//! How [`enable`] does this depends on the platform:
+//! * On Windows, it adds a value to the
+//!   `HKEY_CURRENT_USER\Software\Microsoft\Windows\CurrentVersion\Run` key.
+//! * On macOS, it writes a launch agent to `~/Library/LaunchAgents`.
+//! * On Linux and other Unix desktops, it writes a `.desktop` entry to
+//!   `$XDG_CONFIG_HOME/autostart`.
+//!
+//! Everything is set up for the current user only, so no elevated rights are needed.
+//!
+//! # Examples
+//! ```no_run
+//! use dablenutil::autostart::{self, App};
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! let app = App::new("com.dablenparty.tray", "Tray", "/opt/tray/tray").args(["--minimized"]);
+//! if !autostart::is_enabled(&app)? {
+//!     autostart::enable(&app)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+#[cfg(not(windows))]
+use std::{fs, io};
+
+/// The application to start at login.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct App {
+    id: String,
+    name: String,
+    exec: PathBuf,
+    args: Vec<String>,
+}
+
+impl App {
+    /// Constructs a new `App` with the default values for the optional fields.
+    /// The default values are:
+    /// * `args`: `[]`
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - A unique, reverse-DNS identifier for the app, like `com.dablenparty.tray`. This
+    ///   names the registry value, launch agent, or `.desktop` entry.
+    /// * `name` - The display name of the app.
+    /// * `exec` - The path to the executable.
+    pub fn new<I: Into<String>, N: Into<String>, P: Into<PathBuf>>(
+        id: I,
+        name: N,
+        exec: P,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            exec: exec.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Gets the identifier of the app.
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    /// Gets the display name of the app.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the path to the executable.
+    pub fn get_exec(&self) -> &Path {
+        &self.exec
+    }
+
+    /// Gets the arguments the app is started with.
+    pub fn get_args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Sets the arguments the app is started with, like `--minimized`.
+    ///
+    /// # Arguments
+    /// * `args` - The arguments.
+    pub fn args<I: IntoIterator<Item = S>, S: Into<String>>(mut self, args: I) -> Self {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+#[cfg(windows)]
+const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+/// Starts `app` when the current user logs in, replacing any earlier entry with the same ID.
+///
+/// # Arguments
+///
+/// * `app` - The app to start.
+///
+/// # Errors
+///
+/// An error is returned if the entry could not be written.
+///
+/// # Examples
+/// ```
+/// use dablenutil::autostart::{self, App};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// # #[cfg(all(unix, not(target_os = "macos")))]
+/// # {
+/// let config_home = std::env::temp_dir().join("dablenutil_autostart_enable");
+/// # let _ = fs::remove_dir_all(&config_home);
+/// std::env::set_var("XDG_CONFIG_HOME", &config_home);
+/// let app = App::new("com.dablenparty.tray", "Tray", "/opt/tray/tray").args(["--volume=50%"]);
+/// autostart::enable(&app)?;
+/// let entry = fs::read_to_string(config_home.join("autostart/com.dablenparty.tray.desktop"))?;
+/// assert!(entry.contains(r#"Exec="/opt/tray/tray" "--volume=50%%""#));
+/// # fs::remove_dir_all(&config_home)?;
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+pub fn enable(app: &App) -> crate::Result<()> {
+    #[cfg(windows)]
+    {
+        use crate::{
+            registry::{self, Hive},
+            service::quote_arg,
+        };
+
+        let mut command = quote_arg(&app.exec.to_string_lossy());
+        for arg in &app.args {
+            command.push(' ');
+            command.push_str(&quote_arg(arg));
+        }
+        registry::write_string(Hive::CurrentUser, RUN_KEY, &app.id, &command)
+    }
+    #[cfg(not(windows))]
+    {
+        let path = entry_path(app)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, entry(app))?;
+        Ok(())
+    }
+}
+
+/// Stops `app` from starting when the current user logs in. Does nothing if it wasn't enabled.
+///
+/// # Arguments
+///
+/// * `app` - The app to stop starting.
+///
+/// # Errors
+///
+/// An error is returned if the entry exists but could not be removed.
+pub fn disable(app: &App) -> crate::Result<()> {
+    #[cfg(windows)]
+    {
+        use crate::registry::{self, Hive};
+
+        registry::delete_value(Hive::CurrentUser, RUN_KEY, &app.id)?;
+        Ok(())
+    }
+    #[cfg(not(windows))]
+    {
+        match fs::remove_file(entry_path(app)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Checks if `app` starts when the current user logs in. Only the entry written by [`enable`] is
+/// checked, not whether it still points at the same executable.
+///
+/// # Arguments
+///
+/// * `app` - The app to check.
+///
+/// # Errors
+///
+/// An error is returned if the entry could not be read.
+pub fn is_enabled(app: &App) -> crate::Result<bool> {
+    #[cfg(windows)]
+    {
+        use crate::registry::{self, Hive};
+
+        Ok(registry::read_string(Hive::CurrentUser, RUN_KEY, &app.id)?.is_some())
+    }
+    #[cfg(not(windows))]
+    {
+        Ok(entry_path(app)?.try_exists()?)
+    }
+}
+
+/// Gets the path of the launch agent for `app`.
+#[cfg(target_os = "macos")]
+fn entry_path(app: &App) -> io::Result<PathBuf> {
+    let home = std::env::home_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    Ok(home
+        .join("Library/LaunchAgents")
+        .join(format!("{}.plist", app.id)))
+}
+
+/// Builds the launch agent for `app`.
+#[cfg(target_os = "macos")]
+fn entry(app: &App) -> String {
+    crate::launchd_plist(&app.id, &app.exec, &app.args, "")
+}
+
+/// Gets the path of the autostart entry for `app`.
+#[cfg(all(not(windows), not(target_os = "macos")))]
+fn entry_path(app: &App) -> io::Result<PathBuf> {
+    Ok(crate::xdg_dir("XDG_CONFIG_HOME", ".config")?
+        .join("autostart")
+        .join(format!("{}.desktop", app.id)))
+}
+
+/// Builds the autostart entry for `app`.
+#[cfg(all(not(windows), not(target_os = "macos")))]
+fn entry(app: &App) -> String {
+    let exec = std::iter::once(app.exec.to_string_lossy())
+        .chain(app.args.iter().map(Into::into))
+        .map(|arg| crate::desktop_exec_arg(&arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name={}
+Exec={}
+Terminal=false
+X-GNOME-Autostart-enabled=true
+",
+        app.name, exec
+    )
+}
@@ -0,0 +1,131 @@
+//! Backoff policies deciding how long to wait between retries.
+//!
+//! Every policy implements [`Backoff`], so retry loops, downloads, and supervisors can accept any of
+//! them interchangeably.
+
+use std::time::Duration;
+
+use crate::rand_utils::Rng;
+
+/// A policy deciding how long to wait before each retry.
+pub trait Backoff {
+    /// Gets the delay before the given retry. The first retry is attempt 1; attempt 0 is treated
+    /// the same as attempt 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempt` - The number of the retry, starting at 1.
+    fn delay(&self, attempt: u32) -> Duration;
+}
+
+/// Waits the same amount of time before every retry.
+///
+/// # Examples
+/// ```
+/// use dablenutil::backoff::{Backoff, Fixed};
+/// use std::time::Duration;
+///
+/// let policy = Fixed(Duration::from_secs(2));
+/// assert_eq!(policy.delay(1), Duration::from_secs(2));
+/// assert_eq!(policy.delay(10), Duration::from_secs(2));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixed(pub Duration);
+
+impl Backoff for Fixed {
+    fn delay(&self, _attempt: u32) -> Duration {
+        self.0
+    }
+}
+
+/// Doubles the delay after every retry, starting at `base` and never exceeding `max`.
+///
+/// `jitter` is the fraction of each delay, from 0 to 1, which is randomized. With a jitter of 0.25,
+/// each delay is between 75% and 100% of the exponential delay. Jitter spreads out retries from
+/// many clients that failed at the same time.
+///
+/// # Examples
+/// ```
+/// use dablenutil::backoff::{Backoff, Exponential};
+/// use std::time::Duration;
+///
+/// let policy = Exponential {
+///     base: Duration::from_millis(100),
+///     max: Duration::from_secs(1),
+///     jitter: 0.0,
+/// };
+/// assert_eq!(policy.delay(1), Duration::from_millis(100));
+/// assert_eq!(policy.delay(3), Duration::from_millis(400));
+/// assert_eq!(policy.delay(10), Duration::from_secs(1));
+///
+/// let jittered = Exponential { jitter: 0.5, ..policy };
+/// let delay = jittered.delay(3);
+/// assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exponential {
+    /// The delay before the first retry.
+    pub base: Duration,
+    /// The maximum delay.
+    pub max: Duration,
+    /// The fraction of each delay which is randomized, from 0 to 1.
+    pub jitter: f64,
+}
+
+impl Backoff for Exponential {
+    fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.max(1) - 1;
+        let delay = 2u32
+            .checked_pow(exponent)
+            .and_then(|factor| self.base.checked_mul(factor))
+            .map_or(self.max, |delay| delay.min(self.max));
+        apply_jitter(delay, self.jitter)
+    }
+}
+
+/// Grows the delay along the Fibonacci sequence (1, 1, 2, 3, 5, ... times `base`), never exceeding
+/// `max`. This grows slower than [`Exponential`].
+///
+/// # Examples
+/// ```
+/// use dablenutil::backoff::{Backoff, Fibonacci};
+/// use std::time::Duration;
+///
+/// let policy = Fibonacci {
+///     base: Duration::from_secs(1),
+///     max: Duration::from_secs(10),
+/// };
+/// let delays: Vec<u64> = (1..=7).map(|n| policy.delay(n).as_secs()).collect();
+/// assert_eq!(delays, [1, 1, 2, 3, 5, 8, 10]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fibonacci {
+    /// The delay before the first and second retry.
+    pub base: Duration,
+    /// The maximum delay.
+    pub max: Duration,
+}
+
+impl Backoff for Fibonacci {
+    fn delay(&self, attempt: u32) -> Duration {
+        let (mut current, mut next) = (1u32, 1u32);
+        for _ in 1..attempt.max(1) {
+            (current, next) = (next, current.saturating_add(next));
+            if current == u32::MAX {
+                break;
+            }
+        }
+        self.base
+            .checked_mul(current)
+            .map_or(self.max, |delay| delay.min(self.max))
+    }
+}
+
+/// Randomly shortens `delay` by up to `jitter` (clamped to `[0, 1]`) of its length.
+fn apply_jitter(delay: Duration, jitter: f64) -> Duration {
+    if jitter.is_nan() || jitter <= 0.0 {
+        return delay;
+    }
+    let jitter = jitter.min(1.0);
+    delay.mul_f64(1.0 - jitter * Rng::new().next_f64())
+}
@@ -0,0 +1,124 @@
+//! Micro-benchmarking helpers for quick, in-binary performance checks.
+//!
+//! These are no substitute for a proper harness like `criterion`, but are handy for comparing two
+//! implementations of a code path without setting one up.
+
+use std::{
+    fmt,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+/// Statistics about the run times of a benchmarked function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchStats {
+    /// The number of measured iterations, not counting warmup.
+    pub iterations: usize,
+    /// The mean run time.
+    pub mean: Duration,
+    /// The median run time.
+    pub median: Duration,
+    /// The standard deviation of the run times.
+    pub stddev: Duration,
+    /// The fastest run time.
+    pub min: Duration,
+    /// The slowest run time.
+    pub max: Duration,
+}
+
+impl fmt::Display for BenchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mean {:?} ± {:?}, median {:?}, range {:?}..{:?} ({} iterations)",
+            self.mean, self.stddev, self.median, self.min, self.max, self.iterations
+        )
+    }
+}
+
+/// Runs `f` `iterations` times and measures how long each run takes. Before measuring, `f` is run
+/// a tenth as many times (at least once) to warm up caches. Return values are passed through
+/// [`black_box`] so the compiler can't optimize the work away.
+///
+/// # Arguments
+///
+/// * `iterations` - The number of runs to measure. 0 is treated as 1.
+/// * `f` - The function to benchmark.
+///
+/// # Examples
+/// ```
+/// use dablenutil::bench;
+///
+/// let stats = bench::measure(100, || (0..1000u64).sum::<u64>());
+/// assert_eq!(stats.iterations, 100);
+/// assert!(stats.min <= stats.median && stats.median <= stats.max);
+/// ```
+#[allow(clippy::cast_precision_loss)]
+pub fn measure<T, F: FnMut() -> T>(iterations: usize, mut f: F) -> BenchStats {
+    let iterations = iterations.max(1);
+    for _ in 0..(iterations / 10).max(1) {
+        black_box(f());
+    }
+    let mut samples: Vec<Duration> = (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .collect();
+    samples.sort_unstable();
+    let count = iterations as f64;
+    let mean = samples.iter().map(Duration::as_secs_f64).sum::<f64>() / count;
+    let variance = samples
+        .iter()
+        .map(|sample| (sample.as_secs_f64() - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    let middle = iterations / 2;
+    let median = if iterations.is_multiple_of(2) {
+        (samples[middle - 1] + samples[middle]) / 2
+    } else {
+        samples[middle]
+    };
+    BenchStats {
+        iterations,
+        mean: Duration::try_from_secs_f64(mean).unwrap_or_default(),
+        median,
+        stddev: Duration::try_from_secs_f64(variance.sqrt()).unwrap_or_default(),
+        min: samples[0],
+        max: samples[iterations - 1],
+    }
+}
+
+/// Prints a small report comparing two benchmarks to stdout, and returns how many times faster
+/// `b` is than `a` by mean run time (less than 1 if it is slower).
+///
+/// # Arguments
+///
+/// * `a` - The baseline benchmark.
+/// * `b` - The benchmark to compare against the baseline.
+///
+/// # Examples
+/// ```
+/// use dablenutil::bench;
+///
+/// let a = bench::measure(50, || (0..10_000u64).sum::<u64>());
+/// let b = bench::measure(50, || (0..10u64).sum::<u64>());
+/// let speedup = bench::compare(&a, &b);
+/// assert!(speedup > 0.0);
+/// ```
+pub fn compare(a: &BenchStats, b: &BenchStats) -> f64 {
+    let speedup = if b.mean.is_zero() {
+        f64::INFINITY
+    } else {
+        a.mean.as_secs_f64() / b.mean.as_secs_f64()
+    };
+    println!("a: {}", a);
+    println!("b: {}", b);
+    if speedup >= 1.0 {
+        println!("b is {:.2}x faster than a", speedup);
+    } else {
+        println!("b is {:.2}x slower than a", speedup.recip());
+    }
+    speedup
+}
@@ -0,0 +1,358 @@
+//! Exports an app's configuration, data, and optionally logs into a single `.tar.gz` bundle, and
+//! imports it again, for "back up my settings" and moving to another machine. This module is only
+//! available when the `bundle` feature is enabled.
+//!
+//! A bundle holds a `manifest.txt` describing it, followed by one top-level directory per section:
+//! `config`, `data`, and `logs`. The manifest has one key and value per line, separated by a tab:
+//! `format`, `created` (in seconds since the Unix epoch), and a `section` line per section.
+//!
+//! `format` is the version of the bundle layout, which [`import`] checks so a bundle from a newer
+//! version of this crate is rejected instead of half-imported.
+//!
+//! # Examples
+//! ```
+//! use dablenutil::bundle::{self, AppDirs, ImportOptions, Section};
+//! use std::fs;
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! let dir = std::env::temp_dir().join("dablenutil_bundle");
+//! # let _ = fs::remove_dir_all(&dir);
+//! let old = AppDirs::new(dir.join("old/config"), dir.join("old/data"));
+//! fs::create_dir_all(&old.config_dir)?;
+//! fs::write(old.config_dir.join("settings.toml"), "volume = 11")?;
+//!
+//! let manifest = bundle::export(&old, &dir.join("backup.tar.gz"))?;
+//! assert_eq!(manifest.sections, [Section::Config]);
+//!
+//! let new = AppDirs::new(dir.join("new/config"), dir.join("new/data"));
+//! bundle::import(&dir.join("backup.tar.gz"), &new, &ImportOptions::new())?;
+//! assert_eq!(fs::read_to_string(new.config_dir.join("settings.toml"))?, "volume = 11");
+//! # fs::remove_dir_all(&dir)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::{
+    fs_utils::{copy_dir_recursive, CopyOptions, Overwrite},
+    install::TargetPaths,
+    temp::{TempDir, TempFile},
+};
+
+/// The version of the bundle layout written by [`export`].
+pub const FORMAT: u32 = 1;
+
+/// The name of the manifest in a bundle.
+const MANIFEST: &str = "manifest.txt";
+
+/// The directories of an app which go into a bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AppDirs {
+    /// The directory for the app's configuration.
+    pub config_dir: PathBuf,
+    /// The directory for the app's data.
+    pub data_dir: PathBuf,
+    /// The directory for the app's logs, which are only bundled if this is set.
+    pub log_dir: Option<PathBuf>,
+}
+
+impl AppDirs {
+    /// Constructs a new `AppDirs` without a log directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_dir` - The directory for the app's configuration.
+    /// * `data_dir` - The directory for the app's data.
+    pub fn new<C: Into<PathBuf>, D: Into<PathBuf>>(config_dir: C, data_dir: D) -> Self {
+        Self {
+            config_dir: config_dir.into(),
+            data_dir: data_dir.into(),
+            log_dir: None,
+        }
+    }
+
+    /// Sets the directory for the app's logs, so they are bundled too.
+    ///
+    /// # Arguments
+    /// * `log_dir` - The log directory.
+    pub fn log_dir<P: Into<PathBuf>>(mut self, log_dir: P) -> Self {
+        self.log_dir = Some(log_dir.into());
+        self
+    }
+
+    /// Gets the directory for a section, if the app has one.
+    fn get(&self, section: Section) -> Option<&Path> {
+        match section {
+            Section::Config => Some(&self.config_dir),
+            Section::Data => Some(&self.data_dir),
+            Section::Logs => self.log_dir.as_deref(),
+        }
+    }
+}
+
+impl From<TargetPaths> for AppDirs {
+    fn from(paths: TargetPaths) -> Self {
+        Self::new(paths.config_dir, paths.data_dir)
+    }
+}
+
+/// A part of a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Section {
+    /// The app's configuration.
+    Config,
+    /// The app's data.
+    Data,
+    /// The app's logs.
+    Logs,
+}
+
+impl Section {
+    /// Every section, in the order they are bundled.
+    const ALL: [Section; 3] = [Section::Config, Section::Data, Section::Logs];
+
+    /// Gets the name of the section, which is also its directory in the bundle.
+    pub fn name(self) -> &'static str {
+        match self {
+            Section::Config => "config",
+            Section::Data => "data",
+            Section::Logs => "logs",
+        }
+    }
+}
+
+/// The manifest of a bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Manifest {
+    /// The version of the bundle layout.
+    pub format: u32,
+    /// When the bundle was created, in seconds since the Unix epoch.
+    pub created: u64,
+    /// The sections in the bundle.
+    pub sections: Vec<Section>,
+}
+
+impl Manifest {
+    /// Formats the manifest as stored in a bundle.
+    fn to_text(&self) -> String {
+        let mut text = format!("format\t{}\ncreated\t{}\n", self.format, self.created);
+        for section in &self.sections {
+            text.push_str("section\t");
+            text.push_str(section.name());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parses a manifest, ignoring unknown keys and sections so newer minor additions still load.
+    fn parse(text: &str) -> io::Result<Self> {
+        let mut format = None;
+        let mut created = 0;
+        let mut sections = Vec::new();
+        for line in text.lines() {
+            match line.split_once('\t') {
+                Some(("format", value)) => format = value.parse().ok(),
+                Some(("created", value)) => created = value.parse().unwrap_or(0),
+                Some(("section", value)) => {
+                    sections.extend(Section::ALL.into_iter().find(|s| s.name() == value));
+                }
+                _ => {}
+            }
+        }
+        let format = format
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bundle has no format"))?;
+        Ok(Self {
+            format,
+            created,
+            sections,
+        })
+    }
+}
+
+/// Options for [`import`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImportOptions {
+    sections: Vec<Section>,
+    overwrite: Overwrite,
+}
+
+impl ImportOptions {
+    /// Constructs a new `ImportOptions` with the default values.
+    /// The default values are:
+    /// * `sections`: `[Section::Config, Section::Data]`
+    /// * `overwrite`: `Overwrite::Always`
+    pub fn new() -> Self {
+        Self {
+            sections: vec![Section::Config, Section::Data],
+            overwrite: Overwrite::Always,
+        }
+    }
+
+    /// Gets the sections to import.
+    pub fn get_sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Sets the sections to import. Sections missing from the bundle are skipped.
+    ///
+    /// # Arguments
+    /// * `sections` - The sections to import.
+    pub fn sections<I: IntoIterator<Item = Section>>(mut self, sections: I) -> Self {
+        self.sections = sections.into_iter().collect();
+        self
+    }
+
+    /// Gets what happens to existing files.
+    pub fn get_overwrite(&self) -> Overwrite {
+        self.overwrite
+    }
+
+    /// Sets what happens to existing files in the app's directories.
+    ///
+    /// # Arguments
+    /// * `overwrite` - What to do with existing files.
+    pub fn overwrite(mut self, overwrite: Overwrite) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bundles an app's directories into a `.tar.gz` file. Directories which don't exist are left out.
+/// The bundle is written atomically, so a failed export never leaves a partial bundle at `dest`.
+///
+/// # Arguments
+///
+/// * `dirs` - The app's directories.
+/// * `dest` - The path of the bundle, usually ending in `.tar.gz`.
+///
+/// # Errors
+///
+/// An error is returned if a directory could not be read or the bundle could not be written.
+pub fn export(dirs: &AppDirs, dest: &Path) -> crate::Result<Manifest> {
+    let mut sections = Vec::new();
+    for section in Section::ALL {
+        if let Some(dir) = dirs.get(section) {
+            if dir.is_dir() {
+                sections.push(section);
+            }
+        }
+    }
+    let manifest = Manifest {
+        format: FORMAT,
+        created: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+        sections,
+    };
+
+    let parent = match dest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let temp = TempFile::new_in(parent, ".bundle")?;
+    let mut builder = tar::Builder::new(GzEncoder::new(temp, Compression::default()));
+    let text = manifest.to_text();
+    let mut header = tar::Header::new_gnu();
+    header.set_size(text.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST, text.as_bytes())?;
+    for &section in &manifest.sections {
+        if let Some(dir) = dirs.get(section) {
+            builder.append_dir_all(section.name(), dir)?;
+        }
+    }
+    let mut temp = builder.into_inner()?.finish()?;
+    temp.flush()?;
+    temp.persist(dest)?;
+    Ok(manifest)
+}
+
+/// Reads the manifest of a bundle without importing it, to show what it contains.
+///
+/// # Arguments
+///
+/// * `src` - The path of the bundle.
+///
+/// # Errors
+///
+/// An error is returned if the bundle could not be read or has no valid manifest.
+pub fn read_manifest(src: &Path) -> crate::Result<Manifest> {
+    let mut archive = open(src)?;
+    let mut entries = archive.entries()?;
+    let mut entry = entries
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bundle is empty"))??;
+    if entry.path()?.as_ref() != Path::new(MANIFEST) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "bundle has no manifest").into());
+    }
+    let mut text = String::new();
+    entry.read_to_string(&mut text)?;
+    Ok(Manifest::parse(&text)?)
+}
+
+/// Imports a bundle into an app's directories. The bundle is extracted to a temporary directory
+/// first, so a corrupt bundle is detected before anything is changed. Entries with absolute paths
+/// or `..` components are skipped.
+///
+/// # Arguments
+///
+/// * `src` - The path of the bundle.
+/// * `dirs` - The app's directories. Logs are only imported if `log_dir` is set.
+/// * `options` - Which sections to import and how to treat existing files.
+///
+/// # Errors
+///
+/// An error is returned if the bundle could not be read, is from a newer bundle format, or could
+/// not be copied into the app's directories.
+pub fn import(src: &Path, dirs: &AppDirs, options: &ImportOptions) -> crate::Result<Manifest> {
+    let manifest = read_manifest(src)?;
+    if manifest.format > FORMAT {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "bundle format {} is newer than the supported format {}",
+                manifest.format, FORMAT
+            ),
+        )
+        .into());
+    }
+    let staging = TempDir::new("dablenutil-bundle")?;
+    open(src)?.unpack(staging.path())?;
+    for &section in &options.sections {
+        if !manifest.sections.contains(&section) {
+            continue;
+        }
+        let Some(dest) = dirs.get(section) else {
+            continue;
+        };
+        let from = staging.path().join(section.name());
+        if from.is_dir() {
+            let copy_options = CopyOptions::new().overwrite(options.overwrite);
+            copy_dir_recursive(&from, dest, &copy_options)?;
+        }
+    }
+    Ok(manifest)
+}
+
+/// Opens a bundle for reading.
+fn open(src: &Path) -> io::Result<tar::Archive<GzDecoder<BufReader<File>>>> {
+    Ok(tar::Archive::new(GzDecoder::new(BufReader::new(
+        File::open(src)?,
+    ))))
+}
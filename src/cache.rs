@@ -0,0 +1,178 @@
+//! Cache directories which are invalidated when the app changes.
+//!
+//! A [`Versioned`] cache stores the version it was written by. Opening it with a different version
+//! wipes it, or runs a migration first, so an update never reads entries in an outdated format.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::fs_utils::atomic_write;
+
+/// The file in the cache directory storing its version.
+const VERSION_FILE: &str = ".cache-version";
+
+/// A cache directory stamped with the version of the app or cache format that wrote it.
+///
+/// # Examples
+/// ```
+/// use dablenutil::cache::Versioned;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_cache_versioned");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// let cache = Versioned::open(&dir, "1")?;
+/// std::fs::write(cache.join("index.json"), "[]")?;
+///
+/// let cache = Versioned::open(&dir, "1")?;
+/// assert!(!cache.was_reset());
+/// assert!(cache.join("index.json").exists());
+///
+/// let cache = Versioned::open(&dir, "2")?;
+/// assert!(cache.was_reset());
+/// assert!(!cache.join("index.json").exists());
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Versioned {
+    dir: PathBuf,
+    version: String,
+    was_reset: bool,
+}
+
+impl Versioned {
+    /// Opens the cache in `dir`, creating it if needed. If it was written by another version, or
+    /// its version is unknown, everything in it is deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The cache directory.
+    /// * `version` - The current version, like a schema number or `env!("CARGO_PKG_VERSION")`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the cache could not be read, wiped, or stamped.
+    pub fn open<P: Into<PathBuf>>(dir: P, version: &str) -> crate::Result<Self> {
+        Self::open_with_migration(dir, version, |_, _| Ok(false))
+    }
+
+    /// Opens the cache in `dir` like [`open`](Versioned::open), but when it was written by another
+    /// known version, `migrate` gets a chance to upgrade it in place instead of it being wiped.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The cache directory.
+    /// * `version` - The current version.
+    /// * `migrate` - Called with the cache directory and the stored version. Returns `true` if it
+    ///   migrated the cache, or `false` to wipe it. The cache is also wiped if it returns an error,
+    ///   since its contents are in an unknown state.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the cache could not be read, wiped, or stamped.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::cache::Versioned;
+    ///
+    /// # fn main() -> dablenutil::Result<()> {
+    /// let dir = std::env::temp_dir().join("dablenutil_cache_migration");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let cache = Versioned::open(&dir, "1")?;
+    /// std::fs::write(cache.join("index.txt"), "a,b")?;
+    ///
+    /// let cache = Versioned::open_with_migration(&dir, "2", |dir, old| {
+    ///     if old != "1" {
+    ///         return Ok(false);
+    ///     }
+    ///     let index = std::fs::read_to_string(dir.join("index.txt"))?;
+    ///     std::fs::write(dir.join("index.txt"), index.replace(',', "\n"))?;
+    ///     Ok(true)
+    /// })?;
+    /// assert!(!cache.was_reset());
+    /// assert_eq!(std::fs::read_to_string(cache.join("index.txt"))?, "a\nb");
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open_with_migration<P, F>(dir: P, version: &str, migrate: F) -> crate::Result<Self>
+    where
+        P: Into<PathBuf>,
+        F: FnOnce(&Path, &str) -> crate::Result<bool>,
+    {
+        let dir = dir.into();
+        let stamp = dir.join(VERSION_FILE);
+        let stored = match fs::read_to_string(&stamp) {
+            Ok(stored) => Some(stored.trim().to_string()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let mut cache = Self {
+            dir,
+            version: version.to_string(),
+            was_reset: false,
+        };
+        match stored.as_deref() {
+            Some(stored) if stored == version => {}
+            Some(stored) if matches!(migrate(&cache.dir, stored), Ok(true)) => cache.stamp()?,
+            _ => {
+                // a missing stamp means a new cache, or one written before it was versioned
+                cache.was_reset = stored.is_some() || has_entries(&cache.dir)?;
+                cache.clear()?;
+            }
+        }
+        Ok(cache)
+    }
+
+    /// Gets the cache directory.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Joins a path relative to the cache directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The relative path.
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.dir.join(path)
+    }
+
+    /// Gets the current version of the cache.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Checks if opening the cache deleted an existing cache written by another version.
+    pub fn was_reset(&self) -> bool {
+        self.was_reset
+    }
+
+    /// Deletes everything in the cache, keeping it stamped with the current version.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the cache could not be wiped or stamped.
+    pub fn clear(&self) -> crate::Result<()> {
+        crate::remove_dir_if_exists(&self.dir)?;
+        self.stamp()
+    }
+
+    /// Writes the current version to the cache directory, creating it if needed.
+    fn stamp(&self) -> crate::Result<()> {
+        crate::create_dir_if_not_exists(&self.dir)?;
+        atomic_write(&self.dir.join(VERSION_FILE), &self.version, false)
+    }
+}
+
+/// Checks if a directory exists and has anything in it.
+fn has_entries(dir: &Path) -> io::Result<bool> {
+    match fs::read_dir(dir) {
+        Ok(mut entries) => Ok(entries.next().is_some()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,346 @@
+//! Reusable `clap` arguments, so every CLI wires its logging and output flags the same way, plus
+//! helpers for generating shell completions and man pages. This module is only available when the
+//! `clap` feature is enabled.
+//!
+//! Each struct is meant to be flattened into an application's own arguments:
+//!
+//! ```
+//! use clap::Parser;
+//! use dablenutil::cli::{ColorArgs, JsonOutputArg, LogDirArgs, VerbosityArgs};
+//!
+//! #[derive(Parser)]
+//! struct Cli {
+//!     #[command(flatten)]
+//!     verbosity: VerbosityArgs,
+//!     #[command(flatten)]
+//!     log_dir: LogDirArgs,
+//!     #[command(flatten)]
+//!     color: ColorArgs,
+//!     #[command(flatten)]
+//!     json: JsonOutputArg,
+//! }
+//!
+//! let cli = Cli::parse_from(["app", "-vv", "--log-dir", "/tmp/logs", "--json"]);
+//! assert_eq!(cli.verbosity.verbose, 2);
+//! assert!(cli.json.json);
+//! ```
+
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, Command, ValueEnum};
+use clap_complete::Shell;
+
+use crate::{create_dir_if_not_exists, dry_run::Mode, ensure_parent_dir};
+
+#[cfg(feature = "logging")]
+use crate::logging::LoggingConfig;
+#[cfg(feature = "logging")]
+use log::LevelFilter;
+
+/// `-v`/`--verbose` and `-q`/`--quiet` flags, which can be repeated to raise or lower the log
+/// level from the default of info.
+#[derive(Args, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerbosityArgs {
+    /// Log more; repeat for even more (-vv)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Log less; repeat for even less (-qq)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true, conflicts_with = "verbose")]
+    pub quiet: u8,
+}
+
+#[cfg(feature = "logging")]
+impl VerbosityArgs {
+    /// Gets the log level selected by the flags. Each `-v` raises the level from info to debug,
+    /// then trace; each `-q` lowers it to warn, error, then off.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::cli::VerbosityArgs;
+    /// use log::LevelFilter;
+    ///
+    /// let args = VerbosityArgs { verbose: 1, quiet: 0 };
+    /// assert_eq!(args.level_filter(), LevelFilter::Debug);
+    /// let args = VerbosityArgs { verbose: 0, quiet: 5 };
+    /// assert_eq!(args.level_filter(), LevelFilter::Off);
+    /// ```
+    pub fn level_filter(&self) -> LevelFilter {
+        const LEVELS: [LevelFilter; 6] = [
+            LevelFilter::Off,
+            LevelFilter::Error,
+            LevelFilter::Warn,
+            LevelFilter::Info,
+            LevelFilter::Debug,
+            LevelFilter::Trace,
+        ];
+        let index = (3 + usize::from(self.verbose)).saturating_sub(usize::from(self.quiet));
+        LEVELS[index.min(LEVELS.len() - 1)]
+    }
+
+    /// Applies the selected log level to the terminal output of `config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The `LoggingConfig` to update.
+    pub fn apply(&self, config: LoggingConfig) -> LoggingConfig {
+        config.term_level_filter(self.level_filter())
+    }
+}
+
+#[cfg(feature = "logging")]
+impl From<&VerbosityArgs> for LevelFilter {
+    fn from(args: &VerbosityArgs) -> Self {
+        args.level_filter()
+    }
+}
+
+/// A `--log-dir` option for the folder logs are written to, defaulting to `logs`.
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
+pub struct LogDirArgs {
+    /// The folder to write logs to
+    #[arg(long, value_name = "DIR", default_value = "logs", global = true)]
+    pub log_dir: PathBuf,
+}
+
+impl Default for LogDirArgs {
+    fn default() -> Self {
+        Self {
+            log_dir: PathBuf::from("logs"),
+        }
+    }
+}
+
+#[cfg(feature = "logging")]
+impl From<&LogDirArgs> for LoggingConfig {
+    /// Constructs a `LoggingConfig` writing to the selected log folder.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::{cli::LogDirArgs, logging::LoggingConfig};
+    /// use std::path::Path;
+    ///
+    /// let config = LoggingConfig::from(&LogDirArgs::default());
+    /// assert_eq!(config.get_log_folder(), Path::new("logs"));
+    /// ```
+    fn from(args: &LogDirArgs) -> Self {
+        LoggingConfig::new(args.log_dir.clone())
+    }
+}
+
+/// When to use colored output.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorWhen {
+    /// Use color if stdout is a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    /// Always use color.
+    Always,
+    /// Never use color.
+    Never,
+}
+
+/// A `--color` option choosing when to use colored output.
+#[derive(Args, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorArgs {
+    /// When to use colored output
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorWhen::Auto, global = true)]
+    pub color: ColorWhen,
+}
+
+impl ColorArgs {
+    /// Returns `true` if output should be colored. In auto mode, this checks whether stdout is a
+    /// terminal and honors the `NO_COLOR` environment variable.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::cli::{ColorArgs, ColorWhen};
+    ///
+    /// assert!(ColorArgs { color: ColorWhen::Always }.use_color());
+    /// assert!(!ColorArgs { color: ColorWhen::Never }.use_color());
+    /// ```
+    pub fn use_color(&self) -> bool {
+        match self.color {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "logging")]
+impl From<&ColorArgs> for simplelog::ColorChoice {
+    fn from(args: &ColorArgs) -> Self {
+        match args.color {
+            ColorWhen::Auto => simplelog::ColorChoice::Auto,
+            ColorWhen::Always => simplelog::ColorChoice::Always,
+            ColorWhen::Never => simplelog::ColorChoice::Never,
+        }
+    }
+}
+
+/// A `--json` flag for machine-readable output.
+#[derive(Args, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonOutputArg {
+    /// Print machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    pub json: bool,
+}
+
+/// A `--dry-run` flag for commands which copy, move, or delete files.
+#[derive(Args, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DryRunArg {
+    /// Show what would be done without changing anything
+    #[arg(short = 'n', long, global = true)]
+    pub dry_run: bool,
+}
+
+impl DryRunArg {
+    /// Gets the [`Mode`] selected by the flag.
+    ///
+    /// # Examples
+    /// ```
+    /// use clap::Parser;
+    /// use dablenutil::{cli::DryRunArg, dry_run::Mode};
+    ///
+    /// #[derive(Parser)]
+    /// struct Cli {
+    ///     #[command(flatten)]
+    ///     dry_run: DryRunArg,
+    /// }
+    ///
+    /// assert_eq!(Cli::parse_from(["app", "-n"]).dry_run.mode(), Mode::DryRun);
+    /// assert_eq!(Cli::parse_from(["app"]).dry_run.mode(), Mode::Execute);
+    /// ```
+    pub fn mode(self) -> Mode {
+        if self.dry_run {
+            Mode::DryRun
+        } else {
+            Mode::Execute
+        }
+    }
+}
+
+impl From<&DryRunArg> for Mode {
+    fn from(arg: &DryRunArg) -> Self {
+        arg.mode()
+    }
+}
+
+/// Generates shell completions for `cmd` into `out_dir`, using the layout packages install them
+/// with under `/usr/share` (or `/usr/local/share`), so passing one of those installs them in the
+/// standard locations. Only the shells used on the current platform are generated:
+///
+/// * Bash: `bash-completion/completions/{name}`
+/// * Zsh: `zsh/site-functions/_{name}`
+/// * Fish: `fish/vendor_completions.d/{name}.fish`
+/// * Elvish: `elvish/lib/{name}.elv`
+/// * PowerShell (Windows only): `powershell/{name}.ps1`
+///
+/// Returns the paths of the generated files.
+///
+/// # Arguments
+///
+/// * `cmd` - The command to generate completions for.
+/// * `out_dir` - The directory to write the completions to.
+///
+/// # Errors
+///
+/// An error is returned if a directory or file could not be created.
+///
+/// # Examples
+/// ```
+/// use dablenutil::cli::generate_completions;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let out_dir = std::env::temp_dir().join("dablenutil_generate_completions");
+/// let cmd = clap::Command::new("app").arg(clap::Arg::new("verbose").long("verbose"));
+/// let files = generate_completions(&cmd, &out_dir)?;
+/// assert!(files.iter().all(|file| file.exists()));
+/// # if cfg!(unix) {
+/// assert!(files.contains(&out_dir.join("zsh/site-functions/_app")));
+/// # }
+/// # std::fs::remove_dir_all(&out_dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn generate_completions<P: AsRef<Path>>(
+    cmd: &Command,
+    out_dir: P,
+) -> crate::Result<Vec<PathBuf>> {
+    let out_dir = out_dir.as_ref();
+    let name = cmd
+        .get_bin_name()
+        .unwrap_or_else(|| cmd.get_name())
+        .to_string();
+    let targets = if cfg!(windows) {
+        vec![(Shell::PowerShell, format!("powershell/{}.ps1", name))]
+    } else {
+        vec![
+            (Shell::Bash, format!("bash-completion/completions/{}", name)),
+            (Shell::Zsh, format!("zsh/site-functions/_{}", name)),
+            (
+                Shell::Fish,
+                format!("fish/vendor_completions.d/{}.fish", name),
+            ),
+            (Shell::Elvish, format!("elvish/lib/{}.elv", name)),
+        ]
+    };
+    let mut files = Vec::with_capacity(targets.len());
+    for (shell, relative_path) in targets {
+        let path = out_dir.join(relative_path);
+        ensure_parent_dir(&path)?;
+        let mut file = io::BufWriter::new(fs::File::create(&path)?);
+        clap_complete::generate(shell, &mut cmd.clone(), &name, &mut file);
+        io::Write::flush(&mut file)?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// Generates man pages for `cmd` and its subcommands into `out_dir/man/man1`, the layout used
+/// under `/usr/share` (or `/usr/local/share`). Subcommands get their own pages, named
+/// `{name}-{subcommand}.1`.
+///
+/// Returns the path of the page for `cmd` itself.
+///
+/// # Arguments
+///
+/// * `cmd` - The command to generate man pages for.
+/// * `out_dir` - The directory to write the man pages to.
+///
+/// # Errors
+///
+/// An error is returned if a directory or file could not be created.
+///
+/// # Examples
+/// ```
+/// use dablenutil::cli::generate_man;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let out_dir = std::env::temp_dir().join("dablenutil_generate_man");
+/// let cmd = clap::Command::new("app").subcommand(clap::Command::new("sync"));
+/// let page = generate_man(&cmd, &out_dir)?;
+/// assert_eq!(page, out_dir.join("man/man1/app.1"));
+/// assert!(page.exists());
+/// assert!(out_dir.join("man/man1/app-sync.1").exists());
+/// # std::fs::remove_dir_all(&out_dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn generate_man<P: AsRef<Path>>(cmd: &Command, out_dir: P) -> crate::Result<PathBuf> {
+    let man_dir = out_dir.as_ref().join("man").join("man1");
+    create_dir_if_not_exists(&man_dir)?;
+    let name = cmd
+        .get_bin_name()
+        .unwrap_or_else(|| cmd.get_name())
+        .to_string();
+    clap_mangen::generate_to(cmd.clone(), &man_dir)?;
+    Ok(man_dir.join(format!("{}.1", name)))
+}
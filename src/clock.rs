@@ -0,0 +1,42 @@
+//! A source of time which tests can control.
+//!
+//! Code which schedules, expires, or prunes things by age takes a [`Clock`] instead of calling
+//! `Instant::now` or `SystemTime::now`, so tests can swap in the `MockClock` from the `testutil`
+//! module (with the `test-util` feature).
+
+use std::time::{Duration, Instant, SystemTime};
+
+/// A source of time. Code which schedules, expires, or prunes things by age should take a `Clock`
+/// instead of calling `Instant::now` or `SystemTime::now`, so tests can control it.
+pub trait Clock {
+    /// Gets the current monotonic time.
+    fn now(&self) -> Instant;
+
+    /// Gets the current wall-clock time, for comparing with file timestamps.
+    fn system_time(&self) -> SystemTime;
+
+    /// Blocks for `duration`.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - How long to sleep.
+    fn sleep(&self, duration: Duration);
+}
+
+/// The real [`Clock`], backed by the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
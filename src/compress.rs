@@ -0,0 +1,111 @@
+//! Gzip compression of single files, such as log archives. This module is only available when the
+//! `compress` feature is enabled.
+//!
+//! Files are streamed, so large files are never fully loaded into memory.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+};
+
+use flate2::{read::MultiGzDecoder, Compression, GzBuilder};
+
+use crate::temp::TempFile;
+
+/// Compresses a file with gzip. The original file name is stored in the gzip header, like the
+/// `gzip` tool does. `dest` is written atomically, so a failure never leaves a partial file there.
+/// Returns the size of the compressed file.
+///
+/// # Arguments
+///
+/// * `src` - The file to compress. It is left in place.
+/// * `dest` - The path of the compressed file, usually `src` with `.gz` appended. It is
+///   overwritten if it exists.
+/// * `level` - The compression level, from 0 (none) to 9 (smallest). Higher levels are clamped
+///   to 9; 6 is the default of the `gzip` tool.
+///
+/// # Errors
+///
+/// An error is returned if `src` could not be read or `dest` could not be written.
+///
+/// # Examples
+/// ```
+/// use dablenutil::compress::{gunzip_file, gzip_file};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_gzip_file");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// fs::write(dir.join("world.json"), "{}".repeat(1000))?;
+///
+/// let size = gzip_file(&dir.join("world.json"), &dir.join("world.json.gz"), 9)?;
+/// assert!(size < 2000);
+/// gunzip_file(&dir.join("world.json.gz"), &dir.join("restored.json"))?;
+/// assert_eq!(fs::read_to_string(dir.join("restored.json"))?, "{}".repeat(1000));
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn gzip_file(src: &Path, dest: &Path, level: u32) -> crate::Result<u64> {
+    let name = src
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    let reader = File::open(src)?;
+    let temp = TempFile::new_in(parent_or_current(dest), ".gz")?;
+    let temp = gzip_stream(reader, temp, level, name.as_deref())?;
+    let size = temp.as_file().metadata()?.len();
+    temp.persist(dest)?;
+    Ok(size)
+}
+
+/// Decompresses a gzip file. Files made of several concatenated gzip members, as produced by
+/// appending to a `.gz` file, are decompressed in full. `dest` is written atomically, so a
+/// failure, such as a truncated archive, never leaves a partial file there. Returns the size of
+/// the decompressed file.
+///
+/// # Arguments
+///
+/// * `src` - The gzip file to decompress. It is left in place.
+/// * `dest` - The path of the decompressed file. It is overwritten if it exists.
+///
+/// # Errors
+///
+/// An error is returned if `src` could not be read or isn't valid gzip, or `dest` could not be
+/// written.
+pub fn gunzip_file(src: &Path, dest: &Path) -> crate::Result<u64> {
+    let mut decoder = MultiGzDecoder::new(BufReader::new(File::open(src)?));
+    let mut temp = TempFile::new_in(parent_or_current(dest), ".gunzip")?;
+    let size = io::copy(&mut decoder, &mut temp)?;
+    temp.flush()?;
+    temp.persist(dest)?;
+    Ok(size)
+}
+
+/// Streams `reader` through a gzip encoder into `writer`, and returns the writer once the gzip
+/// trailer is written.
+pub(crate) fn gzip_stream<R: Read, W: Write>(
+    mut reader: R,
+    writer: W,
+    level: u32,
+    filename: Option<&str>,
+) -> io::Result<W> {
+    let mut builder = GzBuilder::new();
+    if let Some(filename) = filename {
+        builder = builder.filename(filename);
+    }
+    let mut encoder = builder.write(writer, Compression::new(level.min(9)));
+    io::copy(&mut reader, &mut encoder)?;
+    let mut writer = encoder.finish()?;
+    writer.flush()?;
+    Ok(writer)
+}
+
+/// Gets the directory a file is in, or `.` for a bare file name.
+fn parent_or_current(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
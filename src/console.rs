@@ -0,0 +1,62 @@
+//! Logs to the browser console. This module is only available when the `wasm` feature is enabled
+//! and compiling for `wasm32`, where the file-based loggers in `logging` can't work.
+//!
+//! Error, warn, and info records go to `console.error`, `console.warn`, and `console.info`, and
+//! debug and trace records go to `console.debug`, so the browser's own level filter applies.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use wasm_bindgen::JsValue;
+use web_sys::console;
+
+/// A logger writing to the browser console.
+struct ConsoleLogger {
+    level: LevelFilter,
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = JsValue::from_str(&format!("[{}] {}", record.target(), record.args()));
+        match record.level() {
+            Level::Error => console::error_1(&message),
+            Level::Warn => console::warn_1(&message),
+            Level::Info => console::info_1(&message),
+            Level::Debug | Level::Trace => console::debug_1(&message),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Sets the global logger to one writing to the browser console.
+///
+/// # Arguments
+///
+/// * `level` - The most verbose level to log.
+///
+/// # Errors
+///
+/// An error is returned if a logger was already set.
+///
+/// # Examples
+/// ```no_run
+/// use dablenutil::console::init_console_logger;
+/// use log::LevelFilter;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// init_console_logger(LevelFilter::Debug)?;
+/// log::info!("Hello from the browser!");
+/// # Ok(())
+/// # }
+/// ```
+pub fn init_console_logger(level: LevelFilter) -> crate::Result<()> {
+    log::set_boxed_logger(Box::new(ConsoleLogger { level }))?;
+    log::set_max_level(level);
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Cryptographic utilities. Only available when the `crypto` feature is enabled.
+//!
+//! Currently, this module contains a function to verify [minisign](https://jedisct1.github.io/minisign/)
+//! signatures of files, such as downloaded release artifacts.
+
+use std::{
+    fs,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use minisign_verify::{PublicKey, Signature};
+
+/// Verifies the minisign (ed25519) signature of a file.
+///
+/// The file is streamed in chunks, so large artifacts are never fully loaded into memory. Only
+/// pre-hashed signatures (the default since minisign 0.8) are accepted; legacy signatures are
+/// rejected.
+///
+/// # Arguments
+///
+/// * `file` - The path to the file to verify.
+/// * `signature` - The contents of the `.minisig` signature file.
+/// * `public_key` - Either the base64-encoded public key or the full contents of a `.pub` key file.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be read, the signature or public key could not be
+/// decoded, or the signature does not match the file.
+///
+/// # Examples
+///
+/// ```
+/// use dablenutil::crypto::verify_signature;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let public_key = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+/// let signature = "untrusted comment: signature from minisign secret key
+/// RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+/// trusted comment: timestamp:1556193335\tfile:test
+/// y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+/// let file = std::env::temp_dir().join("dablenutil_verify_signature.txt");
+/// std::fs::write(&file, "test")?;
+/// verify_signature(&file, signature, public_key)?;
+/// std::fs::write(&file, "tampered")?;
+/// assert!(verify_signature(&file, signature, public_key).is_err());
+/// # std::fs::remove_file(&file)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify_signature(file: &Path, signature: &str, public_key: &str) -> crate::Result<()> {
+    let public_key = if public_key.trim().contains('\n') {
+        PublicKey::decode(public_key)?
+    } else {
+        PublicKey::from_base64(public_key.trim())?
+    };
+    let signature = Signature::decode(signature)?;
+    let mut verifier = public_key.verify_stream(&signature)?;
+    let mut reader = BufReader::new(fs::File::open(file)?);
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        verifier.update(&buf[..read]);
+    }
+    verifier.finalize()?;
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! Support for `--dry-run` in destructive operations.
+//!
+//! Operations which copy, move, or delete files take a [`Plan`]. In [`Mode::Execute`], each action
+//! is performed and recorded; in [`Mode::DryRun`], it is only recorded. Either way, the plan lists
+//! what was (or would have been) done, so a CLI can print it the same way in both modes.
+//!
+//! # Examples
+//! ```
+//! use dablenutil::dry_run::{Action, Mode, Plan};
+//! use std::{fs, path::PathBuf};
+//!
+//! fn clean(files: &[PathBuf], plan: &mut Plan) -> dablenutil::Result<()> {
+//!     for file in files {
+//!         plan.perform(Action::Remove(file.clone()), || fs::remove_file(file))?;
+//!     }
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! let file = std::env::temp_dir().join("dablenutil_dry_run.txt");
+//! fs::write(&file, "keep me")?;
+//! let mut plan = Plan::new(Mode::DryRun);
+//! clean(&[file.clone()], &mut plan)?;
+//! assert!(file.exists());
+//! assert_eq!(plan.actions(), [Action::Remove(file.clone())]);
+//! println!("{}", plan);
+//! # fs::remove_file(&file)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{fmt, io, path::PathBuf};
+
+/// Whether destructive operations are performed or only planned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Perform every action.
+    #[default]
+    Execute,
+    /// Record every action without performing it.
+    DryRun,
+}
+
+impl Mode {
+    /// Checks if this is [`Mode::DryRun`].
+    pub fn is_dry_run(self) -> bool {
+        self == Mode::DryRun
+    }
+}
+
+/// An action taken by a destructive operation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Creates a directory.
+    CreateDir(PathBuf),
+    /// Copies a file.
+    Copy {
+        /// The file to copy.
+        from: PathBuf,
+        /// The destination.
+        to: PathBuf,
+    },
+    /// Moves or renames a file or directory.
+    Move {
+        /// The path to move.
+        from: PathBuf,
+        /// The destination.
+        to: PathBuf,
+    },
+    /// Removes a file.
+    Remove(PathBuf),
+    /// Removes a directory and everything in it.
+    RemoveDir(PathBuf),
+    /// Compresses a file into an archive and removes the original.
+    Archive {
+        /// The file to archive.
+        source: PathBuf,
+        /// The archive to create.
+        archive: PathBuf,
+    },
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::CreateDir(path) => write!(f, "create directory {}", path.display()),
+            Action::Copy { from, to } => {
+                write!(f, "copy {} to {}", from.display(), to.display())
+            }
+            Action::Move { from, to } => {
+                write!(f, "move {} to {}", from.display(), to.display())
+            }
+            Action::Remove(path) => write!(f, "remove {}", path.display()),
+            Action::RemoveDir(path) => write!(f, "remove directory {}", path.display()),
+            Action::Archive { source, archive } => {
+                write!(f, "archive {} to {}", source.display(), archive.display())
+            }
+        }
+    }
+}
+
+/// The actions taken, or planned, by destructive operations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    mode: Mode,
+    actions: Vec<Action>,
+}
+
+impl Plan {
+    /// Constructs a new, empty `Plan`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Whether actions are performed or only recorded.
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            actions: Vec::new(),
+        }
+    }
+
+    /// Gets the mode of the plan.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Gets the recorded actions, in order.
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Checks if no actions were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Records an action which was performed elsewhere, or is only planned in a dry run.
+    pub(crate) fn record(&mut self, action: Action) {
+        self.actions.push(action);
+    }
+
+    /// Records `action` and, unless this is a dry run, performs it by calling `f`. The action is
+    /// only recorded in [`Mode::Execute`] if `f` succeeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - A description of what `f` does.
+    /// * `f` - The function performing the action.
+    ///
+    /// # Errors
+    ///
+    /// The error returned by `f` is returned.
+    pub fn perform<T, F>(&mut self, action: Action, f: F) -> io::Result<Option<T>>
+    where
+        F: FnOnce() -> io::Result<T>,
+    {
+        let result = match self.mode {
+            Mode::Execute => Some(f()?),
+            Mode::DryRun => None,
+        };
+        self.actions.push(action);
+        Ok(result)
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = if self.mode.is_dry_run() { "would " } else { "" };
+        for action in &self.actions {
+            writeln!(f, "{}{}", prefix, action)?;
+        }
+        Ok(())
+    }
+}
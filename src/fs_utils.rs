@@ -0,0 +1,3251 @@
+//! Filesystem helpers built on `std::fs`, for the file juggling installers, backup tools, and game
+//! server managers keep reimplementing.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    dry_run::{Action, Mode, Plan},
+    preserve,
+    progress::{Progress, ProgressTracker},
+    temp::{TempDir, TempFile},
+};
+
+/// What [`copy_dir_recursive`] does when a destination file already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Overwrite {
+    /// Replace the existing file.
+    #[default]
+    Always,
+    /// Keep the existing file and skip the source file.
+    Never,
+    /// Replace the existing file only if the source file was modified more recently.
+    IfNewer,
+    /// Fail with an `AlreadyExists` error.
+    Error,
+}
+
+/// What [`copy_dir_recursive`] and [`dir_size`] do with symbolic links.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Symlinks {
+    /// Copy what the link points to. Directory links which lead back into a directory being copied
+    /// are skipped, so cycles terminate.
+    #[default]
+    Follow,
+    /// Recreate the link itself, pointing at the same target.
+    Preserve,
+    /// Leave links out of the copy.
+    Skip,
+}
+
+/// A filter called with paths relative to the root of a copy.
+type PathFilter = Box<dyn Fn(&Path) -> bool + Send + Sync>;
+
+/// A callback reporting the progress of a copy.
+type ProgressCallback = Box<dyn Fn(Progress) + Send + Sync>;
+
+/// Options for [`copy_dir_recursive`].
+pub struct CopyOptions {
+    overwrite: Overwrite,
+    symlinks: Symlinks,
+    preserve_metadata: bool,
+    mode: Mode,
+    exclude: Option<PathFilter>,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl CopyOptions {
+    /// Constructs a new `CopyOptions` with the default values.
+    /// The default values are:
+    /// * `overwrite`: `Overwrite::Always`
+    /// * `symlinks`: `Symlinks::Follow`
+    /// * `preserve_metadata`: `false`
+    /// * `mode`: `Mode::Execute`
+    /// * `exclude`: `None`
+    /// * `on_progress`: `None`
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::{
+    ///     dry_run::Mode,
+    ///     fs_utils::{CopyOptions, Overwrite, Symlinks},
+    /// };
+    ///
+    /// let options = CopyOptions::new();
+    /// assert_eq!(options.get_overwrite(), Overwrite::Always);
+    /// assert_eq!(options.get_symlinks(), Symlinks::Follow);
+    /// assert!(!options.get_preserve_metadata());
+    /// assert_eq!(options.get_mode(), Mode::Execute);
+    /// assert!(!options.is_excluded(std::path::Path::new("anything")));
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            overwrite: Overwrite::Always,
+            symlinks: Symlinks::Follow,
+            preserve_metadata: false,
+            mode: Mode::Execute,
+            exclude: None,
+            on_progress: None,
+        }
+    }
+
+    /// Gets what happens when a destination file already exists.
+    pub fn get_overwrite(&self) -> Overwrite {
+        self.overwrite
+    }
+
+    /// Sets what happens when a destination file already exists.
+    ///
+    /// # Arguments
+    /// * `overwrite` - The overwrite behavior.
+    pub fn overwrite(mut self, overwrite: Overwrite) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Gets how symbolic links are handled.
+    pub fn get_symlinks(&self) -> Symlinks {
+        self.symlinks
+    }
+
+    /// Sets how symbolic links are handled.
+    ///
+    /// # Arguments
+    /// * `symlinks` - The symbolic link behavior.
+    pub fn symlinks(mut self, symlinks: Symlinks) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Gets whether times, permissions, and ownership are copied (see [`preserve`]).
+    pub fn get_preserve_metadata(&self) -> bool {
+        self.preserve_metadata
+    }
+
+    /// Sets whether times, permissions, and ownership are copied (see [`preserve`]).
+    ///
+    /// # Arguments
+    /// * `preserve` - Whether to copy metadata.
+    pub fn preserve_metadata(mut self, preserve: bool) -> Self {
+        self.preserve_metadata = preserve;
+        self
+    }
+
+    /// Gets whether the copy is performed or only planned.
+    pub fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Sets whether the copy is performed or only planned. In [`Mode::DryRun`], nothing is
+    /// written, and the returned [`Plan`] lists what would have been copied.
+    ///
+    /// # Arguments
+    /// * `mode` - The mode.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets a filter for entries to leave out of the copy. It is called with the path of each
+    /// entry relative to the source directory; excluding a directory excludes everything in it.
+    ///
+    /// # Arguments
+    /// * `exclude` - Returns `true` for entries to leave out.
+    pub fn exclude<F: Fn(&Path) -> bool + Send + Sync + 'static>(mut self, exclude: F) -> Self {
+        self.exclude = Some(Box::new(exclude));
+        self
+    }
+
+    /// Checks if an entry is excluded by the [`exclude`](CopyOptions::exclude) filter.
+    ///
+    /// # Arguments
+    /// * `relative` - The path of the entry relative to the source directory.
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude(relative))
+    }
+
+    /// Sets a callback reporting the progress of the copy, called after each file is copied with
+    /// numbers from a [`ProgressTracker`]. To know the total size, the copy is planned once before
+    /// it starts. The callback is never called in [`Mode::DryRun`].
+    ///
+    /// # Arguments
+    /// * `on_progress` - Called with the progress through the total size of the files to copy.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::fs_utils::{copy_dir_recursive, CopyOptions};
+    /// use std::{
+    ///     fs,
+    ///     sync::{Arc, Mutex},
+    /// };
+    ///
+    /// # fn main() -> dablenutil::Result<()> {
+    /// let dir = std::env::temp_dir().join("dablenutil_copy_on_progress");
+    /// # let _ = fs::remove_dir_all(&dir);
+    /// fs::create_dir_all(dir.join("world/region"))?;
+    /// fs::write(dir.join("world/level.dat"), [0; 300])?;
+    /// fs::write(dir.join("world/region/r.0.0.mca"), [0; 700])?;
+    ///
+    /// let reported = Arc::new(Mutex::new(Vec::new()));
+    /// let options = CopyOptions::new().on_progress({
+    ///     let reported = Arc::clone(&reported);
+    ///     move |progress| reported.lock().unwrap().push((progress.done, progress.total))
+    /// });
+    /// copy_dir_recursive(&dir.join("world"), &dir.join("backup"), &options)?;
+    /// let reported = reported.lock().unwrap();
+    /// assert_eq!(reported.len(), 2);
+    /// assert_eq!(reported.last(), Some(&(1000, 1000)));
+    /// # fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_progress<F: Fn(Progress) + Send + Sync + 'static>(mut self, on_progress: F) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CopyOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CopyOptions")
+            .field("overwrite", &self.overwrite)
+            .field("symlinks", &self.symlinks)
+            .field("preserve_metadata", &self.preserve_metadata)
+            .field("mode", &self.mode)
+            .field("exclude", &self.exclude.is_some())
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+/// Reports the progress of a copy to [`CopyOptions::on_progress`].
+pub(crate) struct CopyProgress<'a> {
+    on_progress: &'a (dyn Fn(Progress) + Send + Sync),
+    tracker: ProgressTracker,
+    done: u64,
+}
+
+impl<'a> CopyProgress<'a> {
+    /// Gets the progress callback of `options`, unless the copy is a dry run.
+    pub(crate) fn callback(options: &CopyOptions) -> Option<&(dyn Fn(Progress) + Send + Sync)> {
+        options
+            .on_progress
+            .as_deref()
+            .filter(|_| !options.mode.is_dry_run())
+    }
+
+    /// Starts reporting the progress of a copy of `total` bytes to `on_progress`.
+    pub(crate) fn new(on_progress: &'a (dyn Fn(Progress) + Send + Sync), total: u64) -> Self {
+        Self {
+            on_progress,
+            tracker: ProgressTracker::new(total),
+            done: 0,
+        }
+    }
+
+    /// Reports that another `bytes` bytes were copied.
+    pub(crate) fn advance(&mut self, bytes: u64) {
+        self.done += bytes;
+        (self.on_progress)(self.tracker.update(self.done));
+    }
+}
+
+/// Sums the sizes of the files copied by `plan`, a dry run of a copy. Recreated links count as
+/// nothing.
+pub(crate) fn planned_copy_size(plan: &Plan, symlinks: Symlinks) -> io::Result<u64> {
+    let mut total = 0;
+    for action in plan.actions() {
+        if let Action::Copy { from, .. } = action {
+            if symlinks != Symlinks::Preserve || !fs::symlink_metadata(from)?.is_symlink() {
+                total += fs::metadata(from)?.len();
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Copies a directory and everything in it to `dst`, creating `dst` if needed. Existing
+/// directories are merged into, and existing files are handled according to
+/// [`CopyOptions::overwrite`]. Returns the [`Plan`] of what was copied.
+///
+/// # Arguments
+///
+/// * `src` - The directory to copy.
+/// * `dst` - The destination directory.
+/// * `options` - The options for the copy.
+///
+/// # Errors
+///
+/// An error is returned if `src` is not a directory, or if an entry could not be read or copied.
+/// Entries copied before the error are left in place. An `InvalidInput` error is returned if `dst`
+/// is `src` or inside it, which would copy the directory into itself.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::{copy_dir_recursive, CopyOptions, Overwrite};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_copy_dir_recursive");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("server/world"))?;
+/// fs::write(dir.join("server/world/level.dat"), "level")?;
+/// fs::write(dir.join("server/server.log"), "log")?;
+///
+/// let options = CopyOptions::new()
+///     .overwrite(Overwrite::Never)
+///     .exclude(|path| path.extension().is_some_and(|ext| ext == "log"));
+/// let plan = copy_dir_recursive(&dir.join("server"), &dir.join("backup"), &options)?;
+/// assert_eq!(fs::read_to_string(dir.join("backup/world/level.dat"))?, "level");
+/// assert!(!dir.join("backup/server.log").exists());
+/// assert_eq!(plan.actions().len(), 3);
+/// assert!(copy_dir_recursive(&dir.join("server"), &dir.join("server/backup"), &options).is_err());
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn copy_dir_recursive(src: &Path, dst: &Path, options: &CopyOptions) -> crate::Result<Plan> {
+    if !src.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            format!("{} is not a directory", src.display()),
+        )
+        .into());
+    }
+    ensure_outside(src, dst)?;
+    let mut progress = match CopyProgress::callback(options) {
+        Some(on_progress) => {
+            let mut planned = Plan::new(Mode::DryRun);
+            copy_dir_inner(
+                src,
+                dst,
+                Path::new(""),
+                options,
+                &mut planned,
+                &mut None,
+                &mut HashSet::new(),
+            )?;
+            let total = planned_copy_size(&planned, options.symlinks)?;
+            Some(CopyProgress::new(on_progress, total))
+        }
+        None => None,
+    };
+    let mut plan = Plan::new(options.mode);
+    let mut visited = HashSet::new();
+    copy_dir_inner(
+        src,
+        dst,
+        Path::new(""),
+        options,
+        &mut plan,
+        &mut progress,
+        &mut visited,
+    )?;
+    Ok(plan)
+}
+
+/// Fails with an `InvalidInput` error if `dst` is the directory `src` or inside it, comparing
+/// canonical paths so links and `..` components can't hide the overlap.
+pub(crate) fn ensure_outside(src: &Path, dst: &Path) -> crate::Result<()> {
+    if canonicalize_missing(dst)?.starts_with(fs::canonicalize(src)?) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is inside {}", dst.display(), src.display()),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Canonicalizes a path which may not exist yet: its deepest existing ancestor is canonicalized,
+/// and the missing components are appended to it.
+fn canonicalize_missing(path: &Path) -> io::Result<PathBuf> {
+    let path = std::path::absolute(path)?;
+    let mut existing = path.as_path();
+    loop {
+        match fs::canonicalize(existing) {
+            Ok(canonical) => {
+                // the missing components can't be links, so resolving them lexically is safe
+                let missing = path.strip_prefix(existing).unwrap_or(Path::new(""));
+                return Ok(normalize(&canonical.join(missing)));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => match existing.parent() {
+                Some(parent) => existing = parent,
+                None => return Err(e),
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Copies the contents of `src` into `dst`. `relative` is the path of `src` relative to the root
+/// of the copy, and `visited` holds the canonical paths of the directories being copied.
+fn copy_dir_inner(
+    src: &Path,
+    dst: &Path,
+    relative: &Path,
+    options: &CopyOptions,
+    plan: &mut Plan,
+    progress: &mut Option<CopyProgress>,
+    visited: &mut HashSet<PathBuf>,
+) -> crate::Result<()> {
+    let canonical = fs::canonicalize(src)?;
+    if !visited.insert(canonical.clone()) {
+        return Ok(());
+    }
+    if !dst.is_dir() {
+        plan.perform(Action::CreateDir(dst.to_path_buf()), || {
+            fs::create_dir_all(dst)
+        })?;
+    }
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let relative = relative.join(entry.file_name());
+        if options.is_excluded(&relative) {
+            continue;
+        }
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+        let mut file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            match options.symlinks {
+                Symlinks::Skip => continue,
+                Symlinks::Preserve => {
+                    copy_symlink(&from, &to, options, plan)?;
+                    continue;
+                }
+                Symlinks::Follow => match fs::metadata(&from) {
+                    Ok(metadata) => file_type = metadata.file_type(),
+                    // dangling links have nothing to copy
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                },
+            }
+        }
+        if file_type.is_dir() {
+            copy_dir_inner(&from, &to, &relative, options, plan, progress, visited)?;
+            if options.preserve_metadata && !plan.mode().is_dry_run() {
+                preserve::apply(&to, &preserve::capture(&from)?)?;
+            }
+        } else {
+            copy_file(&from, &to, options, plan, progress)?;
+        }
+    }
+    visited.remove(&canonical);
+    Ok(())
+}
+
+/// Copies a single file according to `options`.
+fn copy_file(
+    from: &Path,
+    to: &Path,
+    options: &CopyOptions,
+    plan: &mut Plan,
+    progress: &mut Option<CopyProgress>,
+) -> crate::Result<()> {
+    if let Ok(existing) = fs::symlink_metadata(to) {
+        match options.overwrite {
+            Overwrite::Always => {}
+            Overwrite::Never => return Ok(()),
+            Overwrite::IfNewer => {
+                let source = fs::metadata(from)?.modified()?;
+                if existing.modified()? >= source {
+                    return Ok(());
+                }
+            }
+            Overwrite::Error => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", to.display()),
+                )
+                .into())
+            }
+        }
+    }
+    let action = Action::Copy {
+        from: from.to_path_buf(),
+        to: to.to_path_buf(),
+    };
+    let copied = plan.perform(action, || fs::copy(from, to))?;
+    if options.preserve_metadata && !plan.mode().is_dry_run() {
+        preserve::apply(to, &preserve::capture(from)?)?;
+    }
+    if let (Some(progress), Some(copied)) = (progress, copied) {
+        progress.advance(copied);
+    }
+    Ok(())
+}
+
+/// Recreates the symbolic link `from` at `to`.
+fn copy_symlink(
+    from: &Path,
+    to: &Path,
+    options: &CopyOptions,
+    plan: &mut Plan,
+) -> crate::Result<()> {
+    let target = fs::read_link(from)?;
+    if fs::symlink_metadata(to).is_ok() {
+        match options.overwrite {
+            Overwrite::Never | Overwrite::IfNewer => return Ok(()),
+            Overwrite::Error => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", to.display()),
+                )
+                .into())
+            }
+            Overwrite::Always => {
+                plan.perform(Action::Remove(to.to_path_buf()), || fs::remove_file(to))?;
+            }
+        }
+    }
+    let action = Action::Copy {
+        from: from.to_path_buf(),
+        to: to.to_path_buf(),
+    };
+    plan.perform(action, || create_symlink(&target, to, false))?;
+    Ok(())
+}
+
+/// Moves a directory to `dst`. This tries [`fs::rename`] first, and if `src` and `dst` are on
+/// different filesystems or drives, falls back to copying the directory, with symbolic links and
+/// metadata preserved, into a temporary directory next to `dst`. The copy is then renamed to `dst`,
+/// so `dst` never holds a partial copy, and `src` is removed.
+///
+/// # Arguments
+///
+/// * `src` - The directory to move.
+/// * `dst` - The new path of the directory.
+///
+/// # Errors
+///
+/// An error is returned if the directory could not be renamed for any other reason, or if the
+/// fallback copy or removal fails. The fallback fails with an `AlreadyExists` error if `dst`
+/// exists, rather than merging into it. If the copy fails, it is removed and `src` is left
+/// untouched.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::move_dir;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_move_dir");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("download"))?;
+/// fs::write(dir.join("download/mod.jar"), "jar")?;
+///
+/// move_dir(&dir.join("download"), &dir.join("mods"))?;
+/// assert!(!dir.join("download").exists());
+/// assert_eq!(fs::read_to_string(dir.join("mods/mod.jar"))?, "jar");
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn move_dir(src: &Path, dst: &Path) -> crate::Result<()> {
+    move_dir_with_plan(src, dst, &mut Plan::new(Mode::Execute))
+}
+
+/// Moves a directory like [`move_dir`], recording the move in `plan` as a single
+/// [`Action::Move`]. In [`Mode::DryRun`], nothing is moved.
+///
+/// # Arguments
+///
+/// * `src` - The directory to move.
+/// * `dst` - The new path of the directory.
+/// * `plan` - The plan to record the move in.
+///
+/// # Errors
+///
+/// An error is returned in the same cases as [`move_dir`]. In a dry run, an error is only returned
+/// if `src` doesn't exist.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{
+///     dry_run::{Mode, Plan},
+///     fs_utils::move_dir_with_plan,
+/// };
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_move_dir_with_plan");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("download"))?;
+///
+/// let mut dry_run = Plan::new(Mode::DryRun);
+/// move_dir_with_plan(&dir.join("download"), &dir.join("mods"), &mut dry_run)?;
+/// assert_eq!(dry_run.actions().len(), 1);
+/// assert!(dir.join("download").exists());
+/// assert!(!dir.join("mods").exists());
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn move_dir_with_plan(src: &Path, dst: &Path, plan: &mut Plan) -> crate::Result<()> {
+    let action = Action::Move {
+        from: src.to_path_buf(),
+        to: dst.to_path_buf(),
+    };
+    if plan.mode().is_dry_run() {
+        // fail like the move would if there is nothing to move
+        fs::symlink_metadata(src)?;
+        plan.record(action);
+        return Ok(());
+    }
+    match fs::rename(src, dst) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => move_dir_by_copy(src, dst)?,
+        result => result?,
+    }
+    plan.record(action);
+    Ok(())
+}
+
+/// Moves a directory across filesystems by copying it into a temporary directory next to `dst`,
+/// renaming the copy into place, and then removing `src`.
+fn move_dir_by_copy(src: &Path, dst: &Path) -> crate::Result<()> {
+    if fs::symlink_metadata(dst).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", dst.display()),
+        )
+        .into());
+    }
+    let parent = match dst.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    // the temporary directory is removed on drop, taking a failed copy with it
+    let staging = TempDir::new_in(parent, ".dablenutil-move")?;
+    let staged = staging.path().join("dir");
+    let options = CopyOptions::new()
+        .symlinks(Symlinks::Preserve)
+        .preserve_metadata(true);
+    copy_dir_recursive(src, &staged, &options)?;
+    rename_no_replace(&staged, dst)?;
+    drop(staging);
+    fs::remove_dir_all(src)?;
+    Ok(())
+}
+
+/// Hard links `dst` to `src`, or copies `src` to `dst` where a link isn't possible: across
+/// filesystems or drives, on filesystems without hard links such as FAT, when the link count of
+/// `src` is at its maximum, or when the platform forbids the link. Links are instant and take no
+/// space, but changes to either path show up in both; only use them for files which are replaced
+/// rather than edited in place. Returns `true` if a link was made and `false` if `src` was copied.
+///
+/// # Arguments
+///
+/// * `src` - The file to link to.
+/// * `dst` - The path of the link or copy. It must not exist.
+///
+/// # Errors
+///
+/// An error is returned if `dst` already exists, `src` could not be read, or the fallback copy
+/// fails.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::link_or_copy;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_link_or_copy");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("cache"))?;
+/// fs::write(dir.join("cache/library.jar"), "jar")?;
+///
+/// link_or_copy(&dir.join("cache/library.jar"), &dir.join("library.jar"))?;
+/// assert_eq!(fs::read_to_string(dir.join("library.jar"))?, "jar");
+/// assert!(link_or_copy(&dir.join("cache/library.jar"), &dir.join("library.jar")).is_err());
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn link_or_copy(src: &Path, dst: &Path) -> crate::Result<bool> {
+    match fs::hard_link(src, dst) {
+        Ok(()) => Ok(true),
+        Err(e) if link_refused(&e) => {
+            fs::copy(src, dst)?;
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Checks if [`fs::hard_link`] failed because a link can't be made here, rather than because of
+/// the paths themselves.
+fn link_refused(error: &io::Error) -> bool {
+    // FAT and exFAT report ERROR_INVALID_FUNCTION for hard links
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::Foundation::ERROR_INVALID_FUNCTION;
+        if error.raw_os_error() == Some(ERROR_INVALID_FUNCTION as i32) {
+            return true;
+        }
+    }
+    matches!(
+        error.kind(),
+        io::ErrorKind::CrossesDevices
+            | io::ErrorKind::Unsupported
+            | io::ErrorKind::TooManyLinks
+            | io::ErrorKind::PermissionDenied
+    )
+}
+
+/// Renames `from` to `to`, failing with an `AlreadyExists` error instead of replacing anything at
+/// `to`. Renaming a file to a name differing only in case, on a case-insensitive filesystem, is
+/// allowed. The check and the rename are separate steps, so a file created in between is still
+/// replaced.
+pub(crate) fn rename_no_replace(from: &Path, to: &Path) -> io::Result<()> {
+    if let Ok(existing) = fs::symlink_metadata(to) {
+        if !is_same_file(&fs::symlink_metadata(from)?, &existing, from, to) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", to.display()),
+            ));
+        }
+    }
+    fs::rename(from, to)
+}
+
+/// Checks if the entries at `a` and `b`, with the given metadata, are the same entry.
+fn is_same_file(a_metadata: &fs::Metadata, b_metadata: &fs::Metadata, a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let _ = (a, b);
+        a_metadata.dev() == b_metadata.dev() && a_metadata.ino() == b_metadata.ino()
+    }
+    #[cfg(not(unix))]
+    {
+        // without file IDs, fall back to what a case-insensitive filesystem would consider equal
+        let _ = (a_metadata, b_metadata);
+        a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+    }
+}
+
+/// Writes `contents` to `path` atomically: they are written to a temporary file next to `path`,
+/// which is then renamed over it, so readers see either the old or the new contents, never a
+/// partial write. If `path` exists, its permissions are copied to the new file; otherwise, on
+/// Unix, only the owner can read or write it.
+///
+/// With `durable` set, the temporary file is flushed to disk before the rename, and the parent
+/// directory before and after it, so the new contents survive a power loss once this returns.
+/// Windows has no directory flush; there, the rename is journaled by NTFS instead.
+///
+/// # Arguments
+///
+/// * `path` - The path to write to.
+/// * `contents` - The contents to write.
+/// * `durable` - Whether to flush everything to disk before returning.
+///
+/// # Errors
+///
+/// An error is returned if the temporary file could not be written or renamed, in which case it
+/// is removed and `path` is left untouched, or if flushing fails.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::atomic_write;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_atomic_write");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// let state = dir.join("state.json");
+/// atomic_write(&state, r#"{"version":1}"#, false)?;
+/// atomic_write(&state, r#"{"version":2}"#, true)?;
+/// assert_eq!(fs::read_to_string(&state)?, r#"{"version":2}"#);
+/// assert_eq!(fs::read_dir(&dir)?.count(), 1);
+///
+/// # #[cfg(unix)]
+/// # {
+/// use std::os::unix::fs::PermissionsExt;
+///
+/// let secrets = dir.join("secrets.toml");
+/// fs::write(&secrets, "token = 1")?;
+/// fs::set_permissions(&secrets, fs::Permissions::from_mode(0o600))?;
+/// atomic_write(&secrets, "token = 2", false)?;
+/// assert_eq!(fs::metadata(&secrets)?.permissions().mode() & 0o777, 0o600);
+/// # }
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn atomic_write<C: AsRef<[u8]>>(path: &Path, contents: C, durable: bool) -> crate::Result<()> {
+    use std::io::Write;
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", path.display()),
+        )
+    })?;
+    // errors drop the temporary file, which deletes it
+    let mut temp = TempFile::new_in(parent, &format!(".{}", file_name.to_string_lossy()))?;
+    temp.write_all(contents.as_ref())?;
+    match fs::metadata(path) {
+        Ok(metadata) => temp.as_file().set_permissions(metadata.permissions())?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    if durable {
+        temp.as_file().sync_all()?;
+        sync_dir(parent)?;
+    }
+    temp.persist(path)?;
+    if durable {
+        sync_dir(parent)?;
+    }
+    Ok(())
+}
+
+/// Flushes the entries of a directory to disk. Windows can't open directories for this, so there
+/// it does nothing.
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    {
+        let _ = dir;
+        Ok(())
+    }
+    #[cfg(not(windows))]
+    {
+        fs::File::open(dir)?.sync_all()
+    }
+}
+
+/// Options for walking a directory tree, built by [`walk`]. Iterating over a `Walk` yields every
+/// entry below the root, each directory before its contents, in the order the platform lists
+/// them.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::walk;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_walk");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("mods/disabled"))?;
+/// fs::write(dir.join("mods/map.jar"), "")?;
+/// fs::write(dir.join("mods/disabled/old.jar"), "")?;
+/// fs::write(dir.join("mods/README.txt"), "")?;
+/// fs::write(dir.join("mods/.cache.jar"), "")?;
+///
+/// let mut jars = walk(dir.join("mods"))
+///     .include_hidden(false)
+///     .extensions(["jar"])
+///     .into_iter()
+///     .map(|entry| Ok(entry?.depth()))
+///     .collect::<dablenutil::Result<Vec<_>>>()?;
+/// jars.sort();
+/// assert_eq!(jars, [1, 2]);
+///
+/// let top_level = walk(dir.join("mods")).max_depth(1).into_iter().count();
+/// assert_eq!(top_level, 4);
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Walk {
+    root: PathBuf,
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+    include_hidden: bool,
+    extensions: Option<Vec<String>>,
+}
+
+/// Starts building a walk of the directory tree at `root`.
+/// The default values are:
+/// * `max_depth`: `None`
+/// * `follow_symlinks`: `false`
+/// * `include_hidden`: `true`
+/// * `extensions`: `None`
+///
+/// # Arguments
+///
+/// * `root` - The directory to walk.
+pub fn walk<P: Into<PathBuf>>(root: P) -> Walk {
+    Walk {
+        root: root.into(),
+        max_depth: None,
+        follow_symlinks: false,
+        include_hidden: true,
+        extensions: None,
+    }
+}
+
+impl Walk {
+    /// Gets the directory being walked.
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Gets the maximum depth of the walk, if set.
+    pub fn get_max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Sets the maximum depth of the walk. Entries directly in the root have a depth of 1.
+    ///
+    /// # Arguments
+    /// * `max_depth` - The maximum depth.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Gets whether symbolic links are followed.
+    pub fn get_follow_symlinks(&self) -> bool {
+        self.follow_symlinks
+    }
+
+    /// Sets whether symbolic links are followed. When followed, entries have the metadata of what
+    /// the link points to, and directory links are walked unless they lead back to a directory
+    /// being walked. Dangling links are yielded with the metadata of the link itself.
+    ///
+    /// # Arguments
+    /// * `follow_symlinks` - Whether to follow links.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Gets whether hidden entries are included.
+    pub fn get_include_hidden(&self) -> bool {
+        self.include_hidden
+    }
+
+    /// Sets whether hidden entries are included. Entries whose names start with `.` are hidden,
+    /// as are those with the hidden attribute on Windows. Hidden directories aren't walked.
+    ///
+    /// # Arguments
+    /// * `include_hidden` - Whether to include hidden entries.
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Gets the extensions files are filtered by, if set.
+    pub fn get_extensions(&self) -> Option<&[String]> {
+        self.extensions.as_deref()
+    }
+
+    /// Only yields files with one of `extensions`, compared without the leading `.` and ignoring
+    /// case. Directories are still walked, but not yielded.
+    ///
+    /// # Arguments
+    /// * `extensions` - The extensions to keep, like `jar` or `gz`.
+    pub fn extensions<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, extensions: I) -> Self {
+        self.extensions = Some(
+            extensions
+                .into_iter()
+                .map(|ext| ext.as_ref().trim_start_matches('.').to_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Checks if a non-directory entry passes the extension filter.
+    fn matches_extension(&self, path: &Path) -> bool {
+        self.extensions.as_ref().is_none_or(|extensions| {
+            path.extension()
+                .is_some_and(|ext| extensions.contains(&ext.to_string_lossy().to_lowercase()))
+        })
+    }
+}
+
+impl IntoIterator for Walk {
+    type Item = crate::Result<WalkEntry>;
+    type IntoIter = WalkIter;
+
+    fn into_iter(self) -> WalkIter {
+        WalkIter {
+            descend: Some((self.root.clone(), 1)),
+            walk: self,
+            stack: Vec::new(),
+        }
+    }
+}
+
+/// An entry yielded by a [`Walk`].
+#[derive(Debug, Clone)]
+pub struct WalkEntry {
+    path: PathBuf,
+    metadata: fs::Metadata,
+    depth: usize,
+}
+
+impl WalkEntry {
+    /// Gets the path of the entry, which starts with the root of the walk.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the metadata of the entry. Symbolic links have the metadata of the link itself unless
+    /// they are followed.
+    pub fn metadata(&self) -> &fs::Metadata {
+        &self.metadata
+    }
+
+    /// Gets the depth of the entry. Entries directly in the root have a depth of 1.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Turns the entry into its path.
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+}
+
+/// The iterator over a [`Walk`]. An error reading an entry is yielded in its place, and the walk
+/// carries on with the next one.
+#[derive(Debug)]
+pub struct WalkIter {
+    walk: Walk,
+    /// A directory to start reading on the next call, so it is yielded before its contents.
+    descend: Option<(PathBuf, usize)>,
+    stack: Vec<WalkFrame>,
+}
+
+/// A directory being read by a [`WalkIter`].
+#[derive(Debug)]
+struct WalkFrame {
+    entries: fs::ReadDir,
+    depth: usize,
+    /// The canonical path of the directory when following links, to detect cycles.
+    canonical: Option<PathBuf>,
+}
+
+impl WalkIter {
+    /// Starts reading a directory, unless following links leads back into the walk.
+    fn push(&mut self, dir: &Path, depth: usize) -> io::Result<()> {
+        let canonical = if self.walk.follow_symlinks {
+            let canonical = fs::canonicalize(dir)?;
+            if self
+                .stack
+                .iter()
+                .any(|frame| frame.canonical.as_ref() == Some(&canonical))
+            {
+                return Ok(());
+            }
+            Some(canonical)
+        } else {
+            None
+        };
+        self.stack.push(WalkFrame {
+            entries: fs::read_dir(dir)?,
+            depth,
+            canonical,
+        });
+        Ok(())
+    }
+}
+
+impl Iterator for WalkIter {
+    type Item = crate::Result<WalkEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((dir, depth)) = self.descend.take() {
+                if let Err(e) = self.push(&dir, depth) {
+                    return Some(Err(e.into()));
+                }
+            }
+            let frame = self.stack.last_mut()?;
+            let depth = frame.depth;
+            let entry = match frame.entries.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let mut metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if metadata.is_symlink() && self.walk.follow_symlinks {
+                // dangling links keep the metadata of the link
+                if let Ok(target) = fs::metadata(&path) {
+                    metadata = target;
+                }
+            }
+            if !self.walk.include_hidden && has_hidden_attributes(&path, &metadata) {
+                continue;
+            }
+            let is_dir = metadata.is_dir();
+            if is_dir
+                && self
+                    .walk
+                    .max_depth
+                    .is_none_or(|max_depth| depth < max_depth)
+            {
+                self.descend = Some((path.clone(), depth + 1));
+            }
+            if (is_dir && self.walk.extensions.is_some())
+                || (!is_dir && !self.walk.matches_extension(&path))
+            {
+                continue;
+            }
+            return Some(Ok(WalkEntry {
+                path,
+                metadata,
+                depth,
+            }));
+        }
+    }
+}
+
+/// The Windows attribute which hides a file.
+#[cfg(windows)]
+const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+
+/// Checks if an entry is hidden: its name starts with `.`, or on Windows, it has the hidden
+/// attribute.
+fn has_hidden_attributes(path: &Path, metadata: &fs::Metadata) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+
+        if metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0 {
+            return true;
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = metadata;
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Sums the sizes of the files in a directory tree, for cache eviction and disk usage screens.
+/// If `path` is a file, its size is returned. Entries deleted during the walk are skipped, since
+/// logs and caches often change while being measured.
+///
+/// # Arguments
+///
+/// * `path` - The root of the tree.
+/// * `symlinks` - How links are counted. [`Symlinks::Follow`] counts what they point to, once per
+///   link, skipping directory links which lead back into the walk. [`Symlinks::Preserve`] counts
+///   the size of the link itself, and [`Symlinks::Skip`] leaves links out.
+///
+/// # Errors
+///
+/// An error is returned if `path` or an entry in the tree could not be read.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::{dir_size, Symlinks};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_dir_size");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("logs"))?;
+/// fs::write(dir.join("logs/latest.log"), [0; 100])?;
+/// fs::write(dir.join("cache.bin"), [0; 23])?;
+/// assert_eq!(dir_size(&dir, Symlinks::Skip)?, 123);
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn dir_size(path: &Path, symlinks: Symlinks) -> crate::Result<u64> {
+    let metadata = if symlinks == Symlinks::Follow {
+        fs::metadata(path)?
+    } else {
+        fs::symlink_metadata(path)?
+    };
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut size = 0;
+    for entry in walk(path).follow_symlinks(symlinks == Symlinks::Follow) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // deleted during the walk
+            Err(crate::Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let metadata = entry.metadata();
+        // links are only still links here when preserved, skipped, or dangling
+        if metadata.is_symlink() && symlinks != Symlinks::Preserve {
+            continue;
+        }
+        if !metadata.is_dir() {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Finds the paths matching a glob pattern, sorted. Patterns are `/`-separated paths where a
+/// component may contain wildcards:
+/// * `*` matches any run of characters, and `?` matches any single character, within one
+///   component. Neither matches a leading `.`, so hidden entries need the `.` spelled out.
+/// * `**` as a whole component matches any number of directories, including none, skipping
+///   hidden ones.
+///
+/// Relative patterns are resolved against the current directory, and the results are relative
+/// too. Symbolic links are matched like anything else, but `**` doesn't descend into them.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern, like `mods/*.jar` or `logs/**/*.gz`.
+///
+/// # Errors
+///
+/// An error is returned if a directory could not be read, other than because it doesn't exist.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::glob;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_glob");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("logs/2024/01"))?;
+/// fs::write(dir.join("logs/latest.log"), "")?;
+/// fs::write(dir.join("logs/2024/01/a.log.gz"), "")?;
+/// fs::write(dir.join("logs/2024/b.log.gz"), "")?;
+///
+/// let pattern = format!("{}/logs/**/*.gz", dir.display());
+/// assert_eq!(
+///     glob(&pattern)?,
+///     [dir.join("logs/2024/01/a.log.gz"), dir.join("logs/2024/b.log.gz")]
+/// );
+/// assert_eq!(glob(&format!("{}/logs/late?t.*", dir.display()))?.len(), 1);
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn glob(pattern: &str) -> crate::Result<Vec<PathBuf>> {
+    let mut base = PathBuf::new();
+    let mut components = Vec::new();
+    for component in Path::new(pattern).components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir if components.is_empty() => {
+                base.push(component);
+            }
+            Component::CurDir => {}
+            component => components.push(component.as_os_str().to_string_lossy().into_owned()),
+        }
+    }
+    let components: Vec<&str> = components.iter().map(String::as_str).collect();
+    let mut matches = BTreeSet::new();
+    glob_inner(&base, &components, &mut matches)?;
+    Ok(matches.into_iter().collect())
+}
+
+/// Adds the paths below `dir` matching the pattern `components` to `matches`.
+fn glob_inner(
+    dir: &Path,
+    components: &[&str],
+    matches: &mut BTreeSet<PathBuf>,
+) -> crate::Result<()> {
+    let Some((&component, rest)) = components.split_first() else {
+        if !dir.as_os_str().is_empty() {
+            matches.insert(dir.to_path_buf());
+        }
+        return Ok(());
+    };
+    let read_dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    if component == "**" {
+        glob_inner(dir, rest, matches)?;
+        for entry in walk(read_dir).include_hidden(false) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(crate::Error::Io(e)) if is_missing(&e) => continue,
+                Err(e) => return Err(e),
+            };
+            if entry.metadata().is_dir() {
+                let relative = entry.path().strip_prefix(read_dir).unwrap_or(entry.path());
+                glob_inner(&dir.join(relative), rest, matches)?;
+            }
+        }
+    } else if component.contains(['*', '?']) {
+        let entries = match fs::read_dir(read_dir) {
+            Ok(entries) => entries,
+            Err(e) if is_missing(&e) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') && !component.starts_with('.') {
+                continue;
+            }
+            if wildcard_match(component, &name) {
+                glob_inner(&dir.join(&name), rest, matches)?;
+            }
+        }
+    } else {
+        let path = dir.join(component);
+        if path.symlink_metadata().is_ok() {
+            glob_inner(&path, rest, matches)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks if an error means a path doesn't exist as a directory.
+fn is_missing(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::NotFound | io::ErrorKind::NotADirectory
+    )
+}
+
+/// Matches a name against a pattern of `*` and `?` wildcards.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // where the last `*` was, and the name position it currently matches up to
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Finds the most recently modified file directly in `dir` accepted by `filter`, for things like
+/// resuming from the latest save. Directories are ignored, and symbolic links are judged by what
+/// they point to.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to search.
+/// * `filter` - Called with the path of each file; only files it returns `true` for are
+///   considered. [`has_extension`] builds the common one.
+///
+/// # Errors
+///
+/// An error is returned if `dir` or an entry in it could not be read.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::{has_extension, newest_file, oldest_file};
+/// use std::{fs, time::{Duration, SystemTime}};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_newest_file");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// let now = SystemTime::now();
+/// for (name, modified) in [("old.sav", now - Duration::from_secs(60)), ("new.sav", now)] {
+///     fs::File::create(dir.join(name))?.set_modified(modified)?;
+/// }
+/// fs::File::create(dir.join("newest.txt"))?.set_modified(now + Duration::from_secs(5))?;
+///
+/// assert_eq!(newest_file(&dir, has_extension("sav"))?, Some(dir.join("new.sav")));
+/// assert_eq!(oldest_file(&dir, has_extension("sav"))?, Some(dir.join("old.sav")));
+/// assert_eq!(newest_file(&dir, |_| true)?, Some(dir.join("newest.txt")));
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn newest_file<F: Fn(&Path) -> bool>(dir: &Path, filter: F) -> crate::Result<Option<PathBuf>> {
+    find_by_modified(dir, filter, |candidate, best| candidate > best)
+}
+
+/// Finds the least recently modified file directly in `dir` accepted by `filter`, for things like
+/// picking which archive to prune. See [`newest_file`] for the details.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to search.
+/// * `filter` - Called with the path of each file; only files it returns `true` for are
+///   considered.
+///
+/// # Errors
+///
+/// An error is returned if `dir` or an entry in it could not be read.
+pub fn oldest_file<F: Fn(&Path) -> bool>(dir: &Path, filter: F) -> crate::Result<Option<PathBuf>> {
+    find_by_modified(dir, filter, |candidate, best| candidate < best)
+}
+
+/// Builds a filter for [`newest_file`] and [`oldest_file`] accepting paths with an extension,
+/// compared without the leading `.` and ignoring case.
+///
+/// # Arguments
+///
+/// * `extension` - The extension, like `zip`.
+pub fn has_extension(extension: &str) -> impl Fn(&Path) -> bool {
+    let extension = extension.trim_start_matches('.').to_lowercase();
+    move |path| {
+        path.extension()
+            .is_some_and(|ext| ext.to_string_lossy().to_lowercase() == extension)
+    }
+}
+
+/// Finds the file directly in `dir` accepted by `filter` whose modification time beats every
+/// other according to `better`.
+fn find_by_modified<F, B>(dir: &Path, filter: F, better: B) -> crate::Result<Option<PathBuf>>
+where
+    F: Fn(&Path) -> bool,
+    B: Fn(SystemTime, SystemTime) -> bool,
+{
+    let mut best: Option<(PathBuf, SystemTime)> = None;
+    for entry in walk(dir).max_depth(1).follow_symlinks(true) {
+        let entry = entry?;
+        if entry.metadata().is_dir() || !filter(entry.path()) {
+            continue;
+        }
+        let modified = entry.metadata().modified()?;
+        if best
+            .as_ref()
+            .is_none_or(|(_, best_modified)| better(modified, *best_modified))
+        {
+            best = Some((entry.into_path(), modified));
+        }
+    }
+    Ok(best.map(|(path, _)| path))
+}
+
+/// Deletes the files directly in `dir` whose names match `pattern` and which were last modified
+/// more than `age` ago, for cleaning up caches and old archives. Directories are left alone, and
+/// symbolic links are judged and deleted as links.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to prune.
+/// * `age` - How old a file must be to be deleted.
+/// * `pattern` - The names of files to consider, where `*` matches any run of characters and `?`
+///   any single character, like `*.gz`. Use `*` for every file, including hidden ones.
+///
+/// # Returns
+///
+/// The files which were deleted.
+///
+/// # Errors
+///
+/// An error is returned if `dir` or an entry could not be read, or a file could not be deleted.
+/// Files deleted before the error stay deleted.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::prune_older_than;
+/// use std::{fs, time::{Duration, SystemTime}};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_prune_older_than");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// let week_ago = SystemTime::now() - Duration::from_secs(7 * 24 * 60 * 60);
+/// fs::File::create(dir.join("old.log.gz"))?.set_modified(week_ago)?;
+/// fs::File::create(dir.join("old.txt"))?.set_modified(week_ago)?;
+/// fs::File::create(dir.join("new.log.gz"))?;
+///
+/// let day = Duration::from_secs(24 * 60 * 60);
+/// assert_eq!(prune_older_than(&dir, day, "*.gz")?, [dir.join("old.log.gz")]);
+/// assert!(!dir.join("old.log.gz").exists());
+/// assert!(dir.join("old.txt").exists());
+/// assert!(dir.join("new.log.gz").exists());
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn prune_older_than(dir: &Path, age: Duration, pattern: &str) -> crate::Result<Vec<PathBuf>> {
+    prune_older_than_with_plan(dir, age, pattern, &mut Plan::new(Mode::Execute))
+}
+
+/// Deletes old files like [`prune_older_than`], recording each deletion in `plan`. In
+/// [`Mode::DryRun`], nothing is deleted.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to prune.
+/// * `age` - How old a file must be to be deleted.
+/// * `pattern` - The names of files to consider, as in [`prune_older_than`].
+/// * `plan` - The plan to record each deletion in.
+///
+/// # Returns
+///
+/// The files which were deleted, or would be in a dry run.
+///
+/// # Errors
+///
+/// An error is returned in the same cases as [`prune_older_than`].
+///
+/// # Examples
+/// ```
+/// use dablenutil::{
+///     dry_run::{Mode, Plan},
+///     fs_utils::prune_older_than_with_plan,
+/// };
+/// use std::{fs, time::{Duration, SystemTime}};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_prune_older_than_with_plan");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// let week_ago = SystemTime::now() - Duration::from_secs(7 * 24 * 60 * 60);
+/// fs::File::create(dir.join("old.log.gz"))?.set_modified(week_ago)?;
+///
+/// let day = Duration::from_secs(24 * 60 * 60);
+/// let mut dry_run = Plan::new(Mode::DryRun);
+/// let pruned = prune_older_than_with_plan(&dir, day, "*.gz", &mut dry_run)?;
+/// assert_eq!(pruned, [dir.join("old.log.gz")]);
+/// assert_eq!(dry_run.actions().len(), 1);
+/// assert!(dir.join("old.log.gz").exists());
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn prune_older_than_with_plan(
+    dir: &Path,
+    age: Duration,
+    pattern: &str,
+    plan: &mut Plan,
+) -> crate::Result<Vec<PathBuf>> {
+    prune_older_than_with_clock(dir, age, pattern, &SystemClock, plan)
+}
+
+/// Deletes old files like [`prune_older_than_with_plan`], judging their age by the wall-clock
+/// time of `clock`, so tests can check retention without waiting or backdating files by hand.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to prune.
+/// * `age` - How old a file must be to be deleted.
+/// * `pattern` - The names of files to consider, as in [`prune_older_than`].
+/// * `clock` - The clock giving the current time.
+/// * `plan` - The plan to record each deletion in. In [`Mode::DryRun`], nothing is deleted.
+///
+/// # Returns
+///
+/// The files which were deleted, or would be in a dry run.
+///
+/// # Errors
+///
+/// An error is returned if `dir` or an entry could not be read, or a file could not be deleted.
+/// Files deleted before the error stay deleted.
+pub fn prune_older_than_with_clock<C: Clock + ?Sized>(
+    dir: &Path,
+    age: Duration,
+    pattern: &str,
+    clock: &C,
+    plan: &mut Plan,
+) -> crate::Result<Vec<PathBuf>> {
+    let cutoff = clock
+        .system_time()
+        .checked_sub(age)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut deleted = Vec::new();
+    for entry in walk(dir).max_depth(1) {
+        let entry = entry?;
+        let metadata = entry.metadata();
+        let matches = entry
+            .path()
+            .file_name()
+            .is_some_and(|name| wildcard_match(pattern, &name.to_string_lossy()));
+        if metadata.is_dir() || !matches || metadata.modified()? >= cutoff {
+            continue;
+        }
+        let file_type = metadata.file_type();
+        let path = entry.into_path();
+        match plan.perform(Action::Remove(path.clone()), || {
+            remove_non_dir(&path, file_type)
+        }) {
+            // something else deleted it first
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            result => {
+                result?;
+            }
+        }
+        deleted.push(path);
+    }
+    Ok(deleted)
+}
+
+/// Creates an empty file if it doesn't exist, or sets its modification time to now if it does,
+/// like the `touch` command. Parent directories are created as needed. Handy for marker files,
+/// like one recording that first-run setup finished.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+///
+/// # Errors
+///
+/// An error is returned if the file or its parents could not be created, or its modification time
+/// could not be set.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::touch;
+/// use std::{fs, time::{Duration, SystemTime}};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_touch");
+/// # let _ = fs::remove_dir_all(&dir);
+/// let marker = dir.join("state/first-run-complete");
+/// touch(&marker)?;
+/// assert_eq!(fs::metadata(&marker)?.len(), 0);
+///
+/// let hour_ago = SystemTime::now() - Duration::from_secs(60 * 60);
+/// fs::File::options().write(true).open(&marker)?.set_modified(hour_ago)?;
+/// touch(&marker)?;
+/// assert!(fs::metadata(&marker)?.modified()? > hour_ago);
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn touch(path: &Path) -> crate::Result<()> {
+    crate::ensure_parent_dir(path)?;
+    // appending never changes existing contents
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.set_modified(SystemTime::now())?;
+    Ok(())
+}
+
+/// Creates a symbolic link at `dst` pointing to `src`. On Windows, where file and directory links
+/// differ, the kind is picked by what `src` is, and a link to a missing target is a file link.
+/// Creating links on Windows needs Developer Mode or administrator rights; see
+/// [`symlink_or_junction`] for directories.
+///
+/// # Arguments
+///
+/// * `src` - The target of the link. A relative target is relative to the directory of `dst`.
+/// * `dst` - The path of the new link.
+///
+/// # Errors
+///
+/// An error is returned if the link could not be created, for example if `dst` already exists.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::symlink;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_symlink");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("server-1.20"))?;
+/// fs::write(dir.join("server-1.20/server.jar"), "jar")?;
+///
+/// # #[cfg(unix)]
+/// # {
+/// symlink("server-1.20".as_ref(), &dir.join("current"))?;
+/// assert_eq!(fs::read_to_string(dir.join("current/server.jar"))?, "jar");
+/// # }
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn symlink(src: &Path, dst: &Path) -> crate::Result<()> {
+    Ok(create_symlink(src, dst, false)?)
+}
+
+/// Like [`symlink`], but on Windows, a directory link which can't be created for lack of the
+/// symbolic link privilege is created as a junction instead, which needs no privilege. Junctions
+/// always store an absolute target and only work for local directories. On other platforms this
+/// is the same as [`symlink`].
+///
+/// # Arguments
+///
+/// * `src` - The target of the link. A relative target is relative to the directory of `dst`.
+/// * `dst` - The path of the new link.
+///
+/// # Errors
+///
+/// An error is returned if neither a link nor a junction could be created.
+pub fn symlink_or_junction(src: &Path, dst: &Path) -> crate::Result<()> {
+    Ok(create_symlink(src, dst, true)?)
+}
+
+/// Creates a symbolic link, optionally falling back to a junction on Windows.
+fn create_symlink(src: &Path, dst: &Path, junction_fallback: bool) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let _ = junction_fallback;
+        std::os::unix::fs::symlink(src, dst)
+    }
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::Foundation::ERROR_PRIVILEGE_NOT_HELD;
+
+        let target = match dst.parent() {
+            Some(parent) if src.is_relative() => parent.join(src),
+            _ => src.to_path_buf(),
+        };
+        if !fs::metadata(&target).is_ok_and(|metadata| metadata.is_dir()) {
+            return std::os::windows::fs::symlink_file(src, dst);
+        }
+        match std::os::windows::fs::symlink_dir(src, dst) {
+            Err(e)
+                if junction_fallback
+                    && e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD as i32) =>
+            {
+                create_junction(&std::path::absolute(target)?, dst)
+            }
+            result => result,
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (src, dst, junction_fallback);
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+/// Creates a directory junction with `mklink /J`, since std has no API for them.
+#[cfg(windows)]
+fn create_junction(target: &Path, dst: &Path) -> io::Result<()> {
+    let output = std::process::Command::new("cmd")
+        .arg("/C")
+        .arg("mklink")
+        .arg("/J")
+        .arg(dst)
+        .arg(target)
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "mklink /J failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Follows a chain of symbolic links and returns the path it ends at. Unlike
+/// [`fs::canonicalize`], this works on dangling links: the missing path the chain points to is
+/// returned, which is useful for reporting where a broken link leads. Only the final component is
+/// followed at each step; links in parent directories are left as they are. A path which isn't a
+/// link is returned unchanged.
+///
+/// # Arguments
+///
+/// * `path` - The path to resolve.
+/// * `max_depth` - The most links to follow.
+///
+/// # Errors
+///
+/// An [`Error::SymlinkLoop`](crate::Error::SymlinkLoop) is returned if the chain loops or has
+/// more than `max_depth` links. Other errors are returned if a link could not be read.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::resolve_link;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_resolve_link");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+///
+/// # #[cfg(unix)]
+/// # {
+/// use std::os::unix::fs::symlink;
+/// symlink("latest.log", dir.join("current.log"))?;
+/// symlink("current.log", dir.join("app.log"))?;
+/// assert_eq!(resolve_link(&dir.join("app.log"), 8)?, dir.join("latest.log"));
+///
+/// symlink("loop-b", dir.join("loop-a"))?;
+/// symlink("loop-a", dir.join("loop-b"))?;
+/// let error = resolve_link(&dir.join("loop-a"), 8).unwrap_err();
+/// assert_eq!(error.kind(), "symlink_loop");
+/// # }
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn resolve_link(path: &Path, max_depth: usize) -> crate::Result<PathBuf> {
+    let mut current = path.to_path_buf();
+    let mut seen = HashSet::new();
+    loop {
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {}
+            Err(e) if !is_missing(&e) => return Err(e.into()),
+            _ => return Ok(current),
+        }
+        if seen.len() == max_depth || !seen.insert(current.clone()) {
+            return Err(crate::Error::SymlinkLoop(current));
+        }
+        let target = fs::read_link(&current)?;
+        current = match current.parent() {
+            Some(parent) if target.is_relative() => parent.join(target),
+            _ => target,
+        };
+    }
+}
+
+/// Joins an untrusted relative path, such as an archive entry or a path from a manifest, onto
+/// `base`, making sure the result stays inside `base`. `.` components are dropped and `..`
+/// components are resolved lexically, so `mods/../config.toml` is allowed but `../config.toml` is
+/// not. The filesystem is never touched, so links inside `base` aren't accounted for.
+///
+/// # Arguments
+///
+/// * `base` - The directory the result must stay in.
+/// * `untrusted` - The relative path to join.
+///
+/// # Errors
+///
+/// An `InvalidInput` error is returned if `untrusted` is absolute, has a drive or root, or climbs
+/// out of `base` with `..`.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::safe_join;
+/// use std::path::Path;
+///
+/// let base = Path::new("instances/survival");
+/// assert_eq!(
+///     safe_join(base, "mods/./../config.toml").unwrap(),
+///     base.join("config.toml")
+/// );
+/// assert!(safe_join(base, "../../.ssh/id_rsa").is_err());
+/// assert!(safe_join(base, "/etc/passwd").is_err());
+/// ```
+pub fn safe_join<P: AsRef<Path>>(base: &Path, untrusted: P) -> crate::Result<PathBuf> {
+    let untrusted = untrusted.as_ref();
+    let escapes = || -> crate::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} escapes {}", untrusted.display(), base.display()),
+        )
+        .into()
+    };
+    let mut parts = Vec::new();
+    for component in untrusted.components() {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop().ok_or_else(escapes)?;
+            }
+            Component::Prefix(_) | Component::RootDir => return Err(escapes()),
+        }
+    }
+    let mut joined = base.to_path_buf();
+    joined.extend(parts);
+    Ok(joined)
+}
+
+/// Normalizes a path lexically, without touching the filesystem, so it works on paths which don't
+/// exist, unlike [`fs::canonicalize`]. `.` components are dropped, `..` components remove the
+/// component before them, and separators are unified, so `/` becomes `\` on Windows. Leading `..`
+/// components of a relative path are kept, and `..` at the root is dropped. An empty result is
+/// `.`.
+///
+/// Since links aren't followed, `link/..` becomes `.` even if `link` points elsewhere.
+///
+/// # Arguments
+///
+/// * `path` - The path to normalize.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::normalize;
+/// use std::path::Path;
+///
+/// assert_eq!(normalize(Path::new("games/./mods/../saves/")), Path::new("games/saves"));
+/// assert_eq!(normalize(Path::new("../games/..")), Path::new(".."));
+/// assert_eq!(normalize(Path::new("/../games")), Path::new("/games"));
+/// assert_eq!(normalize(Path::new("games/..")), Path::new("."));
+/// ```
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    // the number of normal components which a `..` can remove
+    let mut depth = 0;
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => normalized.push(component),
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => {
+                normalized.pop();
+                depth -= 1;
+            }
+            Component::ParentDir => {
+                if !normalized.has_root() {
+                    normalized.push(component);
+                }
+            }
+            Component::Normal(part) => {
+                normalized.push(part);
+                depth += 1;
+            }
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(Component::CurDir);
+    }
+    normalized
+}
+
+/// Expands a leading `~` to the current user's home directory, or `~name` to the home directory of
+/// the user `name`. Other users' home directories are looked up in `/etc/passwd` on Linux, and
+/// otherwise assumed to sit next to the current user's, like `/Users/name` on macOS or
+/// `C:\Users\name` on Windows. Paths without a leading `~`, or whose home directory can't be
+/// found, are returned unchanged.
+///
+/// # Arguments
+///
+/// * `path` - The path to expand.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::expand_tilde;
+/// use std::path::Path;
+///
+/// let home = std::env::home_dir().unwrap();
+/// assert_eq!(expand_tilde(Path::new("~/games/server")), home.join("games/server"));
+/// assert_eq!(expand_tilde(Path::new("games/~")), Path::new("games/~"));
+/// ```
+pub fn expand_tilde(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Normal(first)) = components.next() else {
+        return path.to_path_buf();
+    };
+    let home = match first.to_str().and_then(|first| first.strip_prefix('~')) {
+        Some("") => std::env::home_dir(),
+        Some(user) => user_home_dir(user),
+        None => None,
+    };
+    match home {
+        Some(mut home) => {
+            home.push(components.as_path());
+            home
+        }
+        None => path.to_path_buf(),
+    }
+}
+
+/// Finds the home directory of another user, for [`expand_tilde`].
+fn user_home_dir(user: &str) -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    if let Ok(passwd) = fs::read_to_string("/etc/passwd") {
+        // name:password:uid:gid:gecos:home:shell
+        return passwd.lines().find_map(|line| {
+            let mut fields = line.split(':');
+            (fields.next() == Some(user))
+                .then(|| fields.nth(4))
+                .flatten()
+                .map(PathBuf::from)
+        });
+    }
+    let home = std::env::home_dir()?;
+    Some(home.parent()?.join(user))
+}
+
+/// Computes the relative path from the directory `base` to `path`, like `../saves/world`, for
+/// writing portable paths into config files. Both paths are [`normalize`]d first and the
+/// filesystem is never touched. On Windows, components are compared ignoring ASCII case.
+///
+/// `None` is returned if there is no such path: if only one of the paths is absolute, if they are
+/// on different drives on Windows, or if `base` climbs further out with `..` than `path` does.
+///
+/// # Arguments
+///
+/// * `path` - The path to reach.
+/// * `base` - The directory the result is relative to.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::relative_to;
+/// use std::path::Path;
+///
+/// let base = Path::new("/srv/games/config");
+/// assert_eq!(
+///     relative_to(Path::new("/srv/games/saves/world"), base),
+///     Some("../saves/world".into())
+/// );
+/// assert_eq!(relative_to(base, base), Some(".".into()));
+/// assert_eq!(relative_to(Path::new("saves"), base), None);
+/// ```
+pub fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    let path = normalize(path);
+    let base = normalize(base);
+    let path: Vec<_> = path
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let base: Vec<_> = base
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let common = path
+        .iter()
+        .zip(&base)
+        .take_while(|(a, b)| same_component(**a, **b))
+        .count();
+    let is_normal = |c: &Component| matches!(c, Component::Normal(_));
+    if !base[common..].iter().all(is_normal)
+        || !path[common..]
+            .iter()
+            .all(|c| is_normal(c) || *c == Component::ParentDir)
+    {
+        return None;
+    }
+    let mut relative: PathBuf = base[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .collect();
+    relative.extend(&path[common..]);
+    if relative.as_os_str().is_empty() {
+        relative.push(Component::CurDir);
+    }
+    Some(relative)
+}
+
+/// Compares path components the way the platform does, ignoring case on Windows.
+fn same_component(a: Component, b: Component) -> bool {
+    if cfg!(windows) {
+        a.as_os_str().eq_ignore_ascii_case(b.as_os_str())
+    } else {
+        a == b
+    }
+}
+
+/// How [`backup_file`] names backups.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BackupStrategy {
+    /// `config.toml.bak` is the newest backup, and older ones are shifted to `config.toml.bak.1`,
+    /// `config.toml.bak.2`, and so on.
+    #[default]
+    Numbered,
+    /// Backups are named after the local time they were made, to the millisecond, like
+    /// `config-2024-05-01_12-30-00-250.toml.bak`, so they sort by age. If that name is taken, the
+    /// time is moved a millisecond later until it is free. Only available when the `time_utils`
+    /// feature is enabled.
+    #[cfg(feature = "time_utils")]
+    Timestamped,
+}
+
+/// Copies a file to a backup next to it before it is overwritten, then deletes the oldest backups
+/// so at most `max_backups` remain. Nothing is backed up if the file doesn't exist, or if
+/// `max_backups` is 0.
+///
+/// # Arguments
+///
+/// * `path` - The file to back up.
+/// * `strategy` - How backups are named.
+/// * `max_backups` - The most backups to keep, including the new one.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be copied, or old backups could not be renamed or
+/// deleted.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::{backup_file, BackupStrategy};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_backup_file");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// let config = dir.join("config.toml");
+/// for volume in 1..=3 {
+///     fs::write(&config, format!("volume = {}", volume))?;
+///     backup_file(&config, BackupStrategy::Numbered, 2)?;
+/// }
+/// assert_eq!(fs::read_to_string(dir.join("config.toml.bak"))?, "volume = 3");
+/// assert_eq!(fs::read_to_string(dir.join("config.toml.bak.1"))?, "volume = 2");
+/// assert!(!dir.join("config.toml.bak.2").exists());
+///
+/// # #[cfg(feature = "time_utils")]
+/// # {
+/// let first = backup_file(&config, BackupStrategy::Timestamped, 2)?.unwrap();
+/// let second = backup_file(&config, BackupStrategy::Timestamped, 2)?.unwrap();
+/// assert_ne!(first, second);
+/// assert!(first < second);
+/// # }
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn backup_file(
+    path: &Path,
+    strategy: BackupStrategy,
+    max_backups: usize,
+) -> crate::Result<Option<PathBuf>> {
+    if max_backups == 0 || !path.try_exists()? {
+        return Ok(None);
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let backup = match strategy {
+        BackupStrategy::Numbered => {
+            let numbered = |n: usize| match n {
+                0 => path.with_file_name(format!("{}.bak", name)),
+                n => path.with_file_name(format!("{}.bak.{}", name, n)),
+            };
+            crate::remove_file_if_exists(&numbered(max_backups - 1))?;
+            for n in (0..max_backups - 1).rev() {
+                match fs::rename(numbered(n), numbered(n + 1)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+            let backup = numbered(0);
+            fs::copy(path, &backup)?;
+            backup
+        }
+        #[cfg(feature = "time_utils")]
+        BackupStrategy::Timestamped => {
+            let (stem, extension) = match path.extension() {
+                Some(extension) => (
+                    path.file_stem().unwrap_or_default().to_string_lossy(),
+                    format!(".{}", extension.to_string_lossy()),
+                ),
+                None => (name.clone(), String::new()),
+            };
+            let mut source = fs::File::open(path)?;
+            // create_new fails if the name is taken, so backups made within the same millisecond
+            // can't overwrite each other
+            let mut time = SystemTime::now();
+            let (mut file, backup) = loop {
+                let timestamp = crate::time_utils::format_local(time, "%Y-%m-%d_%H-%M-%S-%3f");
+                let backup =
+                    path.with_file_name(format!("{}-{}{}.bak", stem, timestamp, extension));
+                match fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&backup)
+                {
+                    Ok(file) => break (file, backup),
+                    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                        time += Duration::from_millis(1);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            io::copy(&mut source, &mut file)?;
+            file.set_permissions(source.metadata()?.permissions())?;
+            let dir = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            let pattern = format!("{}-????-??-??_??-??-??-???{}.bak", stem, extension);
+            let mut backups = Vec::new();
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                if wildcard_match(&pattern, &entry.file_name().to_string_lossy())
+                    && entry.path() != backup
+                {
+                    backups.push(entry.path());
+                }
+            }
+            // timestamps sort by age, so the oldest come first
+            backups.sort();
+            let excess = (backups.len() + 1).saturating_sub(max_backups);
+            for old in &backups[..excess] {
+                crate::remove_file_if_exists(old)?;
+            }
+            backup
+        }
+    };
+    Ok(Some(backup))
+}
+
+/// Checks if a directory has no entries.
+///
+/// # Arguments
+///
+/// * `path` - The directory to check.
+///
+/// # Errors
+///
+/// An error is returned if the directory could not be read, including if it doesn't exist.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::is_dir_empty;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_is_dir_empty");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// assert!(is_dir_empty(&dir)?);
+/// fs::write(dir.join("save.dat"), "")?;
+/// assert!(!is_dir_empty(&dir)?);
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn is_dir_empty(path: &Path) -> crate::Result<bool> {
+    Ok(fs::read_dir(path)?.next().transpose()?.is_none())
+}
+
+/// Deletes everything inside a directory, but keeps the directory itself, along with its
+/// permissions and any handles other processes hold on it. Symbolic links are deleted, never
+/// followed.
+///
+/// # Arguments
+///
+/// * `path` - The directory to clear.
+///
+/// # Errors
+///
+/// An error is returned if the directory could not be read, or an entry could not be deleted.
+/// Entries deleted before the error stay deleted.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::{clear_dir, is_dir_empty};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_clear_dir");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("thumbnails"))?;
+/// fs::write(dir.join("thumbnails/1.png"), "")?;
+/// fs::write(dir.join("index.db"), "")?;
+///
+/// clear_dir(&dir)?;
+/// assert!(dir.is_dir());
+/// assert!(is_dir_empty(&dir)?);
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn clear_dir(path: &Path) -> crate::Result<()> {
+    clear_dir_with_plan(path, &mut Plan::new(Mode::Execute))
+}
+
+/// Clears a directory like [`clear_dir`], recording each deletion in `plan`. In
+/// [`Mode::DryRun`], nothing is deleted.
+///
+/// # Arguments
+///
+/// * `path` - The directory to clear.
+/// * `plan` - The plan to record each deletion in.
+///
+/// # Errors
+///
+/// An error is returned in the same cases as [`clear_dir`].
+///
+/// # Examples
+/// ```
+/// use dablenutil::{
+///     dry_run::{Mode, Plan},
+///     fs_utils::clear_dir_with_plan,
+/// };
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_clear_dir_with_plan");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("thumbnails"))?;
+/// fs::write(dir.join("thumbnails/1.png"), "")?;
+/// fs::write(dir.join("index.db"), "")?;
+///
+/// let mut dry_run = Plan::new(Mode::DryRun);
+/// clear_dir_with_plan(&dir, &mut dry_run)?;
+/// assert_eq!(dry_run.actions().len(), 2);
+/// assert!(dir.join("thumbnails/1.png").exists());
+/// assert!(dir.join("index.db").exists());
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn clear_dir_with_plan(path: &Path, plan: &mut Plan) -> crate::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let entry_path = entry.path();
+        let removed = if file_type.is_dir() {
+            plan.perform(Action::RemoveDir(entry_path.clone()), || {
+                fs::remove_dir_all(&entry_path)
+            })
+        } else {
+            plan.perform(Action::Remove(entry_path.clone()), || {
+                remove_non_dir(&entry_path, file_type)
+            })
+        };
+        match removed {
+            // something else deleted it first
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            result => {
+                result?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deletes a file or link. On Windows, links to directories must be deleted as directories.
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn remove_non_dir(path: &Path, file_type: fs::FileType) -> io::Result<()> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileTypeExt;
+        if file_type.is_symlink_dir() {
+            return fs::remove_dir(path);
+        }
+    }
+    fs::remove_file(path)
+}
+
+/// Makes a file executable, like `chmod +x`: on Unix, the execute bit is set for everyone who can
+/// read the file. On Windows, where being executable depends on the extension, this only checks
+/// that the file exists.
+///
+/// # Arguments
+///
+/// * `path` - The file to make executable.
+///
+/// # Errors
+///
+/// An error is returned if the file doesn't exist or its permissions could not be changed.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::set_executable;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let file = std::env::temp_dir().join("dablenutil_set_executable.sh");
+/// std::fs::write(&file, "#!/bin/sh\necho hello\n")?;
+/// set_executable(&file)?;
+/// # #[cfg(unix)]
+/// # {
+/// use std::os::unix::fs::PermissionsExt;
+/// assert_ne!(std::fs::metadata(&file)?.permissions().mode() & 0o100, 0);
+/// # }
+/// # std::fs::remove_file(&file)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn set_executable(path: &Path) -> crate::Result<()> {
+    let metadata = fs::metadata(path)?;
+    if let Some(permissions) = executable_permissions(&metadata.permissions()) {
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+/// Adds the execute bits to permissions, returning `None` if nothing changes.
+pub(crate) fn executable_permissions(permissions: &fs::Permissions) -> Option<fs::Permissions> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = permissions.mode();
+        // copy each read bit two places down onto the execute bit
+        let executable = mode | ((mode & 0o444) >> 2);
+        (executable != mode).then(|| fs::Permissions::from_mode(executable))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = permissions;
+        None
+    }
+}
+
+/// Checks if a file or directory is hidden: if its name starts with a dot, or on Windows, if it
+/// has the hidden attribute. Links are checked themselves, not what they point to.
+///
+/// # Arguments
+///
+/// * `path` - The path to check.
+///
+/// # Errors
+///
+/// An error is returned if the metadata could not be read, including if the path doesn't exist.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::{is_hidden, set_hidden};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_is_hidden");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// fs::write(dir.join("cache.db"), "")?;
+/// assert!(!is_hidden(&dir.join("cache.db"))?);
+///
+/// let hidden = set_hidden(&dir.join("cache.db"))?;
+/// assert!(is_hidden(&hidden)?);
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn is_hidden(path: &Path) -> crate::Result<bool> {
+    let metadata = fs::symlink_metadata(path)?;
+    Ok(has_hidden_attributes(path, &metadata))
+}
+
+/// Hides a file or directory and returns its path afterwards. On Windows, the hidden attribute is
+/// set and the path stays the same. Elsewhere, hiding means renaming it to start with a dot, so
+/// the returned path differs unless it was already hidden.
+///
+/// # Arguments
+///
+/// * `path` - The path to hide.
+///
+/// # Errors
+///
+/// An error is returned if the path doesn't exist or could not be hidden. On Unix, an
+/// `AlreadyExists` error is returned if the dot-prefixed name is taken.
+pub fn set_hidden(path: &Path) -> crate::Result<PathBuf> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+
+        let attributes = fs::symlink_metadata(path)?.file_attributes();
+        preserve::set_file_attributes(path, attributes | FILE_ATTRIBUTE_HIDDEN)?;
+        Ok(path.to_path_buf())
+    }
+    #[cfg(not(windows))]
+    {
+        fs::symlink_metadata(path)?;
+        let Some(name) = path.file_name() else {
+            return Ok(path.to_path_buf());
+        };
+        if name.to_string_lossy().starts_with('.') {
+            return Ok(path.to_path_buf());
+        }
+        let mut hidden_name = std::ffi::OsString::from(".");
+        hidden_name.push(name);
+        let hidden = path.with_file_name(hidden_name);
+        if fs::symlink_metadata(&hidden).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", hidden.display()),
+            )
+            .into());
+        }
+        fs::rename(path, &hidden)?;
+        Ok(hidden)
+    }
+}
+
+/// Reads a whole file into a string, like [`fs::read_to_string`], but errors name the file.
+///
+/// # Arguments
+///
+/// * `path` - The file to read.
+///
+/// # Errors
+///
+/// An [`Error::IoAt`](crate::Error::IoAt) is returned if the file could not be read or isn't
+/// valid UTF-8.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils;
+///
+/// let error = fs_utils::read_to_string("missing/config.toml").unwrap_err();
+/// assert!(error.to_string().contains("missing/config.toml"));
+/// ```
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> crate::Result<String> {
+    let path = path.as_ref();
+    fs::read_to_string(path).map_err(|e| io_at(path, e))
+}
+
+/// Reads a whole file into bytes, like [`fs::read`], but errors name the file.
+///
+/// # Arguments
+///
+/// * `path` - The file to read.
+///
+/// # Errors
+///
+/// An [`Error::IoAt`](crate::Error::IoAt) is returned if the file could not be read.
+pub fn read<P: AsRef<Path>>(path: P) -> crate::Result<Vec<u8>> {
+    let path = path.as_ref();
+    fs::read(path).map_err(|e| io_at(path, e))
+}
+
+/// Writes a whole file, creating or truncating it, like [`fs::write`], but errors name the file.
+/// See [`atomic_write`] for files which must never be left half-written.
+///
+/// # Arguments
+///
+/// * `path` - The file to write.
+/// * `contents` - The contents of the file.
+///
+/// # Errors
+///
+/// An [`Error::IoAt`](crate::Error::IoAt) is returned if the file could not be written.
+pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> crate::Result<()> {
+    let path = path.as_ref();
+    fs::write(path, contents).map_err(|e| io_at(path, e))
+}
+
+/// Wraps an IO error with the path it happened on.
+fn io_at(path: &Path, source: io::Error) -> crate::Error {
+    crate::Error::IoAt {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// The line ending of the platform: `\r\n` on Windows and `\n` elsewhere.
+pub const LINE_ENDING: &str = if cfg!(windows) { "\r\n" } else { "\n" };
+
+/// Appends a line to a file, creating the file and its parent directories if needed. Any line
+/// ending already at the end of `line` is replaced by [`LINE_ENDING`], so the file ends up with
+/// consistent line endings. The line is written with a single call, so concurrent appenders never
+/// interleave within a line on local filesystems.
+///
+/// # Arguments
+///
+/// * `path` - The file to append to.
+/// * `line` - The line to append.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be opened or written.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::{append_line, LINE_ENDING};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let file = std::env::temp_dir().join("dablenutil_append_line/events.txt");
+/// # let _ = std::fs::remove_file(&file);
+/// append_line(&file, "server started")?;
+/// append_line(&file, "player joined\n")?;
+/// assert_eq!(
+///     std::fs::read_to_string(&file)?,
+///     format!("server started{0}player joined{0}", LINE_ENDING)
+/// );
+/// # std::fs::remove_file(&file)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn append_line(path: &Path, line: &str) -> crate::Result<()> {
+    use std::io::Write;
+
+    crate::ensure_parent_dir(path)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let line = line
+        .strip_suffix('\n')
+        .map_or(line, |line| line.strip_suffix('\r').unwrap_or(line));
+    file.write_all(format!("{}{}", line, LINE_ENDING).as_bytes())?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Reads the last `n` lines of a file, oldest first, like `tail -n`. The file is read backwards
+/// in chunks from the end until enough lines are found, so only the end of a large log is read.
+/// Line endings are stripped, and invalid UTF-8 is replaced with `U+FFFD`.
+///
+/// # Arguments
+///
+/// * `path` - The file to read.
+/// * `n` - The number of lines to read. Fewer are returned if the file is shorter.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be read.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::tail;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let file = std::env::temp_dir().join("dablenutil_tail.log");
+/// let log: String = (1..=1000).map(|i| format!("line {}\n", i)).collect();
+/// std::fs::write(&file, log)?;
+/// assert_eq!(tail(&file, 2)?, ["line 999", "line 1000"]);
+/// assert_eq!(tail(&file, 5000)?.len(), 1000);
+/// # std::fs::remove_file(&file)?;
+/// # Ok(())
+/// # }
+/// ```
+#[allow(clippy::naive_bytecount)]
+pub fn tail(path: &Path, n: usize) -> crate::Result<Vec<String>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    const CHUNK_SIZE: u64 = 8 * 1024;
+
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    let mut file = fs::File::open(path)?;
+    let mut start = file.metadata()?.len();
+    let mut chunks = Vec::new();
+    let mut newlines = 0;
+    // one more newline than lines is needed, since the file usually ends with one
+    while start > 0 && newlines <= n {
+        let chunk_size = CHUNK_SIZE.min(start);
+        start -= chunk_size;
+        file.seek(SeekFrom::Start(start))?;
+        let mut chunk = vec![0; usize::try_from(chunk_size).unwrap_or(usize::MAX)];
+        file.read_exact(&mut chunk)?;
+        newlines += chunk.iter().filter(|&&byte| byte == b'\n').count();
+        chunks.push(chunk);
+    }
+    chunks.reverse();
+    let contents = chunks.concat();
+    let text = String::from_utf8_lossy(&contents);
+    let lines: Vec<&str> = text.lines().collect();
+    Ok(lines[lines.len().saturating_sub(n)..]
+        .iter()
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Finds files with identical contents in a directory tree, for deduplication reports. Files are
+/// grouped by size first, and only files sharing a size are hashed with SHA-256, streaming them.
+/// Empty files and symbolic links are ignored. Each group is sorted, and the groups are sorted by
+/// their first path. Only available when the `hash` feature is enabled.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to search.
+///
+/// # Errors
+///
+/// An error is returned if the directory could not be walked or a file could not be read.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::find_duplicates;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_find_duplicates");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("downloads"))?;
+/// fs::write(dir.join("sodium.jar"), "jar")?;
+/// fs::write(dir.join("downloads/sodium (1).jar"), "jar")?;
+/// fs::write(dir.join("downloads/lithium.jar"), "other")?;
+///
+/// let duplicates = find_duplicates(&dir)?;
+/// assert_eq!(
+///     duplicates,
+///     [vec![dir.join("downloads/sodium (1).jar"), dir.join("sodium.jar")]]
+/// );
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "hash")]
+pub fn find_duplicates(dir: &Path) -> crate::Result<Vec<Vec<PathBuf>>> {
+    use std::collections::HashMap;
+
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for entry in walk(dir) {
+        let entry = entry?;
+        let metadata = entry.metadata();
+        if metadata.is_file() && metadata.len() > 0 {
+            by_size
+                .entry(metadata.len())
+                .or_default()
+                .push(entry.into_path());
+        }
+    }
+    let mut duplicates = Vec::new();
+    for paths in by_size.into_values().filter(|paths| paths.len() > 1) {
+        let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for path in paths {
+            by_hash
+                .entry(crate::hash::file_sha256(&path)?)
+                .or_default()
+                .push(path);
+        }
+        duplicates.extend(by_hash.into_values().filter(|paths| paths.len() > 1));
+    }
+    for paths in &mut duplicates {
+        paths.sort();
+    }
+    duplicates.sort();
+    Ok(duplicates)
+}
+
+/// Finds an unused path for a new file, appending ` (1)`, ` (2)`, and so on before the extension,
+/// like browsers do for downloads: `map.zip` becomes `map (1).zip`, and `world.tar.gz` becomes
+/// `world (1).tar.gz`. `desired` is returned if it is free.
+///
+/// Another process can take the path between this check and creating the file; use
+/// [`create_unique`] to find and create the file in one step.
+///
+/// # Arguments
+///
+/// * `desired` - The path the file would ideally have.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::unique_path;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_unique_path");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// assert_eq!(unique_path(&dir.join("map.zip")), dir.join("map.zip"));
+///
+/// fs::write(dir.join("map.zip"), "")?;
+/// fs::write(dir.join("map (1).zip"), "")?;
+/// assert_eq!(unique_path(&dir.join("map.zip")), dir.join("map (2).zip"));
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn unique_path(desired: &Path) -> PathBuf {
+    (0..u64::MAX)
+        .map(|n| numbered_path(desired, n))
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .unwrap_or_else(|| desired.to_path_buf())
+}
+
+/// Creates a new file at an unused path, named like [`unique_path`] does. The file is created
+/// with `create_new`, so the path can't be taken by another process in between. Returns the open
+/// file and its path.
+///
+/// # Arguments
+///
+/// * `desired` - The path the file would ideally have. Its parent directory must exist.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be created for any reason other than the path being
+/// taken.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::create_unique;
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_create_unique");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// let (_, first) = create_unique(&dir.join("export.csv"))?;
+/// let (_, second) = create_unique(&dir.join("export.csv"))?;
+/// assert_eq!(first, dir.join("export.csv"));
+/// assert_eq!(second, dir.join("export (1).csv"));
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_unique(desired: &Path) -> crate::Result<(fs::File, PathBuf)> {
+    for n in 0..u64::MAX {
+        let candidate = numbered_path(desired, n);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => return Ok((file, candidate)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(io::Error::from(io::ErrorKind::AlreadyExists).into())
+}
+
+/// Gets the `n`th candidate for [`unique_path`], where 0 is the path itself.
+fn numbered_path(path: &Path, n: u64) -> PathBuf {
+    if n == 0 {
+        return path.to_path_buf();
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    // a leading dot starts a hidden name, not an extension
+    let split = name
+        .find(".tar.")
+        .filter(|&i| i > 0)
+        .or_else(|| name.rfind('.').filter(|&i| i > 0));
+    let (stem, extension) = name.split_at(split.unwrap_or(name.len()));
+    path.with_file_name(format!("{} ({}){}", stem, n, extension))
+}
+
+/// How [`mirror`] decides whether a file changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Compare {
+    /// Files differ if their sizes or modification times differ. Times within two seconds of each
+    /// other count as equal, since FAT filesystems store them with two second precision.
+    #[default]
+    SizeAndTime,
+    /// Files differ if their sizes or SHA-256 digests differ. Slower, but catches changes which
+    /// kept the size and time. Only available when the `hash` feature is enabled.
+    #[cfg(feature = "hash")]
+    Hash,
+}
+
+/// Options for [`mirror`].
+pub struct MirrorOptions {
+    compare: Compare,
+    delete_extraneous: bool,
+    mode: Mode,
+    exclude: Option<PathFilter>,
+}
+
+impl MirrorOptions {
+    /// Constructs a new `MirrorOptions` with the default values.
+    /// The default values are:
+    /// * `compare`: `Compare::SizeAndTime`
+    /// * `delete_extraneous`: `false`
+    /// * `mode`: `Mode::Execute`
+    /// * `exclude`: `None`
+    pub fn new() -> Self {
+        Self {
+            compare: Compare::SizeAndTime,
+            delete_extraneous: false,
+            mode: Mode::Execute,
+            exclude: None,
+        }
+    }
+
+    /// Gets how changed files are detected.
+    pub fn get_compare(&self) -> Compare {
+        self.compare
+    }
+
+    /// Sets how changed files are detected.
+    ///
+    /// # Arguments
+    /// * `compare` - The comparison.
+    pub fn compare(mut self, compare: Compare) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Gets whether entries missing from the source are deleted from the destination.
+    pub fn get_delete_extraneous(&self) -> bool {
+        self.delete_extraneous
+    }
+
+    /// Sets whether entries missing from the source are deleted from the destination, making it an
+    /// exact copy.
+    ///
+    /// # Arguments
+    /// * `delete_extraneous` - Whether to delete extraneous entries.
+    pub fn delete_extraneous(mut self, delete_extraneous: bool) -> Self {
+        self.delete_extraneous = delete_extraneous;
+        self
+    }
+
+    /// Gets whether the mirror is performed or only planned.
+    pub fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Sets whether the mirror is performed or only planned. In [`Mode::DryRun`], nothing is
+    /// changed, and the returned [`Plan`] lists what would have been.
+    ///
+    /// # Arguments
+    /// * `mode` - The mode.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets a filter for entries to leave alone. It is called with the path of each entry relative
+    /// to the source or destination directory. Excluded entries are neither copied nor deleted,
+    /// and excluding a directory excludes everything in it.
+    ///
+    /// # Arguments
+    /// * `exclude` - Returns `true` for entries to leave alone.
+    pub fn exclude<F: Fn(&Path) -> bool + Send + Sync + 'static>(mut self, exclude: F) -> Self {
+        self.exclude = Some(Box::new(exclude));
+        self
+    }
+
+    /// Checks if an entry is excluded by the [`exclude`](MirrorOptions::exclude) filter.
+    ///
+    /// # Arguments
+    /// * `relative` - The path of the entry relative to the mirrored directories.
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude(relative))
+    }
+}
+
+impl Default for MirrorOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MirrorOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MirrorOptions")
+            .field("compare", &self.compare)
+            .field("delete_extraneous", &self.delete_extraneous)
+            .field("mode", &self.mode)
+            .field("exclude", &self.exclude.is_some())
+            .finish()
+    }
+}
+
+/// Makes `dst` match `src`, like a one-way `rsync`: new and changed files are copied, and with
+/// [`MirrorOptions::delete_extraneous`], entries only in `dst` are deleted. Unchanged files are
+/// left alone, so repeated mirrors are cheap. Copied files get the modification time of their
+/// source, so they compare as unchanged next time. Symbolic links in `src` are followed, and
+/// links leading back into a directory being mirrored are skipped. Returns the [`Plan`] of what
+/// changed.
+///
+/// # Arguments
+///
+/// * `src` - The directory to mirror.
+/// * `dst` - The directory to update. It is created if needed.
+/// * `options` - The options for the mirror.
+///
+/// # Errors
+///
+/// An error is returned if `src` is not a directory, or an entry could not be read, copied, or
+/// deleted. Changes made before the error are left in place. An `InvalidInput` error is returned
+/// if `dst` is `src` or inside it, which would mirror the directory into itself.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::{mirror, MirrorOptions};
+/// use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_mirror");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(dir.join("config"))?;
+/// fs::write(dir.join("config/server.properties"), "motd=hi")?;
+/// fs::create_dir_all(dir.join("remote"))?;
+/// fs::write(dir.join("remote/old.properties"), "")?;
+///
+/// let options = MirrorOptions::new().delete_extraneous(true);
+/// let plan = mirror(&dir.join("config"), &dir.join("remote"), &options)?;
+/// assert_eq!(plan.actions().len(), 2);
+/// assert_eq!(fs::read_to_string(dir.join("remote/server.properties"))?, "motd=hi");
+/// assert!(!dir.join("remote/old.properties").exists());
+///
+/// assert!(mirror(&dir.join("config"), &dir.join("remote"), &options)?.is_empty());
+/// assert!(mirror(&dir.join("config"), &dir.join("config/../config/backup"), &options).is_err());
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn mirror(src: &Path, dst: &Path, options: &MirrorOptions) -> crate::Result<Plan> {
+    if !src.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            format!("{} is not a directory", src.display()),
+        )
+        .into());
+    }
+    ensure_outside(src, dst)?;
+    let mut plan = Plan::new(options.mode);
+    let mut visited = HashSet::new();
+    mirror_inner(src, dst, Path::new(""), options, &mut plan, &mut visited)?;
+    Ok(plan)
+}
+
+/// Mirrors the contents of `src` into `dst`. `relative` is the path of `src` relative to the root
+/// of the mirror, and `visited` holds the canonical paths of the directories being mirrored.
+fn mirror_inner(
+    src: &Path,
+    dst: &Path,
+    relative: &Path,
+    options: &MirrorOptions,
+    plan: &mut Plan,
+    visited: &mut HashSet<PathBuf>,
+) -> crate::Result<()> {
+    let canonical = fs::canonicalize(src)?;
+    if !visited.insert(canonical.clone()) {
+        return Ok(());
+    }
+    let existing = fs::symlink_metadata(dst);
+    if existing.as_ref().is_ok_and(|metadata| !metadata.is_dir()) {
+        remove_entry(dst, plan)?;
+    }
+    if !existing.as_ref().is_ok_and(fs::Metadata::is_dir) {
+        plan.perform(Action::CreateDir(dst.to_path_buf()), || {
+            fs::create_dir_all(dst)
+        })?;
+    }
+    // in a dry run, a destination which isn't a directory yet has nothing to compare against
+    let dst_is_dir = dst.is_dir();
+    let mut names = HashSet::new();
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let relative = relative.join(entry.file_name());
+        if options.is_excluded(&relative) {
+            continue;
+        }
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+        let metadata = match fs::metadata(&from) {
+            Ok(metadata) => metadata,
+            // dangling links have nothing to copy
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        names.insert(entry.file_name());
+        if metadata.is_dir() {
+            mirror_inner(&from, &to, &relative, options, plan, visited)?;
+        } else if !dst_is_dir || is_changed(&from, &metadata, &to, options.compare)? {
+            if fs::symlink_metadata(&to).is_ok_and(|existing| !existing.is_file()) {
+                remove_entry(&to, plan)?;
+            }
+            let action = Action::Copy {
+                from: from.clone(),
+                to: to.clone(),
+            };
+            plan.perform(action, || {
+                fs::copy(&from, &to)?;
+                let copy = fs::OpenOptions::new().write(true).open(&to)?;
+                copy.set_modified(metadata.modified()?)
+            })?;
+        }
+    }
+    if options.delete_extraneous && dst_is_dir {
+        for entry in fs::read_dir(dst)? {
+            let entry = entry?;
+            if !names.contains(&entry.file_name())
+                && !options.is_excluded(&relative.join(entry.file_name()))
+            {
+                remove_entry(&entry.path(), plan)?;
+            }
+        }
+    }
+    visited.remove(&canonical);
+    Ok(())
+}
+
+/// Checks if the file `to` is missing or differs from `from`.
+#[cfg_attr(not(feature = "hash"), allow(unused_variables))]
+fn is_changed(
+    from: &Path,
+    metadata: &fs::Metadata,
+    to: &Path,
+    compare: Compare,
+) -> crate::Result<bool> {
+    let existing = match fs::symlink_metadata(to) {
+        Ok(existing) if existing.is_file() => existing,
+        Ok(_) => return Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e.into()),
+    };
+    if existing.len() != metadata.len() {
+        return Ok(true);
+    }
+    match compare {
+        Compare::SizeAndTime => {
+            let (source, copy) = (metadata.modified()?, existing.modified()?);
+            let difference = source
+                .duration_since(copy)
+                .or_else(|_| copy.duration_since(source))
+                .unwrap_or_default();
+            Ok(difference >= Duration::from_secs(2))
+        }
+        #[cfg(feature = "hash")]
+        Compare::Hash => Ok(crate::hash::file_sha256(from)? != crate::hash::file_sha256(to)?),
+    }
+}
+
+/// Deletes a file, link, or directory tree for [`mirror`].
+fn remove_entry(path: &Path, plan: &mut Plan) -> crate::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        plan.perform(Action::RemoveDir(path.to_path_buf()), || {
+            fs::remove_dir_all(path)
+        })?;
+    } else {
+        plan.perform(Action::Remove(path.to_path_buf()), || fs::remove_file(path))?;
+    }
+    Ok(())
+}
+
+/// An advisory lock on a file, shared between processes, which is released when dropped. On Unix
+/// this is `flock`, and on Windows `LockFileEx`. Advisory locks only keep out processes which also
+/// take the lock; others can still read and write the file, except on Windows, where a locked
+/// region can't be written through other handles.
+///
+/// The lock file is created if it doesn't exist, but its parent directory must exist. The file is
+/// never truncated, so it can hold data too.
+///
+/// # Examples
+/// ```
+/// use dablenutil::fs_utils::FileLock;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let path = std::env::temp_dir().join("dablenutil_file_lock.lock");
+/// let lock = FileLock::lock_exclusive(&path)?;
+/// assert!(FileLock::try_lock(&path)?.is_none());
+/// drop(lock);
+///
+/// let reader = FileLock::lock_shared(&path)?;
+/// assert!(FileLock::try_lock_shared(&path)?.is_some());
+/// assert!(FileLock::try_lock(&path)?.is_none());
+/// reader.unlock()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FileLock {
+    file: fs::File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Takes an exclusive lock on a file, blocking until no other process holds any lock on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the lock file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be opened or locked.
+    pub fn lock_exclusive(path: &Path) -> crate::Result<Self> {
+        Ok(Self::acquire(path, false)?)
+    }
+
+    /// Takes a shared lock on a file, blocking while another process holds an exclusive lock on
+    /// it. Any number of processes can hold shared locks at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the lock file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be opened or locked.
+    pub fn lock_shared(path: &Path) -> crate::Result<Self> {
+        Ok(Self::acquire(path, true)?)
+    }
+
+    /// Tries to take an exclusive lock on a file without blocking, returning `None` if another
+    /// process holds any lock on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the lock file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be opened, or locking fails for another reason.
+    pub fn try_lock(path: &Path) -> crate::Result<Option<Self>> {
+        Ok(Self::try_acquire(path, false)?)
+    }
+
+    /// Tries to take a shared lock on a file without blocking, returning `None` if another process
+    /// holds an exclusive lock on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the lock file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be opened, or locking fails for another reason.
+    pub fn try_lock_shared(path: &Path) -> crate::Result<Option<Self>> {
+        Ok(Self::try_acquire(path, true)?)
+    }
+
+    /// Gets the path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the open lock file, for reading or writing data kept in it.
+    pub fn file(&self) -> &fs::File {
+        &self.file
+    }
+
+    /// Releases the lock, reporting any error instead of ignoring it like dropping does.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the lock could not be released.
+    pub fn unlock(self) -> crate::Result<()> {
+        Ok(self.file.unlock()?)
+    }
+
+    /// Opens a lock file and blocks until it is locked.
+    pub(crate) fn acquire(path: &Path, shared: bool) -> io::Result<Self> {
+        let file = open_lock_file(path)?;
+        if shared {
+            file.lock_shared()?;
+        } else {
+            file.lock()?;
+        }
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Opens a lock file and locks it if no other process holds a conflicting lock.
+    pub(crate) fn try_acquire(path: &Path, shared: bool) -> io::Result<Option<Self>> {
+        let file = open_lock_file(path)?;
+        let locked = if shared {
+            file.try_lock_shared()
+        } else {
+            file.try_lock()
+        };
+        match locked {
+            Ok(()) => Ok(Some(Self {
+                file,
+                path: path.to_path_buf(),
+            })),
+            Err(fs::TryLockError::WouldBlock) => Ok(None),
+            Err(fs::TryLockError::Error(e)) => Err(e),
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // closing the file releases the lock too, but only once every handle to it is closed
+        let _ = self.file.unlock();
+    }
+}
+
+/// Opens a lock file for [`FileLock`], creating it without truncating.
+fn open_lock_file(path: &Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(path)
+}
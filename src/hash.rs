@@ -0,0 +1,241 @@
+//! Checksums of files, for verifying downloads. Only available when the `hash` feature is enabled.
+//! BLAKE3 digests additionally need the `blake3` feature.
+//!
+//! Files are streamed in chunks, so large files are never fully loaded into memory. Digests are
+//! returned as lowercase hex, the format published alongside most downloads.
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+/// A hash algorithm for [`file_digest`] and [`verify_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// SHA-256.
+    Sha256,
+    /// BLAKE3. Only available when the `blake3` feature is enabled.
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+/// The outcome of [`verify_file`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Verification {
+    /// The file matches the expected digest.
+    Ok,
+    /// The file doesn't match the expected digest.
+    Mismatch {
+        /// The expected digest, in lowercase hex.
+        expected: String,
+        /// The actual digest, in lowercase hex.
+        actual: String,
+    },
+}
+
+impl Verification {
+    /// Checks if the file matched the expected digest.
+    pub fn is_ok(&self) -> bool {
+        *self == Verification::Ok
+    }
+
+    /// Turns a mismatch into an [`Error::ChecksumMismatch`](crate::Error::ChecksumMismatch), for
+    /// callers which treat it like any other error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file, for the error message.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file didn't match.
+    pub fn into_result<P: Into<PathBuf>>(self, path: P) -> crate::Result<()> {
+        match self {
+            Verification::Ok => Ok(()),
+            Verification::Mismatch { expected, actual } => Err(crate::Error::ChecksumMismatch {
+                path: path.into(),
+                expected,
+                actual,
+            }),
+        }
+    }
+}
+
+/// Computes the digest of a file with any [`Algorithm`].
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to hash.
+/// * `algorithm` - The hash algorithm.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be read.
+pub fn file_digest(path: &Path, algorithm: Algorithm) -> crate::Result<String> {
+    match algorithm {
+        Algorithm::Sha256 => file_sha256(path),
+        #[cfg(feature = "blake3")]
+        Algorithm::Blake3 => file_blake3(path),
+    }
+}
+
+/// Checks a file against an expected digest, such as one published next to a download. The
+/// comparison ignores case and surrounding whitespace.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to verify.
+/// * `expected_hex` - The expected digest, in hex.
+/// * `algorithm` - The hash algorithm of the digest.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be read. A mismatch is not an error, but
+/// [`Verification::into_result`] turns it into one.
+///
+/// # Examples
+/// ```
+/// use dablenutil::hash::{verify_file, Algorithm, Verification};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let file = std::env::temp_dir().join("dablenutil_verify_file.jar");
+/// std::fs::write(&file, "abc")?;
+/// let expected = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+/// assert!(verify_file(&file, expected, Algorithm::Sha256)?.is_ok());
+///
+/// std::fs::write(&file, "corrupted")?;
+/// let verification = verify_file(&file, expected, Algorithm::Sha256)?;
+/// assert!(matches!(verification, Verification::Mismatch { .. }));
+/// let error = verification.into_result(&file).unwrap_err();
+/// assert_eq!(error.kind(), "checksum_mismatch");
+/// # std::fs::remove_file(&file)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify_file(
+    path: &Path,
+    expected_hex: &str,
+    algorithm: Algorithm,
+) -> crate::Result<Verification> {
+    let expected = expected_hex.trim().to_ascii_lowercase();
+    let actual = file_digest(path, algorithm)?;
+    Ok(if actual == expected {
+        Verification::Ok
+    } else {
+        Verification::Mismatch { expected, actual }
+    })
+}
+
+/// Computes the SHA-256 digest of bytes in memory.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to hash.
+///
+/// # Examples
+/// ```
+/// use dablenutil::hash::sha256;
+///
+/// assert_eq!(
+///     sha256("abc"),
+///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+/// );
+/// ```
+pub fn sha256<B: AsRef<[u8]>>(bytes: B) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+/// Computes the SHA-256 digest of a file.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to hash.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be read.
+///
+/// # Examples
+/// ```
+/// use dablenutil::hash::file_sha256;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let file = std::env::temp_dir().join("dablenutil_file_sha256.txt");
+/// std::fs::write(&file, "abc")?;
+/// assert_eq!(
+///     file_sha256(&file)?,
+///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+/// );
+/// # std::fs::remove_file(&file)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn file_sha256(path: &Path) -> crate::Result<String> {
+    let mut hasher = Sha256::new();
+    stream_file(path, |chunk| hasher.update(chunk))?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Computes the BLAKE3 digest of a file. BLAKE3 is much faster than SHA-256, so prefer it where
+/// the expected digest is under your control. Only available when the `blake3` feature is enabled.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file to hash.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be read.
+///
+/// # Examples
+/// ```
+/// use dablenutil::hash::file_blake3;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let file = std::env::temp_dir().join("dablenutil_file_blake3.txt");
+/// std::fs::write(&file, "")?;
+/// assert_eq!(
+///     file_blake3(&file)?,
+///     "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+/// );
+/// # std::fs::remove_file(&file)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "blake3")]
+pub fn file_blake3(path: &Path) -> crate::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    stream_file(path, |chunk| {
+        hasher.update(chunk);
+    })?;
+    Ok(to_hex(hasher.finalize().as_bytes()))
+}
+
+/// Reads a file in chunks, passing each to `update`.
+fn stream_file<F: FnMut(&[u8])>(path: &Path, mut update: F) -> io::Result<()> {
+    let mut file = fs::File::open(path)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = match file.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        update(&buf[..read]);
+    }
+}
+
+/// Encodes bytes as lowercase hex.
+fn to_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for &byte in bytes {
+        hex.push(char::from(DIGITS[usize::from(byte >> 4)]));
+        hex.push(char::from(DIGITS[usize::from(byte & 0xf)]));
+    }
+    hex
+}
@@ -0,0 +1,183 @@
+//! Heartbeat files, which let watchdogs and other tools tell whether an instance is alive.
+//!
+//! A [`Heartbeat`] rewrites a small JSON file on a background thread:
+//!
+//! ```json
+//! {"pid":4242,"started":1700000000,"timestamp":1700000030,"status":"running"}
+//! ```
+//!
+//! `started` and `timestamp` are seconds since the Unix epoch. The file is removed on a clean
+//! shutdown, so a watchdog which finds it with a `timestamp` older than a few intervals has found a
+//! hung or crashed instance.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::fs_utils::atomic_write;
+
+/// Periodically writes a heartbeat file until stopped or dropped.
+///
+/// # Examples
+/// ```
+/// use dablenutil::health::Heartbeat;
+/// use std::time::Duration;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let path = std::env::temp_dir().join("dablenutil_heartbeat.json");
+/// let heartbeat = Heartbeat::start(&path, Duration::from_secs(10))?;
+/// assert!(std::fs::read_to_string(&path)?.contains(r#""status":"running""#));
+///
+/// heartbeat.set_status("backing up")?;
+/// assert!(std::fs::read_to_string(&path)?.contains(r#""status":"backing up""#));
+///
+/// heartbeat.stop()?;
+/// assert!(!path.exists());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Heartbeat {
+    state: Arc<State>,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// What the heartbeat thread shares with the [`Heartbeat`].
+#[derive(Debug)]
+struct State {
+    path: PathBuf,
+    started: u64,
+    status: Mutex<String>,
+}
+
+impl State {
+    /// Writes the heartbeat file with the current time and status.
+    fn beat(&self) -> crate::Result<()> {
+        let status = self
+            .status
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let contents = format!(
+            "{{\"pid\":{},\"started\":{},\"timestamp\":{},\"status\":{}}}\n",
+            std::process::id(),
+            self.started,
+            unix_time(),
+            crate::json_string(&status)
+        );
+        atomic_write(&self.path, contents, false)
+    }
+}
+
+impl Heartbeat {
+    /// Writes the heartbeat file with the status `running`, then starts a thread which rewrites it
+    /// every `interval`. Errors writing the file on the thread are ignored, since a missing or
+    /// stale heartbeat is exactly what a watchdog looks for.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the heartbeat file. Its parent directory must exist.
+    /// * `interval` - How often to rewrite the file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the first heartbeat could not be written, or the thread could not be
+    /// spawned.
+    pub fn start<P: Into<PathBuf>>(path: P, interval: Duration) -> crate::Result<Self> {
+        let state = Arc::new(State {
+            path: path.into(),
+            started: unix_time(),
+            status: Mutex::new("running".to_string()),
+        });
+        state.beat()?;
+        let (stop, receiver) = mpsc::channel();
+        let thread_state = Arc::clone(&state);
+        let spawned = thread::Builder::new()
+            .name("heartbeat".to_string())
+            .spawn(move || loop {
+                match receiver.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {
+                        let _ = thread_state.beat();
+                    }
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+                }
+            });
+        let thread = match spawned {
+            Ok(thread) => thread,
+            Err(e) => {
+                let _ = crate::remove_file_if_exists(&state.path);
+                return Err(e.into());
+            }
+        };
+        Ok(Self {
+            state,
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+
+    /// Gets the path of the heartbeat file.
+    pub fn path(&self) -> &Path {
+        &self.state.path
+    }
+
+    /// Sets the status reported in the heartbeat file, and rewrites it right away.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The new status, like `idle` or `backing up`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the heartbeat file could not be written.
+    pub fn set_status<S: Into<String>>(&self, status: S) -> crate::Result<()> {
+        *self
+            .state
+            .status
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = status.into();
+        self.state.beat()
+    }
+
+    /// Stops the heartbeat and removes the file. Dropping the `Heartbeat` does the same, but
+    /// ignores errors.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the heartbeat file could not be removed.
+    pub fn stop(mut self) -> crate::Result<()> {
+        self.shutdown()
+    }
+
+    /// Stops the thread, waiting for any write in progress, then removes the file.
+    fn shutdown(&mut self) -> crate::Result<()> {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+            crate::remove_file_if_exists(&self.state.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+/// Gets the current time in seconds since the Unix epoch.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
@@ -0,0 +1,253 @@
+//! Runs user-configured hook scripts at certain points in an application's lifecycle. This module
+//! is only available when the `hooks` feature is enabled.
+//!
+//! Hooks live in a hooks directory. A hook for an event is any file named after the event, with
+//! any extension (e.g. `post-rotate.sh` or `post-rotate.exe`), or any file in a directory named
+//! `{event}.d` (e.g. `post-rotate.d/10-upload.sh`). Hooks run one at a time, sorted by path.
+//!
+//! Scripts are run with an interpreter chosen by their extension:
+//!
+//! * `.sh` - `sh`
+//! * `.ps1` - `powershell -File`
+//! * `.bat` and `.cmd` - `cmd /C`
+//!
+//! Anything else is executed directly.
+
+use std::{
+    ffi::OsStr,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+/// An event hooks can be run for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Event {
+    /// Before a backup is made.
+    PreBackup,
+    /// After logs are rotated.
+    PostRotate,
+    /// When an error occurs.
+    OnError,
+}
+
+impl Event {
+    /// Gets the name of the event, which is also the name of its hooks.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::hooks::Event;
+    ///
+    /// assert_eq!(Event::PostRotate.name(), "post-rotate");
+    /// ```
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::PreBackup => "pre-backup",
+            Event::PostRotate => "post-rotate",
+            Event::OnError => "on-error",
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The context hooks are run in: where to find them and which environment variables to pass.
+///
+/// Every hook receives the event name in the `HOOK_EVENT` environment variable, in addition to
+/// the variables added with [`HookContext::var`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookContext {
+    hooks_dir: PathBuf,
+    vars: Vec<(String, String)>,
+}
+
+impl HookContext {
+    /// Constructs a new `HookContext` with no extra environment variables.
+    ///
+    /// # Arguments
+    ///
+    /// * `hooks_dir` - The directory containing the hooks.
+    pub fn new<P: Into<PathBuf>>(hooks_dir: P) -> Self {
+        Self {
+            hooks_dir: hooks_dir.into(),
+            vars: Vec::new(),
+        }
+    }
+
+    /// Gets the directory containing the hooks.
+    pub fn get_hooks_dir(&self) -> &Path {
+        &self.hooks_dir
+    }
+
+    /// Gets the extra environment variables passed to hooks.
+    pub fn get_vars(&self) -> &[(String, String)] {
+        &self.vars
+    }
+
+    /// Adds an environment variable to pass to hooks.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the variable.
+    /// * `value` - The value of the variable.
+    pub fn var<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.vars.push((key.into(), value.into()));
+        self
+    }
+}
+
+/// The result of running a single hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookOutput {
+    /// The absolute path to the hook.
+    pub path: PathBuf,
+    /// The exit status of the hook, or `None` if it could not be started.
+    pub status: Option<ExitStatus>,
+    /// Everything the hook wrote to stdout.
+    pub stdout: String,
+    /// Everything the hook wrote to stderr, or why it could not be started.
+    pub stderr: String,
+}
+
+impl HookOutput {
+    /// Checks if the hook was started and exited successfully.
+    pub fn success(&self) -> bool {
+        self.status.is_some_and(|status| status.success())
+    }
+}
+
+/// Runs every hook for `event`, waiting for each to finish before starting the next. Hooks run
+/// with the hooks directory as their working directory.
+///
+/// The output of each hook is logged with the `hooks` target: stdout at the info level and stderr
+/// at the warn level. Hooks exiting with a non-zero status are logged as errors but do not stop the
+/// remaining hooks from running. Neither do hooks which could not be started, which are reported
+/// without a status.
+///
+/// If the hooks directory doesn't exist, no hooks are run.
+///
+/// # Arguments
+///
+/// * `event` - The event to run hooks for.
+/// * `context` - The context to run the hooks in.
+///
+/// # Errors
+///
+/// An error is returned if the hooks directory could not be read.
+///
+/// # Examples
+/// ```
+/// use dablenutil::hooks::{self, Event, HookContext};
+/// # use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let hooks_dir = std::env::temp_dir().join("dablenutil_hooks_run");
+/// # fs::create_dir_all(&hooks_dir)?;
+/// let context = HookContext::new(&hooks_dir).var("ARCHIVE", "app.log.gz");
+/// # if cfg!(unix) {
+/// fs::write(hooks_dir.join("post-rotate.sh"), "echo \"$HOOK_EVENT $ARCHIVE\"")?;
+/// let outputs = hooks::run(Event::PostRotate, &context)?;
+/// assert_eq!(outputs.len(), 1);
+/// assert!(outputs[0].success());
+/// assert_eq!(outputs[0].stdout.trim(), "post-rotate app.log.gz");
+/// # }
+/// assert!(hooks::run(Event::PreBackup, &context)?.is_empty());
+/// # fs::remove_dir_all(&hooks_dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn run(event: Event, context: &HookContext) -> crate::Result<Vec<HookOutput>> {
+    // hook paths must stay valid once the working directory is the hooks directory
+    let hooks_dir = std::path::absolute(context.get_hooks_dir())?;
+    let mut outputs = Vec::new();
+    for hook in find_hooks(event, &hooks_dir)? {
+        let result = hook_command(&hook)
+            .current_dir(&hooks_dir)
+            .env("HOOK_EVENT", event.name())
+            .envs(context.get_vars().iter().map(|(k, v)| (k, v)))
+            .output();
+        let output = match result {
+            Ok(output) => HookOutput {
+                path: hook,
+                status: Some(output.status),
+                stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            },
+            Err(e) => HookOutput {
+                path: hook,
+                status: None,
+                stdout: String::new(),
+                stderr: e.to_string(),
+            },
+        };
+        log_output(&output);
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
+
+/// Finds the hooks for `event` in `hooks_dir`, sorted by path.
+fn find_hooks(event: Event, hooks_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fn files_in(dir: &Path) -> io::Result<Vec<PathBuf>> {
+        match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .filter(|path| path.as_ref().map_or(true, |path| path.is_file()))
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+    let mut hooks: Vec<PathBuf> = files_in(hooks_dir)?
+        .into_iter()
+        .filter(|path| path.file_stem() == Some(OsStr::new(event.name())))
+        .collect();
+    hooks.extend(files_in(&hooks_dir.join(format!("{}.d", event.name())))?);
+    hooks.sort();
+    Ok(hooks)
+}
+
+/// Builds the command running `hook`, using an interpreter for known script types.
+fn hook_command(hook: &Path) -> Command {
+    let extension = hook
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    let (program, args): (&OsStr, &[&str]) = match extension.as_deref() {
+        Some("sh") => (OsStr::new("sh"), &[]),
+        Some("ps1") => (
+            OsStr::new("powershell"),
+            &["-NoProfile", "-ExecutionPolicy", "Bypass", "-File"],
+        ),
+        Some("bat" | "cmd") => (OsStr::new("cmd"), &["/C"]),
+        _ => return Command::new(hook),
+    };
+    let mut command = Command::new(program);
+    command.args(args).arg(hook);
+    command
+}
+
+/// Logs the output and exit status of a hook.
+fn log_output(output: &HookOutput) {
+    let name = output
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    for line in output.stdout.lines() {
+        log::info!(target: "hooks", "[{}] {}", name, line);
+    }
+    for line in output.stderr.lines() {
+        log::warn!(target: "hooks", "[{}] {}", name, line);
+    }
+    match output.status {
+        Some(status) if !status.success() => {
+            log::error!(target: "hooks", "[{}] exited with {}", name, status);
+        }
+        Some(_) => {}
+        None => log::error!(target: "hooks", "[{}] could not be started", name),
+    }
+}
@@ -0,0 +1,254 @@
+//! Localization helpers, so GUIs and CLIs can share translations.
+//!
+//! Translations are loaded from catalogs in a directory, one per locale, named `{locale}.ftl`
+//! (e.g. `en.ftl`, `pt-BR.ftl`). Catalogs use a simple subset of the
+//! [Fluent](https://projectfluent.org) syntax, which doubles as a plain key-value format:
+//!
+//! ```text
+//! # comments start with a hash
+//! greeting = Hello, { $name }!
+//! farewell = Goodbye!
+//!     Indented lines continue the previous message.
+//! ```
+//!
+//! Messages missing from a locale fall back to its base language (`pt-BR` to `pt`), then to
+//! English (`en`), and finally to the key itself.
+
+use std::{
+    collections::HashMap,
+    env, fmt, fs, io,
+    path::Path,
+    sync::{PoisonError, RwLock},
+};
+
+/// The locale every lookup falls back to.
+const FALLBACK_LOCALE: &str = "en";
+
+/// The translations used by [`translate`] and [`t!`](crate::t).
+static GLOBAL: RwLock<Option<Translations>> = RwLock::new(None);
+
+/// A set of message catalogs for one locale, including its fallbacks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Translations {
+    locale: String,
+    catalogs: Vec<HashMap<String, String>>,
+}
+
+impl Translations {
+    /// Loads the catalogs for `locale` and its fallbacks from `dir`. Missing catalogs are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory containing the catalogs.
+    /// * `locale` - The locale to load, such as `en-US`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a catalog exists but could not be read.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::i18n::Translations;
+    /// # use std::fs;
+    ///
+    /// # fn main() -> dablenutil::Result<()> {
+    /// let dir = std::env::temp_dir().join("dablenutil_i18n_load");
+    /// # fs::create_dir_all(&dir)?;
+    /// fs::write(dir.join("en.ftl"), "greeting = Hello, { $name }!\nfarewell = Goodbye!")?;
+    /// fs::write(dir.join("fr.ftl"), "greeting = Bonjour, { $name } !")?;
+    /// let translations = Translations::load(&dir, "fr-CA")?;
+    /// assert_eq!(translations.translate("greeting", &[("name", &"Ana")]), "Bonjour, Ana !");
+    /// assert_eq!(translations.translate("farewell", &[]), "Goodbye!");
+    /// assert_eq!(translations.translate("missing", &[]), "missing");
+    /// # fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load<P: AsRef<Path>>(dir: P, locale: &str) -> crate::Result<Self> {
+        let dir = dir.as_ref();
+        let mut candidates = vec![locale];
+        if let Some((language, _)) = locale.split_once('-') {
+            candidates.push(language);
+        }
+        candidates.push(FALLBACK_LOCALE);
+        candidates.dedup();
+        let mut catalogs = Vec::new();
+        for candidate in candidates {
+            match fs::read_to_string(dir.join(format!("{}.ftl", candidate))) {
+                Ok(source) => catalogs.push(parse_catalog(&source)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(Self {
+            locale: locale.to_string(),
+            catalogs,
+        })
+    }
+
+    /// Gets the locale these translations were loaded for.
+    pub fn get_locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Translates a message, replacing `{ $name }` placeables with the matching argument. Unknown
+    /// placeables are left as-is. If no catalog has the message, the key is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the message.
+    /// * `args` - The arguments to fill in, by name.
+    pub fn translate(&self, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        self.catalogs
+            .iter()
+            .find_map(|catalog| catalog.get(key))
+            .map_or_else(|| key.to_string(), |message| format_message(message, args))
+    }
+}
+
+/// Gets the user's preferred locale from the `LC_ALL`, `LC_MESSAGES`, or `LANG` environment
+/// variables, normalized to a tag like `en-US`. Returns `None` if none are set, or if the locale
+/// is `C` or `POSIX`.
+///
+/// # Examples
+/// ```
+/// use dablenutil::i18n::system_locale;
+///
+/// if let Some(locale) = system_locale() {
+///     assert!(!locale.contains('_'));
+/// }
+/// ```
+pub fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| {
+            // strip the encoding and modifier, e.g. `en_US.UTF-8@euro`
+            let tag = value.split(['.', '@']).next().unwrap_or_default();
+            (!tag.is_empty() && tag != "C" && tag != "POSIX").then(|| tag.replace('_', "-"))
+        })
+}
+
+/// Sets the translations used by [`translate`] and [`t!`](crate::t), replacing any previous ones.
+///
+/// # Arguments
+///
+/// * `translations` - The translations to use.
+pub fn set_global(translations: Translations) {
+    *GLOBAL.write().unwrap_or_else(PoisonError::into_inner) = Some(translations);
+}
+
+/// Loads the translations for the [system locale](system_locale) (or English if it's unknown)
+/// from `dir` and sets them as the global translations.
+///
+/// # Arguments
+///
+/// * `dir` - The directory containing the catalogs.
+///
+/// # Errors
+///
+/// An error is returned if a catalog exists but could not be read.
+pub fn init<P: AsRef<Path>>(dir: P) -> crate::Result<()> {
+    let locale = system_locale().unwrap_or_else(|| FALLBACK_LOCALE.to_string());
+    set_global(Translations::load(dir, &locale)?);
+    Ok(())
+}
+
+/// Translates a message with the global translations. If none are set, the key is returned.
+/// [`t!`](crate::t) is usually more convenient.
+///
+/// # Arguments
+///
+/// * `key` - The key of the message.
+/// * `args` - The arguments to fill in, by name.
+pub fn translate(key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    GLOBAL
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map_or_else(|| key.to_string(), |t| t.translate(key, args))
+}
+
+/// Translates a message with the global translations, filling in named arguments.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{i18n, t};
+/// # use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_i18n_t");
+/// # fs::create_dir_all(&dir)?;
+/// fs::write(dir.join("en.ftl"), "files-copied = Copied { $count } files to { $dest }")?;
+/// i18n::set_global(i18n::Translations::load(&dir, "en")?);
+/// assert_eq!(t!("files-copied", count = 3, dest = "backup"), "Copied 3 files to backup");
+/// assert_eq!(t!("missing"), "missing");
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($key:expr $(,)?) => {
+        $crate::i18n::translate($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::translate(
+            $key,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
+
+/// Parses a catalog into a map of keys to messages.
+fn parse_catalog(source: &str) -> HashMap<String, String> {
+    let mut catalog = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    for line in source.lines() {
+        let is_continuation = line.starts_with([' ', '\t']) && !line.trim().is_empty();
+        if is_continuation {
+            if let Some((_, message)) = current.as_mut() {
+                if !message.is_empty() {
+                    message.push('\n');
+                }
+                message.push_str(line.trim());
+            }
+            continue;
+        }
+        catalog.extend(current.take());
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, message)) = line.split_once('=') {
+            current = Some((key.trim().to_string(), message.trim().to_string()));
+        }
+    }
+    catalog.extend(current);
+    catalog
+}
+
+/// Replaces `{ $name }` placeables in `message` with the matching argument.
+fn format_message(message: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut formatted = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        formatted.push_str(&rest[..start]);
+        let placeable = &rest[start..=end];
+        let value = placeable[1..placeable.len() - 1]
+            .trim()
+            .strip_prefix('$')
+            .and_then(|name| args.iter().find(|(arg, _)| *arg == name));
+        match value {
+            Some((_, value)) => formatted.push_str(&value.to_string()),
+            None => formatted.push_str(placeable),
+        }
+        rest = &rest[end + 1..];
+    }
+    formatted.push_str(rest);
+    formatted
+}
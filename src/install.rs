@@ -0,0 +1,525 @@
+//! Helpers for installers: where to install to, and tracking of everything an install creates, so
+//! the app can cleanly remove itself later.
+//!
+//! [`target_paths`] resolves the standard install locations for a per-user or system-wide
+//! install, and [`check_privileges`] fails early, with a hint, when a system-wide install needs
+//! elevation.
+//!
+//! Files, directories, and services created through a [`Manifest`] are recorded in a manifest
+//! file as they are created, so the record survives a crash halfway through an install.
+//! [`Manifest::uninstall`] removes them all again, newest first. With the `registry` feature on
+//! Windows, registry keys are tracked too.
+//!
+//! # Examples
+//! ```
+//! use dablenutil::install::Manifest;
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! let dir = std::env::temp_dir().join("dablenutil_install");
+//! # let _ = std::fs::remove_dir_all(&dir);
+//! std::fs::create_dir_all(&dir)?;
+//! let mut manifest = Manifest::open(&dir.join("manifest.tsv"))?;
+//! manifest.write_file(&dir.join("app/config/settings.toml"), "volume = 11")?;
+//! assert!(dir.join("app/config/settings.toml").exists());
+//!
+//! // later, in the uninstaller
+//! let manifest = Manifest::open(&dir.join("manifest.tsv"))?;
+//! manifest.uninstall()?;
+//! assert!(!dir.join("app").exists());
+//! assert!(!dir.join("manifest.tsv").exists());
+//! # std::fs::remove_dir_all(&dir)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(all(feature = "registry", windows))]
+use crate::registry::{self, Hive};
+use crate::{
+    fs_utils, remove_dir_if_exists, remove_file_if_exists,
+    service::{self, InstalledPaths, ServiceSpec},
+};
+
+/// Who an install is for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// Only the current user, which needs no elevation.
+    #[default]
+    User,
+    /// Every user on the machine, which needs root or administrator rights.
+    System,
+}
+
+/// The standard install locations for an app, as resolved by [`target_paths`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TargetPaths {
+    /// The directory for executables. On Unix, this is shared with other apps and usually on the
+    /// `PATH`; on Windows, it belongs to the app.
+    pub bin_dir: PathBuf,
+    /// The directory for the app's data.
+    pub data_dir: PathBuf,
+    /// The directory for the app's configuration.
+    pub config_dir: PathBuf,
+}
+
+/// Resolves the standard install locations for an app. Nothing is created.
+///
+/// | Platform | Scope | `bin_dir` | `data_dir` | `config_dir` |
+/// |----------|-------|-----------|------------|--------------|
+/// | Linux | User | `~/.local/bin` | `$XDG_DATA_HOME/<app>` | `$XDG_CONFIG_HOME/<app>` |
+/// | Linux | System | `/usr/local/bin` | `/usr/local/share/<app>` | `/etc/<app>` |
+/// | macOS | User | `~/.local/bin` | `~/Library/Application Support/<app>` | same as `data_dir` |
+/// | macOS | System | `/usr/local/bin` | `/Library/Application Support/<app>` | same as `data_dir` |
+/// | Windows | User | `%LOCALAPPDATA%\Programs\<app>` | `%LOCALAPPDATA%\<app>` | `%APPDATA%\<app>` |
+/// | Windows | System | `%ProgramFiles%\<app>` | `%ProgramData%\<app>` | `%ProgramData%\<app>` |
+///
+/// # Arguments
+///
+/// * `app_name` - The name of the app, used as the name of its own directories.
+/// * `scope` - Who the install is for.
+///
+/// # Errors
+///
+/// An error is returned if the home directory or a required environment variable could not be
+/// found, or on platforms other than these.
+///
+/// # Examples
+/// ```
+/// use dablenutil::install::{target_paths, Scope};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let paths = target_paths("dablenparty-server", Scope::User)?;
+/// assert!(paths.data_dir.ends_with("dablenparty-server"));
+/// assert!(paths.config_dir.ends_with("dablenparty-server"));
+///
+/// # #[cfg(all(unix, not(target_os = "macos")))]
+/// # {
+/// // relative XDG directories are invalid, so the default is used instead
+/// std::env::set_var("XDG_CONFIG_HOME", "relative/config");
+/// let paths = target_paths("dablenparty-server", Scope::User)?;
+/// assert!(paths.config_dir.is_absolute());
+/// assert!(paths.config_dir.ends_with(".config/dablenparty-server"));
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+pub fn target_paths(app_name: &str, scope: Scope) -> crate::Result<TargetPaths> {
+    #[cfg(windows)]
+    {
+        let env_dir = |var: &str| {
+            std::env::var_os(var).map(PathBuf::from).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("{} is not set", var))
+            })
+        };
+        Ok(match scope {
+            Scope::User => {
+                let local = env_dir("LOCALAPPDATA")?;
+                TargetPaths {
+                    bin_dir: local.join("Programs").join(app_name),
+                    data_dir: local.join(app_name),
+                    config_dir: env_dir("APPDATA")?.join(app_name),
+                }
+            }
+            Scope::System => {
+                let program_data = env_dir("ProgramData")?.join(app_name);
+                TargetPaths {
+                    bin_dir: env_dir("ProgramFiles")?.join(app_name),
+                    data_dir: program_data.clone(),
+                    config_dir: program_data,
+                }
+            }
+        })
+    }
+    #[cfg(unix)]
+    {
+        let home = || {
+            std::env::home_dir()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))
+        };
+        #[cfg(target_os = "macos")]
+        let paths = match scope {
+            Scope::User => {
+                let support = home()?.join("Library/Application Support").join(app_name);
+                TargetPaths {
+                    bin_dir: home()?.join(".local/bin"),
+                    data_dir: support.clone(),
+                    config_dir: support,
+                }
+            }
+            Scope::System => {
+                let support = Path::new("/Library/Application Support").join(app_name);
+                TargetPaths {
+                    bin_dir: PathBuf::from("/usr/local/bin"),
+                    data_dir: support.clone(),
+                    config_dir: support,
+                }
+            }
+        };
+        #[cfg(not(target_os = "macos"))]
+        let paths = match scope {
+            Scope::User => TargetPaths {
+                bin_dir: home()?.join(".local/bin"),
+                data_dir: crate::xdg_dir("XDG_DATA_HOME", ".local/share")?.join(app_name),
+                config_dir: crate::xdg_dir("XDG_CONFIG_HOME", ".config")?.join(app_name),
+            },
+            Scope::System => TargetPaths {
+                bin_dir: PathBuf::from("/usr/local/bin"),
+                data_dir: Path::new("/usr/local/share").join(app_name),
+                config_dir: Path::new("/etc").join(app_name),
+            },
+        };
+        Ok(paths)
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (app_name, scope);
+        Err(io::Error::from(io::ErrorKind::Unsupported).into())
+    }
+}
+
+/// Checks if the process runs as root on Unix, or as an elevated administrator on Windows.
+/// Elsewhere, this is always `false`.
+pub fn is_elevated() -> bool {
+    #[cfg(unix)]
+    {
+        extern "C" {
+            fn geteuid() -> u32;
+        }
+        // SAFETY: geteuid has no preconditions and can't fail
+        unsafe { geteuid() == 0 }
+    }
+    #[cfg(windows)]
+    {
+        // SAFETY: IsUserAnAdmin has no preconditions
+        unsafe { windows_sys::Win32::UI::Shell::IsUserAnAdmin() != 0 }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        false
+    }
+}
+
+/// Checks that the process may install for `scope`, so installers can fail before changing
+/// anything.
+///
+/// # Arguments
+///
+/// * `scope` - Who the install is for.
+///
+/// # Errors
+///
+/// An [`ElevationRequired`](crate::Error::ElevationRequired) error, suggesting how to elevate, is
+/// returned if `scope` is [`Scope::System`] and the process isn't [elevated](is_elevated).
+///
+/// # Examples
+/// ```
+/// use dablenutil::install::{check_privileges, Scope};
+///
+/// assert!(check_privileges(Scope::User).is_ok());
+/// ```
+pub fn check_privileges(scope: Scope) -> crate::Result<()> {
+    if scope == Scope::User || is_elevated() {
+        return Ok(());
+    }
+    let hint = if cfg!(windows) {
+        "installing for all users needs administrator rights; run this again as administrator"
+    } else {
+        "installing for all users needs root; run this again with sudo"
+    };
+    Err(crate::Error::ElevationRequired(hint.to_string()))
+}
+
+/// Something created by an install, recorded in a [`Manifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Entry {
+    /// A file.
+    File(PathBuf),
+    /// A directory, which is removed along with everything in it.
+    Dir(PathBuf),
+    /// A service installed with [`service::install`].
+    Service {
+        /// The name of the service.
+        name: String,
+        /// Whether it is a per-user service.
+        user: bool,
+    },
+    /// A registry key, which is removed along with its values and subkeys. Only available on
+    /// Windows when the `registry` feature is enabled.
+    #[cfg(all(feature = "registry", windows))]
+    RegistryKey {
+        /// The root key.
+        hive: Hive,
+        /// The path of the key within `hive`.
+        key: String,
+    },
+}
+
+impl Entry {
+    /// Removes what the entry refers to, succeeding if it is already gone.
+    fn remove(&self) -> crate::Result<()> {
+        match self {
+            Entry::File(path) => remove_file_if_exists(path),
+            Entry::Dir(path) => remove_dir_if_exists(path),
+            Entry::Service { name, user } => {
+                service::uninstall(&ServiceSpec::new(name.as_str()).user(*user))
+            }
+            #[cfg(all(feature = "registry", windows))]
+            Entry::RegistryKey { hive, key } => {
+                registry::delete_key(*hive, key)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A record of everything an install created, saved to a file after every change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl Manifest {
+    /// Opens the manifest saved at `path`, or starts an empty one if it doesn't exist yet. The file
+    /// isn't created until something is recorded.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The manifest file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file exists but could not be read or is malformed.
+    pub fn open(path: &Path) -> crate::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed install manifest");
+        let mut entries = Vec::new();
+        for line in contents.lines() {
+            let mut fields = line.split('\t');
+            let entry = match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                (Some("file"), Some(path), None, None) => Entry::File(path.into()),
+                (Some("dir"), Some(path), None, None) => Entry::Dir(path.into()),
+                (Some("service"), Some(name), Some(scope @ ("user" | "system")), None) => {
+                    Entry::Service {
+                        name: name.to_string(),
+                        user: scope == "user",
+                    }
+                }
+                #[cfg(all(feature = "registry", windows))]
+                (Some("registry"), Some(hive @ ("HKCU" | "HKLM")), Some(key), None) => {
+                    Entry::RegistryKey {
+                        hive: if hive == "HKCU" {
+                            Hive::CurrentUser
+                        } else {
+                            Hive::LocalMachine
+                        },
+                        key: key.to_string(),
+                    }
+                }
+                _ => return Err(invalid().into()),
+            };
+            entries.push(entry);
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Gets the path of the manifest file.
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the recorded entries, oldest first.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Records an entry created some other way, and saves the manifest. Entries which are already
+    /// recorded are not recorded again.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The entry to record.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the manifest could not be saved.
+    pub fn record(&mut self, entry: Entry) -> crate::Result<()> {
+        if !self.entries.contains(&entry) {
+            self.entries.push(entry);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Creates a directory and its missing parents. Only the outermost directory which didn't
+    /// exist yet is recorded, since removing it removes the rest.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to create.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the directory could not be created or the manifest could not be
+    /// saved.
+    pub fn create_dir(&mut self, dir: &Path) -> crate::Result<()> {
+        let Some(outermost) = dir
+            .ancestors()
+            .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+            .last()
+        else {
+            return Ok(());
+        };
+        let outermost = outermost.to_path_buf();
+        fs::create_dir_all(dir)?;
+        self.record(Entry::Dir(outermost))
+    }
+
+    /// Writes a file atomically (see [`fs_utils::atomic_write`]), creating its parent directories,
+    /// and records it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to write.
+    /// * `contents` - The contents to write.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be written or the manifest could not be saved.
+    pub fn write_file<C: AsRef<[u8]>>(&mut self, path: &Path, contents: C) -> crate::Result<()> {
+        if let Some(parent) = path.parent() {
+            self.create_dir(parent)?;
+        }
+        fs_utils::atomic_write(path, contents, false)?;
+        self.record(Entry::File(path.to_path_buf()))
+    }
+
+    /// Copies a file, creating the parent directories of `to`, and records the copy.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The file to copy.
+    /// * `to` - The destination.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be copied or the manifest could not be saved.
+    pub fn copy_file(&mut self, from: &Path, to: &Path) -> crate::Result<()> {
+        if let Some(parent) = to.parent() {
+            self.create_dir(parent)?;
+        }
+        fs::copy(from, to)?;
+        self.record(Entry::File(to.to_path_buf()))
+    }
+
+    /// Installs a service with [`service::install`] and records it.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - The service to install.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the service could not be installed or the manifest could not be
+    /// saved.
+    pub fn install_service(&mut self, spec: &ServiceSpec) -> crate::Result<InstalledPaths> {
+        let paths = service::install(spec)?;
+        self.record(Entry::Service {
+            name: spec.get_name().to_string(),
+            user: spec.get_user(),
+        })?;
+        Ok(paths)
+    }
+
+    /// Writes a registry string value with [`registry::write_string`] and records its key. Only
+    /// available on Windows when the `registry` feature is enabled.
+    ///
+    /// The whole key is removed on uninstall, so it should belong to the app.
+    ///
+    /// # Arguments
+    ///
+    /// * `hive` - The root key.
+    /// * `key` - The path of the key within `hive`.
+    /// * `name` - The name of the value, or `""` for the default value of the key.
+    /// * `value` - The value to write.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the value could not be written or the manifest could not be saved.
+    #[cfg(all(feature = "registry", windows))]
+    pub fn write_registry_string(
+        &mut self,
+        hive: Hive,
+        key: &str,
+        name: &str,
+        value: &str,
+    ) -> crate::Result<()> {
+        registry::write_string(hive, key, name, value)?;
+        self.record(Entry::RegistryKey {
+            hive,
+            key: key.to_string(),
+        })
+    }
+
+    /// Removes everything recorded, newest first, and then the manifest file itself.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned as soon as an entry could not be removed. That entry and the ones
+    /// before it stay in the saved manifest, so the uninstall can be retried.
+    pub fn uninstall(mut self) -> crate::Result<()> {
+        while let Some(entry) = self.entries.last() {
+            if let Err(e) = entry.remove() {
+                self.save()?;
+                return Err(e);
+            }
+            self.entries.pop();
+        }
+        remove_file_if_exists(&self.path)
+    }
+
+    /// Saves the manifest, with one tab-separated entry per line.
+    fn save(&self) -> crate::Result<()> {
+        let mut contents = String::new();
+        for entry in &self.entries {
+            let line = match entry {
+                Entry::File(path) => format!(
+                    "file\t{}\n",
+                    crate::tsv_field(path.to_str(), &path.display(), "an install manifest")?
+                ),
+                Entry::Dir(path) => format!(
+                    "dir\t{}\n",
+                    crate::tsv_field(path.to_str(), &path.display(), "an install manifest")?
+                ),
+                Entry::Service { name, user } => format!(
+                    "service\t{}\t{}\n",
+                    crate::tsv_field(Some(name), name, "an install manifest")?,
+                    if *user { "user" } else { "system" }
+                ),
+                #[cfg(all(feature = "registry", windows))]
+                Entry::RegistryKey { hive, key } => format!(
+                    "registry\t{}\t{}\n",
+                    match hive {
+                        Hive::CurrentUser => "HKCU",
+                        Hive::LocalMachine => "HKLM",
+                    },
+                    crate::tsv_field(Some(key), key, "an install manifest")?
+                ),
+            };
+            contents.push_str(&line);
+        }
+        crate::ensure_parent_dir(&self.path)?;
+        fs_utils::atomic_write(&self.path, contents, true)
+    }
+}
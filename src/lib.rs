@@ -8,40 +8,290 @@
 //!
 //! # Features
 //!
+//! * `android` - Enables `logging::init_android_logger` for logging to logcat on Android.
+//! * `archive` - Enables the `archive` module for creating and extracting archives.
+//! * `associations` - Enables the `associations` module for registering file type handlers.
+//! * `audit` - Enables the `audit` module for tamper-evident audit logs.
+//! * `autostart` - Enables the `autostart` module for starting apps when the user logs in.
+//! * `blake3` - Enables `hash::file_blake3` for BLAKE3 checksums.
+//! * `bundle` - Enables the `bundle` module for exporting and importing app data.
+//! * `clap` - Enables the `cli` module with reusable `clap` arguments.
+//! * `compress` - Enables the `compress` module for gzip compression of files.
+//! * `crash_report` - Enables `logging::create_crash_report` for bundling logs into a zip file.
+//! * `crypto` - Enables the `crypto` module for signature verification.
+//! * `error_reporter` - Enables `logging::report` for forwarding error-level records to a tracker.
+//! * `hash` - Enables the `hash` module for file checksums, and `fs_utils::find_duplicates`.
+//! * `hooks` - Enables the `hooks` module for running user-configured hook scripts.
 //! * `logging` - Enables the `logging` module.
+//! * `plugins` - Enables the `plugins` module for loading plugins from dynamic libraries.
+//! * `registry` - Enables the `registry` module for reading and writing the Windows registry.
+//! * `rename` - Enables the `rename` module for batch renaming with pattern rules.
+//! * `tauri` - Implements `serde::Serialize` for `Error`, so `Result` can be returned from Tauri
+//!   commands as is.
+//! * `test-util` - Enables the `testutil` module with helpers for testing filesystem code.
+//! * `time_utils` - Enables the `time_utils` module for timestamp formatting and parsing.
 //! * `tokio` - Enables the `tokio` module for async utils.
+//! * `tracing` - Enables `logging::init_tracing_subscriber` for projects using `tracing`.
+//! * `wasm` - Enables the `console` module for logging to the browser console on `wasm32`.
+//! * `wasm_plugins` - Enables the `plugins::wasm` module for running sandboxed WebAssembly plugins.
+//! * `watch` - Enables the `watch` module for debounced filesystem change events.
+//! * `xattr` - Enables the `xattr` module for extended attributes and alternate data streams.
 
 #![warn(clippy::all, clippy::pedantic)]
 #![allow(clippy::uninlined_format_args, clippy::must_use_candidate, clippy::return_self_not_must_use)]
 
 use const_format::formatcp;
-use std::{env, error, fmt, fs::create_dir_all, io, path::Path};
+use std::{
+    env, error, fmt,
+    fs::{create_dir_all, remove_dir_all, remove_file},
+    io,
+    path::{Path, PathBuf},
+};
 
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod assets;
+#[cfg(feature = "associations")]
+pub mod associations;
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "autostart")]
+pub mod autostart;
+pub mod backoff;
+pub mod bench;
+#[cfg(feature = "bundle")]
+pub mod bundle;
+pub mod cache;
+#[cfg(feature = "clap")]
+pub mod cli;
+pub mod clock;
+#[cfg(feature = "compress")]
+pub mod compress;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod console;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+pub mod dry_run;
+pub mod fs_utils;
+#[cfg(feature = "hash")]
+pub mod hash;
+pub mod health;
+#[cfg(feature = "hooks")]
+pub mod hooks;
+pub mod i18n;
+pub mod install;
 #[cfg(feature = "logging")]
 pub mod logging;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+pub mod preserve;
+pub mod progress;
+pub mod rand_utils;
+#[cfg(all(feature = "registry", windows))]
+pub mod registry;
+#[cfg(feature = "rename")]
+pub mod rename;
+pub mod service;
+pub mod single_instance;
+pub mod stats;
+pub mod temp;
+#[cfg(feature = "test-util")]
+pub mod testutil;
+#[cfg(feature = "time_utils")]
+pub mod time_utils;
 #[cfg(feature = "tokio")]
 pub mod tokio;
+pub mod undo;
+pub mod vfs;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "xattr")]
+pub mod xattr;
 
 /// A custom error type for this crate.
+///
+/// With the `tauri` feature, errors implement `serde::Serialize` as a `{ "kind", "message" }`
+/// object, so the frontend can match on [`Error::kind`]. Tauri converts any serializable error into
+/// its `InvokeError` through a blanket `From` impl, which rules out a separate one here, so
+/// [`Result`] can be returned directly from Tauri commands without a wrapper or attribute:
+///
+/// ```ignore
+/// #[tauri::command]
+/// fn load_settings(path: std::path::PathBuf) -> dablenutil::Result<String> {
+///     Ok(std::fs::read_to_string(path)?)
+/// }
+/// ```
 #[derive(Debug)]
 pub enum Error {
     /// Wraps an `io::Error`.
     Io(io::Error),
-    /// Wraps an error from `simplelog`.
-    #[cfg(feature = "logging")]
+    /// Wraps an `io::Error` along with the path it happened on, so the message names the file.
+    IoAt {
+        /// The path the operation was on.
+        path: PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// Wraps an error from `log` when setting the global logger.
+    #[cfg(any(feature = "logging", feature = "wasm"))]
     Logging(log::SetLoggerError),
+    /// Wraps an error from `minisign-verify`.
+    #[cfg(feature = "crypto")]
+    Signature(minisign_verify::Error),
+    /// Wraps an error from `tracing-subscriber`.
+    #[cfg(feature = "tracing")]
+    Tracing(tracing_subscriber::util::TryInitError),
+    /// Wraps an error from `libloading`.
+    #[cfg(feature = "plugins")]
+    Plugin(libloading::Error),
+    /// A plugin was built against a different plugin ABI version.
+    #[cfg(feature = "plugins")]
+    PluginAbiMismatch {
+        /// The path to the plugin.
+        path: PathBuf,
+        /// The ABI version supported by this crate.
+        expected: u32,
+        /// The ABI version reported by the plugin.
+        found: u32,
+    },
+    /// Wraps an error from `wasmi`.
+    #[cfg(feature = "wasm_plugins")]
+    Wasm(wasmi::Error),
+    /// Wraps an error from `zip`.
+    #[cfg(any(feature = "archive", feature = "crash_report"))]
+    Zip(zip::result::ZipError),
+    /// Wraps an error from `regex`.
+    #[cfg(feature = "rename")]
+    Regex(regex::Error),
+    /// Wraps an error from `notify`.
+    #[cfg(feature = "watch")]
+    Watch(notify::Error),
+    /// The checksum of a file didn't match the expected digest.
+    #[cfg(feature = "hash")]
+    ChecksumMismatch {
+        /// The path to the file.
+        path: PathBuf,
+        /// The expected digest, in lowercase hex.
+        expected: String,
+        /// The actual digest, in lowercase hex.
+        actual: String,
+    },
+    /// A chain of symbolic links loops, or is longer than allowed. Holds the link where following
+    /// stopped.
+    SymlinkLoop(PathBuf),
+    /// Another instance of the app is already running. Holds the name of the app.
+    AlreadyRunning(String),
+    /// An operation needs root or administrator rights which the process doesn't have. The message
+    /// suggests how to elevate.
+    ElevationRequired(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+impl Error {
+    /// Gets a short, stable name for the kind of error, suitable for matching on in IPC clients.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::Error;
+    ///
+    /// let error = Error::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+    /// assert_eq!(error.kind(), "io");
+    /// ```
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Io(_) | Error::IoAt { .. } => "io",
+            #[cfg(any(feature = "logging", feature = "wasm"))]
+            Error::Logging(_) => "logging",
+            #[cfg(feature = "crypto")]
+            Error::Signature(_) => "signature",
+            #[cfg(feature = "tracing")]
+            Error::Tracing(_) => "tracing",
+            #[cfg(feature = "plugins")]
+            Error::Plugin(_) => "plugin",
+            #[cfg(feature = "plugins")]
+            Error::PluginAbiMismatch { .. } => "plugin_abi_mismatch",
+            #[cfg(feature = "wasm_plugins")]
+            Error::Wasm(_) => "wasm",
+            #[cfg(any(feature = "archive", feature = "crash_report"))]
+            Error::Zip(_) => "zip",
+            #[cfg(feature = "rename")]
+            Error::Regex(_) => "regex",
+            #[cfg(feature = "watch")]
+            Error::Watch(_) => "watch",
+            #[cfg(feature = "hash")]
+            Error::ChecksumMismatch { .. } => "checksum_mismatch",
+            Error::SymlinkLoop(_) => "symlink_loop",
+            Error::AlreadyRunning(_) => "already_running",
+            Error::ElevationRequired(_) => "elevation_required",
+        }
+    }
+}
+
 impl error::Error for Error {}
 
+#[cfg(feature = "tauri")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut payload = serializer.serialize_struct("Error", 2)?;
+        payload.serialize_field("kind", self.kind())?;
+        payload.serialize_field("message", &self.to_string())?;
+        payload.end()
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Io(e) => write!(f, "IO Error: {}", e),
-            #[cfg(feature = "logging")]
+            Error::IoAt { path, source } => write!(f, "IO Error: {}: {}", path.display(), source),
+            #[cfg(any(feature = "logging", feature = "wasm"))]
             Error::Logging(e) => write!(f, "Logging Error: {}", e),
+            #[cfg(feature = "crypto")]
+            Error::Signature(e) => write!(f, "Signature Error: {}", e),
+            #[cfg(feature = "tracing")]
+            Error::Tracing(e) => write!(f, "Logging Error: {}", e),
+            #[cfg(feature = "plugins")]
+            Error::Plugin(e) => write!(f, "Plugin Error: {}", e),
+            #[cfg(feature = "plugins")]
+            Error::PluginAbiMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Plugin Error: {} uses ABI version {}, expected {}",
+                path.display(),
+                found,
+                expected
+            ),
+            #[cfg(feature = "wasm_plugins")]
+            Error::Wasm(e) => write!(f, "Wasm Error: {}", e),
+            #[cfg(any(feature = "archive", feature = "crash_report"))]
+            Error::Zip(e) => write!(f, "Zip Error: {}", e),
+            #[cfg(feature = "rename")]
+            Error::Regex(e) => write!(f, "Regex Error: {}", e),
+            #[cfg(feature = "watch")]
+            Error::Watch(e) => write!(f, "Watch Error: {}", e),
+            #[cfg(feature = "hash")]
+            Error::ChecksumMismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Checksum Mismatch: {} has digest {}, expected {}",
+                path.display(),
+                actual,
+                expected
+            ),
+            Error::SymlinkLoop(path) => write!(f, "Symlink Loop: {}", path.display()),
+            Error::AlreadyRunning(app_name) => {
+                write!(f, "Already Running: another instance of {} is running", app_name)
+            }
+            Error::ElevationRequired(hint) => write!(f, "Elevation Required: {}", hint),
         }
     }
 }
@@ -52,13 +302,65 @@ impl From<io::Error> for Error {
     }
 }
 
-#[cfg(feature = "logging")]
+#[cfg(any(feature = "logging", feature = "wasm"))]
 impl From<log::SetLoggerError> for Error {
     fn from(e: log::SetLoggerError) -> Self {
         Error::Logging(e)
     }
 }
 
+#[cfg(feature = "crypto")]
+impl From<minisign_verify::Error> for Error {
+    fn from(e: minisign_verify::Error) -> Self {
+        Error::Signature(e)
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl From<tracing_subscriber::util::TryInitError> for Error {
+    fn from(e: tracing_subscriber::util::TryInitError) -> Self {
+        Error::Tracing(e)
+    }
+}
+
+#[cfg(feature = "plugins")]
+impl From<libloading::Error> for Error {
+    fn from(e: libloading::Error) -> Self {
+        Error::Plugin(e)
+    }
+}
+
+#[cfg(feature = "wasm_plugins")]
+impl From<wasmi::Error> for Error {
+    fn from(e: wasmi::Error) -> Self {
+        Error::Wasm(e)
+    }
+}
+
+#[cfg(any(feature = "archive", feature = "crash_report"))]
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Self {
+        Error::Zip(e)
+    }
+}
+
+#[cfg(feature = "rename")]
+impl From<regex::Error> for Error {
+    fn from(e: regex::Error) -> Self {
+        Error::Regex(e)
+    }
+}
+
+#[cfg(feature = "watch")]
+impl From<notify::Error> for Error {
+    fn from(e: notify::Error) -> Self {
+        Error::Watch(e)
+    }
+}
+
+/// The `_{OS}_{ARCH}` suffix shared by platform-specific file names.
+const PLATFORM_SUFFIX: &str = formatcp!("_{}_{}", env::consts::OS, env::consts::ARCH);
+
 /// Gets a platform-specific executable name based on the `CARGO_PKG_NAME` environment variable.
 ///
 /// This function is generated at compile-time and can be used in `const` contexts.
@@ -77,13 +379,7 @@ impl From<log::SetLoggerError> for Error {
 #[must_use]
 pub const fn platform_specific_executable_name() -> &'static str {
     const PACKAGE_NAME: &str = env!("CARGO_PKG_NAME");
-    formatcp!(
-        "{}_{}_{}{}",
-        PACKAGE_NAME,
-        env::consts::OS,
-        env::consts::ARCH,
-        env::consts::EXE_SUFFIX
-    )
+    formatcp!("{}{}{}", PACKAGE_NAME, PLATFORM_SUFFIX, env::consts::EXE_SUFFIX)
 }
 
 /// Synchronously creates a directory and all of its parent directories if they don't exist.
@@ -123,3 +419,225 @@ pub fn create_dir_if_not_exists(dir: &Path) -> Result<()> {
         Ok(())
     }
 }
+
+/// Synchronously creates the parent directory of a file path and all of its parent directories if
+/// they don't exist. Paths without a parent, like a bare file name, are left alone.
+///
+/// # Arguments
+///
+/// * `file` - The path to the file whose parent directory to create.
+///
+/// # Errors
+///
+/// An error is returned if the directory could not be created for some reason
+/// (see `fs::create_dir_all` for more information), ignoring the error when the
+/// directory already exists.
+///
+/// # Examples
+/// ```
+/// use dablenutil::ensure_parent_dir;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_ensure_parent_dir");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// let file = dir.join("config/nested/settings.toml");
+/// ensure_parent_dir(&file)?;
+/// std::fs::write(&file, "")?;
+/// assert!(file.exists());
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn ensure_parent_dir(file: &Path) -> Result<()> {
+    match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => create_dir_if_not_exists(parent),
+        _ => Ok(()),
+    }
+}
+
+/// Synchronously removes a directory and everything in it if it exists.
+/// If the directory doesn't exist, the error is ignored.
+///
+/// # Arguments
+///
+/// * `dir` - The path to the directory to remove.
+///
+/// # Errors
+///
+/// An error is returned if the directory could not be removed for some reason
+/// (see `fs::remove_dir_all` for more information), ignoring the error when the
+/// directory doesn't exist.
+///
+/// # Examples
+/// ```
+/// use dablenutil::remove_dir_if_exists;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let path = std::env::temp_dir().join("dablenutil_remove_dir_if_exists");
+/// std::fs::create_dir_all(path.join("nested"))?;
+/// remove_dir_if_exists(&path)?;
+/// assert!(!path.exists());
+/// remove_dir_if_exists(&path)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn remove_dir_if_exists(dir: &Path) -> Result<()> {
+    match remove_dir_all(dir) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Synchronously removes a file if it exists.
+/// If the file doesn't exist, the error is ignored.
+///
+/// # Arguments
+///
+/// * `file` - The path to the file to remove.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be removed for some reason
+/// (see `fs::remove_file` for more information), ignoring the error when the
+/// file doesn't exist.
+///
+/// # Examples
+/// ```
+/// use dablenutil::remove_file_if_exists;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let path = std::env::temp_dir().join("dablenutil_remove_file_if_exists.txt");
+/// std::fs::write(&path, "")?;
+/// remove_file_if_exists(&path)?;
+/// assert!(!path.exists());
+/// remove_file_if_exists(&path)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn remove_file_if_exists(file: &Path) -> Result<()> {
+    match remove_file(file) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a field can be saved in the tab-separated files written by the `undo` and
+/// `install` modules, which have no escaping: it must be valid UTF-8, non-empty, and free of tabs
+/// and newlines. `file` names the kind of file in the error, e.g. "a journal".
+pub(crate) fn tsv_field<'a>(
+    field: Option<&'a str>,
+    display: &dyn fmt::Display,
+    file: &str,
+) -> io::Result<&'a str> {
+    field
+        .filter(|field| !field.is_empty() && !field.contains(['\t', '\n', '\r']))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} cannot be saved in {}", display, file),
+            )
+        })
+}
+
+/// Quotes a string as a JSON string literal, for the modules which write small JSON documents
+/// without depending on `serde`.
+pub(crate) fn json_string(s: &str) -> String {
+    use std::fmt::Write;
+
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", u32::from(c));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escapes the characters with special meaning in XML text and attributes.
+#[cfg(any(feature = "associations", target_os = "macos"))]
+pub(crate) fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Builds a launchd plist which runs `exec` with `args` at load. `extra` holds any further
+/// `<key>`/value lines for the top-level `<dict>`, already escaped.
+#[cfg(target_os = "macos")]
+pub(crate) fn launchd_plist(label: &str, exec: &Path, args: &[String], extra: &str) -> String {
+    let mut arguments = String::new();
+    for arg in std::iter::once(exec.to_string_lossy()).chain(args.iter().map(Into::into)) {
+        arguments.push_str("    <string>");
+        arguments.push_str(&xml_escape(&arg));
+        arguments.push_str("</string>\n");
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{}</string>
+  <key>ProgramArguments</key>
+  <array>
+{}  </array>
+  <key>RunAtLoad</key>
+  <true/>
+{}</dict>
+</plist>
+"#,
+        xml_escape(label),
+        arguments,
+        extra
+    )
+}
+
+/// Quotes an argument for the `Exec` key of a `.desktop` entry. A literal `%` is doubled, so it
+/// isn't taken for a field code like `%f`.
+#[cfg(any(
+    all(feature = "autostart", not(windows), not(target_os = "macos")),
+    all(feature = "associations", unix, not(target_os = "macos"))
+))]
+pub(crate) fn desktop_exec_arg(arg: &str) -> String {
+    // quoted arguments escape these characters with a backslash, and then backslashes are escaped
+    // again as for any other string value
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        } else if c == '%' {
+            quoted.push('%');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted.replace('\\', "\\\\")
+}
+
+/// Gets the XDG base directory named by the `var` environment variable, or `fallback` in the
+/// home directory if it is unset, empty, or relative. The XDG spec says relative paths are invalid
+/// and must be ignored.
+#[cfg(any(
+    all(unix, not(target_os = "macos")),
+    all(feature = "autostart", not(windows), not(target_os = "macos"))
+))]
+pub(crate) fn xdg_dir(var: &str, fallback: &str) -> io::Result<PathBuf> {
+    match env::var_os(var).map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => Ok(dir),
+        _ => env::home_dir()
+            .map(|home| home.join(fallback))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory")),
+    }
+}
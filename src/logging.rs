@@ -1,29 +1,88 @@
 //! Contains logging utilities. This module is only available when the `logging` feature is enabled.
 //!
-//! Currently, this module contains a function to initialize a logger and a function to rotate logs.
+//! [`init_simple_logger`] sets up a terminal logger and a file logger writing to a live log file,
+//! and [`rotate_logs`] archives and compresses the log files of previous runs. On top of that:
+//!
+//! * `init_tracing_subscriber` sets up the same sinks for projects using `tracing` (with the
+//!   `tracing` feature).
+//! * [`LoggingConfig::rate_limit`] and [`LoggingConfig::dedup_window`] drop records from targets
+//!   which log too much, or repeat themselves.
+//! * [`LoggerHandle`] attaches and removes extra sinks after the logger is set up.
+//! * [`reopen_log_file`] and `install_sighup_rotation` (on Unix) rotate the log while the app runs.
+//! * [`install_panic_hook`] logs panics, and `create_crash_report` bundles the logs into a zip
+//!   file (with the `crash_report` feature).
 
 use std::{
+    collections::HashMap,
     fs,
-    io::Write,
+    io::{self, Write},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock, PoisonError, RwLock,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
 use chrono::{DateTime, Local};
-use flate2::{Compression, GzBuilder};
-use log::LevelFilter;
+use log::{Level, LevelFilter, Log, Metadata, Record};
 use simplelog::{
-    format_description, ColorChoice, CombinedLogger, TermLogger, TerminalMode, ThreadLogMode,
-    WriteLogger,
+    format_description, ColorChoice, CombinedLogger, SharedLogger, TermLogger, TerminalMode,
+    ThreadLogMode, WriteLogger,
+};
+
+use crate::{
+    compress, create_dir_if_not_exists,
+    dry_run::{Action, Mode, Plan},
+    fs_utils::FileLock,
+    time_utils::RunClock,
 };
 
-use crate::create_dir_if_not_exists;
+#[cfg(all(feature = "android", target_os = "android"))]
+mod android;
+#[cfg(feature = "error_reporter")]
+pub mod report;
+
+#[cfg(all(feature = "android", target_os = "android"))]
+pub use android::init_android_logger;
+
+/// The live log file of the logger set up by [`init_simple_logger`] or
+/// [`init_async_logger`](crate::tokio::init_async_logger), shared with its [`LiveLogWriter`] so it
+/// can be swapped by [`reopen_log_file`].
+static LIVE_LOG: OnceLock<Arc<Mutex<Option<fs::File>>>> = OnceLock::new();
+
+/// The sinks of the logger set up by [`init_simple_logger`], shared with [`LoggerHandle`].
+static SINKS: OnceLock<Arc<Sinks>> = OnceLock::new();
+
+/// The absolute paths of the live log file and log folder of the global logger.
+static ACTIVE_LOG: OnceLock<(PathBuf, PathBuf)> = OnceLock::new();
+
+/// The message of the last panic, set once [`install_panic_hook`] is called.
+static LAST_PANIC: OnceLock<Mutex<Option<String>>> = OnceLock::new();
 
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct LoggingConfig {
     log_folder: PathBuf,
     filename: String,
     term_level_filter: LevelFilter,
     file_level_filter: LevelFilter,
     package_name: Option<String>,
+    show_thread: bool,
+    show_target: bool,
+    banner: bool,
+    package_version: Option<String>,
+    git_hash: Option<String>,
+    archive_name_template: Option<String>,
+    pid_in_filename: bool,
+    rotate_on_init: bool,
+    dedup_window: Option<Duration>,
+    rate_limit: Option<u32>,
+    term_format: Option<LogFormat>,
+    file_format: Option<LogFormat>,
+    tag: Option<String>,
+    #[cfg(feature = "error_reporter")]
+    error_reporting: Option<report::Reporting>,
 }
 
 impl LoggingConfig {
@@ -33,6 +92,20 @@ impl LoggingConfig {
     /// * `term_level_filter`: `LevelFilter::Info`
     /// * `file_level_filter`: `LevelFilter::Info`
     /// * `package_name`: `env!("CARGO_PKG_NAME")`
+    /// * `show_thread`: `true`
+    /// * `show_target`: `false`
+    /// * `banner`: `false`
+    /// * `package_version`: `env!("CARGO_PKG_VERSION")`
+    /// * `git_hash`: `None`
+    /// * `archive_name_template`: `None`
+    /// * `pid_in_filename`: `false`
+    /// * `rotate_on_init`: `false`
+    /// * `dedup_window`: `None`
+    /// * `rate_limit`: `None`
+    /// * `term_format`: `None`
+    /// * `file_format`: `None`
+    /// * `tag`: `None`
+    /// * `error_reporter`: `None` (with the `error_reporter` feature)
     ///
     /// # Arguments
     ///
@@ -48,6 +121,19 @@ impl LoggingConfig {
     /// assert_eq!(config.get_term_level_filter(), log::LevelFilter::Info);
     /// assert_eq!(config.get_file_level_filter(), log::LevelFilter::Info);
     /// assert_eq!(config.get_package_name(), Some(env!("CARGO_PKG_NAME")));
+    /// assert!(config.get_show_thread());
+    /// assert!(!config.get_show_target());
+    /// assert!(!config.get_banner());
+    /// assert_eq!(config.get_package_version(), Some(env!("CARGO_PKG_VERSION")));
+    /// assert_eq!(config.get_git_hash(), None);
+    /// assert_eq!(config.get_archive_name_template(), None);
+    /// assert!(!config.get_pid_in_filename());
+    /// assert!(!config.get_rotate_on_init());
+    /// assert_eq!(config.get_dedup_window(), None);
+    /// assert_eq!(config.get_rate_limit(), None);
+    /// assert_eq!(config.get_term_format(), None);
+    /// assert_eq!(config.get_file_format(), None);
+    /// assert_eq!(config.get_tag(), None);
     /// ```
     pub fn new(path: PathBuf) -> Self {
         Self {
@@ -56,6 +142,21 @@ impl LoggingConfig {
             term_level_filter: LevelFilter::Info,
             file_level_filter: LevelFilter::Info,
             package_name: Some(env!("CARGO_PKG_NAME").to_string()),
+            show_thread: true,
+            show_target: false,
+            banner: false,
+            package_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            git_hash: None,
+            archive_name_template: None,
+            pid_in_filename: false,
+            rotate_on_init: false,
+            dedup_window: None,
+            rate_limit: None,
+            term_format: None,
+            file_format: None,
+            tag: None,
+            #[cfg(feature = "error_reporter")]
+            error_reporting: None,
         }
     }
 
@@ -160,134 +261,2329 @@ impl LoggingConfig {
         self.package_name = name.map(Into::into);
         self
     }
-}
 
-/// Compresses the log file found at `{config.log_folder}/{config.filename}`..
-///
-/// The logs are compressed with `gzip` and `flate2`.
-///
-/// # Arguments
-///
-/// * `config` - The `LoggingConfig` to use.
-/// An underscore `_` will be appended to it as well. See the Examples for more.
-///
-/// # Errors
-///
-/// An error is returned if the directory could not be created, the log file metadata could not be
-/// retrieved, or the log file could not be renamed.
-///
-/// # Examples
-///
-/// ```
-/// # use dablenutil::logging::{LoggingConfig, rotate_logs};
-/// # use log::info;
-/// # use std::path::PathBuf;
-/// # use std::fs;
-///
-/// # fn main() -> dablenutil::Result<()> {
-/// let log_folder = PathBuf::from("./path/to/logs");
-/// // path cloned for testing purposes
-/// let config = LoggingConfig::new(log_folder.clone());
-/// # assert!(!log_folder.exists());
-/// rotate_logs(&config)?;
-/// let log_file = log_folder.join(config.get_filename());
-/// # assert!(log_file.ends_with("latest.log"));
-/// # assert!(log_folder.exists());
-/// # fs::write(&log_file, "Hello, world!")?;
-/// # rotate_logs(&config)?;
-/// # let prefix = format!("{}_", config.get_package_name().unwrap());
-/// # let zipped_archive_exists = log_folder
-/// #     .read_dir()?
-/// #     .filter_map(|r| r.ok())
-/// #     .any(|e| {
-/// #         let file_name = e.file_name();
-/// #         let encoded = file_name.to_string_lossy();
-/// #         encoded.starts_with(&prefix)
-/// #         && encoded.ends_with(".log.gz")
-/// #     });
-/// # assert!(zipped_archive_exists);
-/// # fs::remove_dir_all(&log_folder)?;
-/// # Ok(())
-/// # }
-/// ```
-pub fn rotate_logs(config: &LoggingConfig) -> crate::Result<()> {
-    let log_folder = config.get_log_folder();
-    create_dir_if_not_exists(log_folder)?;
-    let log_filename = config.get_filename();
-    let latest_log_file = log_folder.join(log_filename);
-    if latest_log_file.exists() {
-        let create_time = latest_log_file
-            .metadata()?
-            .created()
-            .map_or_else(|_| Local::now(), DateTime::<Local>::from);
-        let prefix = {
-            let package_name = config.get_package_name();
-            package_name.map_or(String::default(), |s| format!("{}_", s))
-        };
-        let dated_name = create_time
-            .format(&format!("{}%Y-%m-%d_%H-%M-%S.log", prefix))
-            .to_string();
-        let archive_path = log_folder.join(format!("{}.gz", dated_name));
-        let file_handle = fs::File::create(archive_path)?;
-        let last_log_data = fs::read(&latest_log_file)?;
-        let mut gz = GzBuilder::new()
-            .filename(dated_name)
-            .write(file_handle, Compression::default());
-        gz.write_all(&last_log_data)?;
-        gz.finish()?;
-        fs::remove_file(&latest_log_file)?;
+    /// Gets whether the thread is shown on every log line.
+    pub fn get_show_thread(&self) -> bool {
+        self.show_thread
     }
-    Ok(())
-}
 
-/// Initialize the logger with `simplelog`. Logs are outputted to the terminal
-/// as well as the specified file.
-///
-/// This will create a new log file at the given path, but will not rotate the
-/// logs. There is a dedicated function for that, [`rotate_logs`](fn@rotate_logs)
-///
-/// # Arguments
-///
-/// * `config` - The `LoggingConfig` to use.
-///
-/// # Errors
-///
-/// An error is returned if the log files could not be created for some reason.
-///
-/// # Examples
-/// ```
-/// # use dablenutil::logging::{LoggingConfig, init_simple_logger};
-///
-/// # fn main() -> dablenutil::Result<()> {
-/// let path = std::path::PathBuf::from("./path/to/logs");
-/// # assert!(!path.exists());
-/// // path cloned for testing purposes
-/// let config = LoggingConfig::new(path.clone());
-/// init_simple_logger(&config)?;
-/// log::info!("Hello, world!");
-/// # assert!(path.exists());
-/// # std::fs::remove_dir_all("path")?;
-/// # Ok(())
-/// # }
-/// ```
-pub fn init_simple_logger(config: &LoggingConfig) -> crate::Result<()> {
-    let simplelog_config = simplelog::ConfigBuilder::new()
-        .set_time_format_custom(format_description!("[[[hour]:[minute]:[second]]"))
-        .set_thread_mode(ThreadLogMode::Both)
-        .set_target_level(LevelFilter::Off)
-        .set_thread_level(LevelFilter::Error)
-        .build();
-    let log_path = config.get_log_folder();
-    create_dir_if_not_exists(log_path)?;
-    let log_file = fs::File::create(log_path.join(config.get_filename()))?;
-    CombinedLogger::init(vec![
-        TermLogger::new(
-            config.get_term_level_filter(),
-            simplelog_config.clone(),
-            TerminalMode::Mixed,
-            ColorChoice::Auto,
-        ),
-        WriteLogger::new(config.get_file_level_filter(), simplelog_config, log_file),
-    ])?;
-    Ok(())
+    /// Sets whether the thread name and id are shown on every log line.
+    ///
+    /// # Arguments
+    /// * `show` - Whether to show the thread.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).show_thread(false);
+    /// assert!(!config.get_show_thread());
+    /// ```
+    pub fn show_thread(mut self, show: bool) -> Self {
+        self.show_thread = show;
+        self
+    }
+
+    /// Gets whether the target (usually the module path) is shown on every log line.
+    pub fn get_show_target(&self) -> bool {
+        self.show_target
+    }
+
+    /// Sets whether the target of each record is shown on every log line. The target defaults to
+    /// the module path the record originated from.
+    ///
+    /// # Arguments
+    /// * `show` - Whether to show the target.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).show_target(true);
+    /// assert!(config.get_show_target());
+    /// ```
+    pub fn show_target(mut self, show: bool) -> Self {
+        self.show_target = show;
+        self
+    }
+
+    /// Gets whether a startup banner is written to new log files.
+    pub fn get_banner(&self) -> bool {
+        self.banner
+    }
+
+    /// Sets whether a startup banner is written to the top of every new log file. The banner
+    /// contains the package name, version, git hash (if set), OS/architecture, PID, the start time
+    /// of the process' [`RunClock`], and a [`ClockAnchor`](crate::time_utils::ClockAnchor).
+    ///
+    /// # Arguments
+    /// * `banner` - Whether to write the banner.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).banner(true);
+    /// assert!(config.get_banner());
+    /// ```
+    pub fn banner(mut self, banner: bool) -> Self {
+        self.banner = banner;
+        self
+    }
+
+    /// Gets the current package version.
+    pub fn get_package_version(&self) -> Option<&str> {
+        self.package_version.as_deref()
+    }
+
+    /// Sets the package version shown in the startup banner. Usually this is
+    /// `env!("CARGO_PKG_VERSION")` of the calling crate.
+    ///
+    /// # Arguments
+    /// * `version` - The package version to set.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).package_version(Some("1.2.3"));
+    /// assert_eq!(config.get_package_version(), Some("1.2.3"));
+    /// ```
+    pub fn package_version<S: Into<String>>(mut self, version: Option<S>) -> Self {
+        self.package_version = version.map(Into::into);
+        self
+    }
+
+    /// Gets the current git hash.
+    pub fn get_git_hash(&self) -> Option<&str> {
+        self.git_hash.as_deref()
+    }
+
+    /// Sets the git hash shown in the startup banner. This is usually provided by a build script.
+    ///
+    /// # Arguments
+    /// * `hash` - The git hash to set.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).git_hash(Some("a1b2c3d"));
+    /// assert_eq!(config.get_git_hash(), Some("a1b2c3d"));
+    /// ```
+    pub fn git_hash<S: Into<String>>(mut self, hash: Option<S>) -> Self {
+        self.git_hash = hash.map(Into::into);
+        self
+    }
+
+    /// Gets the current archive name template.
+    pub fn get_archive_name_template(&self) -> Option<&str> {
+        self.archive_name_template.as_deref()
+    }
+
+    /// Sets the template used by [`rotate_logs`](fn@rotate_logs) to name log archives. The
+    /// following placeholders are replaced:
+    /// * `{package}` - The package name, or nothing if no package name is set.
+    /// * `{date}` - The date of the log file, formatted as `%Y-%m-%d_%H-%M-%S`.
+    /// * `{seq}` - The lowest sequence number, starting at 1, which does not collide with an
+    ///   existing archive.
+    ///
+    /// If this is `None`, archives are named `{package}_{date}.log.gz`, leaving out the package
+    /// name and underscore if no package name is set.
+    ///
+    /// # Arguments
+    /// * `template` - The archive name template to set.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder)
+    ///     .archive_name_template(Some("{package}_{date}_{seq}.log.gz"));
+    /// assert_eq!(
+    ///     config.get_archive_name_template(),
+    ///     Some("{package}_{date}_{seq}.log.gz")
+    /// );
+    /// ```
+    pub fn archive_name_template<S: Into<String>>(mut self, template: Option<S>) -> Self {
+        self.archive_name_template = template.map(Into::into);
+        self
+    }
+
+    /// Gets whether the process ID is included in the live log filename.
+    pub fn get_pid_in_filename(&self) -> bool {
+        self.pid_in_filename
+    }
+
+    /// Sets whether the process ID is included in the live log filename, e.g. `latest-1234.log`
+    /// instead of `latest.log`. Enable this if several instances of an application may log to the
+    /// same folder at once, so they don't fight over the same file.
+    ///
+    /// # Arguments
+    /// * `pid_in_filename` - Whether to include the process ID.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).pid_in_filename(true);
+    /// assert!(config.get_pid_in_filename());
+    /// assert_eq!(
+    ///     config.get_live_filename(),
+    ///     format!("latest-{}.log", std::process::id())
+    /// );
+    /// ```
+    pub fn pid_in_filename(mut self, pid_in_filename: bool) -> Self {
+        self.pid_in_filename = pid_in_filename;
+        self
+    }
+
+    /// Gets whether the logs are rotated when the logger is initialized.
+    pub fn get_rotate_on_init(&self) -> bool {
+        self.rotate_on_init
+    }
+
+    /// Sets whether [`init_simple_logger`], [`init_async_logger`](crate::tokio::init_async_logger),
+    /// and `init_tracing_subscriber` rotate the logs of previous runs before creating the new log
+    /// file, instead of leaving that to a separate call to [`rotate_logs`](fn@rotate_logs).
+    ///
+    /// # Arguments
+    /// * `rotate_on_init` - Whether to rotate the logs on initialization.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).rotate_on_init(true);
+    /// assert!(config.get_rotate_on_init());
+    /// ```
+    pub fn rotate_on_init(mut self, rotate_on_init: bool) -> Self {
+        self.rotate_on_init = rotate_on_init;
+        self
+    }
+
+    /// Gets the window in which identical consecutive log records are collapsed, or `None` if
+    /// duplicate suppression is disabled.
+    pub fn get_dedup_window(&self) -> Option<Duration> {
+        self.dedup_window
+    }
+
+    /// Enables duplicate suppression. Identical consecutive records (same level, target, and
+    /// message) logged within `window` of the first one are dropped, and a single
+    /// "last message repeated N times" line is logged in their place once a different record
+    /// arrives, the window expires, or the logger is flushed.
+    ///
+    /// # Arguments
+    /// * `window` - How long to suppress repeats of a record for.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::{path::PathBuf, time::Duration};
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).dedup_window(Duration::from_secs(5));
+    /// assert_eq!(config.get_dedup_window(), Some(Duration::from_secs(5)));
+    /// ```
+    pub fn dedup_window(mut self, window: Duration) -> Self {
+        self.dedup_window = Some(window);
+        self
+    }
+
+    /// Gets the maximum number of records logged per second for each target, or `None` if rate
+    /// limiting is disabled.
+    pub fn get_rate_limit(&self) -> Option<u32> {
+        self.rate_limit
+    }
+
+    /// Enables rate limiting. Each target may log at most `records_per_sec` records per second;
+    /// excess records are dropped and counted. Once a second has passed, a warning with the number
+    /// of dropped records is logged for each target that exceeded the limit, from a background
+    /// thread if nothing else is logged. This protects the disk from a misbehaving dependency
+    /// flooding the log.
+    ///
+    /// # Arguments
+    /// * `records_per_sec` - The maximum number of records per second for each target.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).rate_limit(100);
+    /// assert_eq!(config.get_rate_limit(), Some(100));
+    /// ```
+    pub fn rate_limit(mut self, records_per_sec: u32) -> Self {
+        self.rate_limit = Some(records_per_sec);
+        self
+    }
+
+    /// Gets the format of terminal log lines, or `None` if it follows
+    /// [`show_thread`](LoggingConfig::show_thread) and [`show_target`](LoggingConfig::show_target).
+    pub fn get_term_format(&self) -> Option<&LogFormat> {
+        self.term_format.as_ref()
+    }
+
+    /// Sets the format of terminal log lines, overriding [`show_thread`](LoggingConfig::show_thread)
+    /// and [`show_target`](LoggingConfig::show_target) for the terminal.
+    ///
+    /// # Arguments
+    /// * `format` - The format to use.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::{LogFormat, LoggingConfig};
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let terse = LogFormat::new().show_time(false).show_thread(false);
+    /// let config = LoggingConfig::new(log_folder).term_format(terse);
+    /// assert_eq!(config.get_term_format(), Some(&terse));
+    /// ```
+    pub fn term_format(mut self, format: LogFormat) -> Self {
+        self.term_format = Some(format);
+        self
+    }
+
+    /// Gets the format of log file lines, or `None` if it follows
+    /// [`show_thread`](LoggingConfig::show_thread) and [`show_target`](LoggingConfig::show_target).
+    pub fn get_file_format(&self) -> Option<&LogFormat> {
+        self.file_format.as_ref()
+    }
+
+    /// Sets the format of log file lines, overriding [`show_thread`](LoggingConfig::show_thread)
+    /// and [`show_target`](LoggingConfig::show_target) for the log file.
+    ///
+    /// # Arguments
+    /// * `format` - The format to use.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::{LogFormat, LoggingConfig};
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let verbose = LogFormat::new().show_target(true).show_location(true);
+    /// let config = LoggingConfig::new(log_folder).file_format(verbose);
+    /// assert_eq!(config.get_file_format(), Some(&verbose));
+    /// ```
+    pub fn file_format(mut self, format: LogFormat) -> Self {
+        self.file_format = Some(format);
+        self
+    }
+
+    /// Gets the tag prefixed to every record, if any.
+    pub fn get_tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// Sets a tag to prefix every record with, such as an instance name or shard id, so logs from
+    /// several processes can be told apart once aggregated. Records are logged as `[tag] message`.
+    ///
+    /// # Arguments
+    /// * `tag` - The tag to prefix records with.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LoggingConfig;
+    /// # use std::path::PathBuf;
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder).tag("worker-2");
+    /// assert_eq!(config.get_tag(), Some("worker-2"));
+    /// ```
+    pub fn tag<S: Into<String>>(mut self, tag: S) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Gets the batch size and interval used for the error reporter, if one is set. Only available
+    /// when the `error_reporter` feature is enabled.
+    #[cfg(feature = "error_reporter")]
+    pub fn get_error_batch(&self) -> Option<(usize, Duration)> {
+        self.error_reporting
+            .as_ref()
+            .map(|reporting| (reporting.batch_size, reporting.batch_interval))
+    }
+
+    /// Sets an [`ErrorReporter`](report::ErrorReporter) which is sent every error-level record,
+    /// for forwarding errors to a tracker such as Sentry. By default, records are reported in
+    /// batches of up to 10, at most 5 seconds after they are logged; see
+    /// [`error_batch`](LoggingConfig::error_batch). Only available when the `error_reporter`
+    /// feature is enabled.
+    ///
+    /// # Arguments
+    /// * `reporter` - The reporter to send records to.
+    #[cfg(feature = "error_reporter")]
+    pub fn error_reporter<R: report::ErrorReporter + 'static>(mut self, reporter: R) -> Self {
+        let (batch_size, batch_interval) = self
+            .get_error_batch()
+            .unwrap_or((10, Duration::from_secs(5)));
+        self.error_reporting = Some(report::Reporting {
+            reporter: Arc::new(reporter),
+            batch_size,
+            batch_interval,
+        });
+        self
+    }
+
+    /// Sets how records are batched for the error reporter. A batch is reported once it holds
+    /// `size` records, or `interval` after the last batch, whichever comes first. Does nothing if
+    /// no reporter is set. Only available when the `error_reporter` feature is enabled.
+    ///
+    /// # Arguments
+    /// * `size` - The maximum number of records in a batch, at least 1.
+    /// * `interval` - The maximum time between batches.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::{LoggingConfig, report::{ErrorEvent, ErrorReporter}};
+    /// # use std::{path::PathBuf, time::Duration};
+    /// struct Webhook;
+    ///
+    /// impl ErrorReporter for Webhook {
+    ///     fn report(&self, batch: &[ErrorEvent]) {}
+    /// }
+    ///
+    /// let log_folder = PathBuf::from("./path/to/logs");
+    /// let config = LoggingConfig::new(log_folder)
+    ///     .error_reporter(Webhook)
+    ///     .error_batch(50, Duration::from_secs(30));
+    /// assert_eq!(config.get_error_batch(), Some((50, Duration::from_secs(30))));
+    /// ```
+    #[cfg(feature = "error_reporter")]
+    pub fn error_batch(mut self, size: usize, interval: Duration) -> Self {
+        if let Some(reporting) = self.error_reporting.as_mut() {
+            reporting.batch_size = size.max(1);
+            reporting.batch_interval = interval;
+        }
+        self
+    }
+
+    /// Gets the format used for a sink, falling back to the shared thread and target settings.
+    fn effective_format(&self, format: Option<&LogFormat>) -> LogFormat {
+        format.copied().unwrap_or_else(|| {
+            LogFormat::new()
+                .show_thread(self.show_thread)
+                .show_target(self.show_target)
+        })
+    }
+
+    /// Gets the filename of the log file this process writes to, which is the configured filename
+    /// with the process ID inserted before the extension if
+    /// [`pid_in_filename`](LoggingConfig::pid_in_filename) is enabled.
+    pub fn get_live_filename(&self) -> String {
+        if !self.pid_in_filename {
+            return self.filename.clone();
+        }
+        let pid = std::process::id();
+        match self.filename.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => {
+                format!("{}-{}.{}", stem, pid, extension)
+            }
+            _ => format!("{}-{}", self.filename, pid),
+        }
+    }
+
+    /// Builds the archive name for a log file dated `date` with the sequence number `seq`.
+    fn archive_name(&self, date: &DateTime<Local>, seq: u32) -> String {
+        let date = date.format("%Y-%m-%d_%H-%M-%S").to_string();
+        let package = self.get_package_name().unwrap_or_default();
+        match self.get_archive_name_template() {
+            Some(template) => template
+                .replace("{package}", package)
+                .replace("{date}", &date)
+                .replace("{seq}", &seq.to_string()),
+            None if package.is_empty() => format!("{}.log.gz", date),
+            None => format!("{}_{}.log.gz", package, date),
+        }
+    }
+
+    /// Builds the `attempt`-th candidate archive name for a log file dated `date`, starting at 0.
+    /// Orphaned logs pass their file stem as `orphan`, which goes in front of the name so the
+    /// archive still shows where it came from.
+    ///
+    /// If the template contains `{seq}`, the attempt is used as the sequence number. Otherwise,
+    /// every attempt after the first gets a `-{attempt}` suffix before the extension.
+    pub(crate) fn archive_candidate(
+        &self,
+        date: &DateTime<Local>,
+        attempt: u32,
+        orphan: Option<&str>,
+    ) -> String {
+        let has_seq = self
+            .get_archive_name_template()
+            .is_some_and(|t| t.contains("{seq}"));
+        let name = if has_seq {
+            self.archive_name(date, attempt + 1)
+        } else {
+            let name = self.archive_name(date, 1);
+            if attempt == 0 {
+                name
+            } else {
+                let (stem, extension) = [".log.gz", ".gz"]
+                    .iter()
+                    .find_map(|ext| name.strip_suffix(ext).map(|stem| (stem, *ext)))
+                    .unwrap_or((&name, ""));
+                format!("{}-{}{}", stem, attempt, extension)
+            }
+        };
+        match orphan {
+            Some(stem) => format!("{}_{}", stem, name),
+            None => name,
+        }
+    }
+}
+
+/// Which parts of a record are shown on each log line. Each sink (terminal or file) can have its
+/// own format; see [`LoggingConfig::term_format`] and [`LoggingConfig::file_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct LogFormat {
+    show_time: bool,
+    show_level: bool,
+    show_thread: bool,
+    show_target: bool,
+    show_location: bool,
+}
+
+impl LogFormat {
+    /// Constructs a new `LogFormat` with the default settings:
+    ///
+    /// * `show_time`: `true`
+    /// * `show_level`: `true`
+    /// * `show_thread`: `true`
+    /// * `show_target`: `false`
+    /// * `show_location`: `false`
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::LogFormat;
+    /// let format = LogFormat::new();
+    /// assert!(format.get_show_time());
+    /// assert!(format.get_show_level());
+    /// assert!(format.get_show_thread());
+    /// assert!(!format.get_show_target());
+    /// assert!(!format.get_show_location());
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            show_time: true,
+            show_level: true,
+            show_thread: true,
+            show_target: false,
+            show_location: false,
+        }
+    }
+
+    /// Gets whether the time is shown.
+    pub fn get_show_time(&self) -> bool {
+        self.show_time
+    }
+
+    /// Sets whether the time is shown.
+    ///
+    /// # Arguments
+    /// * `show` - Whether to show the time.
+    pub fn show_time(mut self, show: bool) -> Self {
+        self.show_time = show;
+        self
+    }
+
+    /// Gets whether the level is shown.
+    pub fn get_show_level(&self) -> bool {
+        self.show_level
+    }
+
+    /// Sets whether the level is shown.
+    ///
+    /// # Arguments
+    /// * `show` - Whether to show the level.
+    pub fn show_level(mut self, show: bool) -> Self {
+        self.show_level = show;
+        self
+    }
+
+    /// Gets whether the thread is shown.
+    pub fn get_show_thread(&self) -> bool {
+        self.show_thread
+    }
+
+    /// Sets whether the thread name and id are shown.
+    ///
+    /// # Arguments
+    /// * `show` - Whether to show the thread.
+    pub fn show_thread(mut self, show: bool) -> Self {
+        self.show_thread = show;
+        self
+    }
+
+    /// Gets whether the target is shown.
+    pub fn get_show_target(&self) -> bool {
+        self.show_target
+    }
+
+    /// Sets whether the target (usually the module path) is shown.
+    ///
+    /// # Arguments
+    /// * `show` - Whether to show the target.
+    pub fn show_target(mut self, show: bool) -> Self {
+        self.show_target = show;
+        self
+    }
+
+    /// Gets whether the source location is shown.
+    pub fn get_show_location(&self) -> bool {
+        self.show_location
+    }
+
+    /// Sets whether the source file and line of each record are shown.
+    ///
+    /// # Arguments
+    /// * `show` - Whether to show the source location.
+    pub fn show_location(mut self, show: bool) -> Self {
+        self.show_location = show;
+        self
+    }
+
+    /// Builds the `simplelog` config for this format.
+    fn simplelog_config(self) -> simplelog::Config {
+        fn level(show: bool) -> LevelFilter {
+            // simplelog shows each part for records at this level and more verbose
+            if show {
+                LevelFilter::Error
+            } else {
+                LevelFilter::Off
+            }
+        }
+        simplelog::ConfigBuilder::new()
+            .set_time_format_custom(format_description!("[[[hour]:[minute]:[second]]"))
+            .set_thread_mode(ThreadLogMode::Both)
+            .set_time_level(level(self.show_time))
+            .set_max_level(level(self.show_level))
+            .set_thread_level(level(self.show_thread))
+            .set_target_level(level(self.show_target))
+            .set_location_level(level(self.show_location))
+            .build()
+    }
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A log file which was archived by [`rotate_logs`](fn@rotate_logs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotatedLog {
+    /// The path of the original log file, which no longer exists.
+    pub source: PathBuf,
+    /// The path of the compressed archive.
+    pub archive: PathBuf,
+    /// The size of the compressed archive, in bytes.
+    pub size: u64,
+}
+
+/// Compresses the log file found at `{config.log_folder}/{config.filename}`..
+///
+/// The logs are compressed with `gzip` and `flate2`. The archive is named according to
+/// [`LoggingConfig::archive_name_template`], using the creation time of the log file. If the
+/// creation time is not supported by the filesystem, the modification time is used instead, and
+/// only if that fails too is the current time used. If an archive with the same name already
+/// exists, a sequence number is appended instead of overwriting it. Existing archives are never
+/// overwritten, even by concurrent rotations.
+///
+/// Any other plain `.log` files in the log folder which were last modified before this session
+/// started, as measured by [`RunClock::global`], are archived the same way. These are logs left
+/// behind by crashed runs or by a previously configured filename. Their archives start with their
+/// file stem, like `old-name_{package}_{date}.log.gz`, so they can be told apart. Log files which
+/// are still in use by a running logger, possibly in another process, are skipped.
+///
+/// Rotation holds an advisory lock on a `.rotate.lock` file in the log folder, so multiple
+/// processes rotating the same folder at once don't interfere with each other.
+///
+/// Returns a [`RotatedLog`] for every archive that was created, so callers can upload or announce
+/// them without re-scanning the log folder. Use [`rotate_logs_with_plan`] to see what would be
+/// archived without changing anything.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` to use.
+///
+/// # Errors
+///
+/// An error is returned if the directory could not be created, the log file metadata could not be
+/// retrieved, or the log file could not be renamed.
+///
+/// # Examples
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, rotate_logs};
+/// # use log::info;
+/// # use std::path::PathBuf;
+/// # use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = PathBuf::from("./path/to/logs");
+/// // path cloned for testing purposes
+/// let config = LoggingConfig::new(log_folder.clone());
+/// # assert!(!log_folder.exists());
+/// rotate_logs(&config)?;
+/// let log_file = log_folder.join(config.get_filename());
+/// # assert!(log_file.ends_with("latest.log"));
+/// # assert!(log_folder.exists());
+/// # fs::write(&log_file, "Hello, world!")?;
+/// # rotate_logs(&config)?;
+/// # let prefix = format!("{}_", config.get_package_name().unwrap());
+/// # let zipped_archive_exists = log_folder
+/// #     .read_dir()?
+/// #     .filter_map(|r| r.ok())
+/// #     .any(|e| {
+/// #         let file_name = e.file_name();
+/// #         let encoded = file_name.to_string_lossy();
+/// #         encoded.starts_with(&prefix)
+/// #         && encoded.ends_with(".log.gz")
+/// #     });
+/// # assert!(zipped_archive_exists);
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Using a custom archive name template:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, rotate_logs};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_rotate_logs_template");
+/// let config = LoggingConfig::new(log_folder.clone())
+///     .package_name(Some("app"))
+///     .archive_name_template(Some("{package}-{seq}.log.gz"));
+/// # fs::create_dir_all(&log_folder)?;
+/// fs::write(log_folder.join("latest.log"), "first")?;
+/// rotate_logs(&config)?;
+/// fs::write(log_folder.join("latest.log"), "second")?;
+/// rotate_logs(&config)?;
+/// assert!(log_folder.join("app-1.log.gz").exists());
+/// assert!(log_folder.join("app-2.log.gz").exists());
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Rotating twice within the same second does not overwrite the first archive:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, rotate_logs};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_rotate_logs_collision");
+/// let config = LoggingConfig::new(log_folder.clone());
+/// # fs::create_dir_all(&log_folder)?;
+/// for _ in 0..3 {
+///     fs::write(log_folder.join("latest.log"), "Hello, world!")?;
+///     rotate_logs(&config)?;
+/// }
+/// let archives = fs::read_dir(&log_folder)?
+///     .filter_map(Result::ok)
+///     .filter(|e| e.path().extension().is_some_and(|ext| ext == "gz"))
+///     .count();
+/// # fs::remove_dir_all(&log_folder)?;
+/// assert_eq!(archives, 3);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Stale logs with other names are archived too, keeping their name in front:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, rotate_logs};
+/// # use std::{fs, time::{Duration, SystemTime}};
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_rotate_logs_orphans");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone()).package_name(Some("app"));
+/// # fs::create_dir_all(&log_folder)?;
+/// let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+/// fs::File::create(log_folder.join("old-name.log"))?.set_modified(yesterday)?;
+/// let rotated = rotate_logs(&config)?;
+/// assert_eq!(rotated.len(), 1);
+/// let archive_name = rotated[0].archive.file_name().unwrap().to_string_lossy();
+/// assert!(archive_name.starts_with("old-name_app_"));
+/// assert!(!log_folder.join("old-name.log").exists());
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Logs written since this session started are left alone:
+///
+/// ```
+/// # use dablenutil::{logging::{LoggingConfig, rotate_logs}, time_utils::RunClock};
+/// # use std::{fs, thread, time::Duration};
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_rotate_logs_current_session");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone());
+/// # fs::create_dir_all(&log_folder)?;
+/// RunClock::global();
+/// # // leave room for coarse filesystem timestamps
+/// # thread::sleep(Duration::from_millis(50));
+/// fs::write(log_folder.join("worker.log"), "still being written")?;
+/// assert!(rotate_logs(&config)?.is_empty());
+/// assert!(log_folder.join("worker.log").exists());
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Inspecting the created archives:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, rotate_logs};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_rotate_logs_rotated");
+/// let config = LoggingConfig::new(log_folder.clone());
+/// # fs::create_dir_all(&log_folder)?;
+/// fs::write(log_folder.join("latest.log"), "Hello, world!")?;
+/// let rotated = rotate_logs(&config)?;
+/// assert_eq!(rotated.len(), 1);
+/// assert_eq!(rotated[0].source, log_folder.join("latest.log"));
+/// assert!(rotated[0].archive.exists());
+/// assert_eq!(rotated[0].size, fs::metadata(&rotated[0].archive)?.len());
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn rotate_logs(config: &LoggingConfig) -> crate::Result<Vec<RotatedLog>> {
+    rotate_logs_with_plan(config, &mut Plan::new(Mode::Execute))
+}
+
+/// Rotates logs like [`rotate_logs`], recording every archive in `plan`. In [`Mode::DryRun`],
+/// nothing is created, archived, or locked, no [`RotatedLog`] is returned, and `plan` lists the
+/// archives which would have been created.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` to use.
+/// * `plan` - The plan to record the archives in.
+///
+/// # Errors
+///
+/// An error is returned in the same cases as [`rotate_logs`].
+///
+/// # Examples
+///
+/// A dry run leaves the log folder untouched:
+///
+/// ```
+/// # use dablenutil::{
+/// #     dry_run::{Action, Mode, Plan},
+/// #     logging::{LoggingConfig, rotate_logs_with_plan},
+/// # };
+/// # use std::{fs, time::{Duration, SystemTime}};
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_rotate_logs_dry_run");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone());
+/// # fs::create_dir_all(&log_folder)?;
+/// fs::write(log_folder.join("latest.log"), "Hello, world!")?;
+/// let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+/// fs::File::create(log_folder.join("crashed.log"))?.set_modified(yesterday)?;
+/// let mut plan = Plan::new(Mode::DryRun);
+/// assert!(rotate_logs_with_plan(&config, &mut plan)?.is_empty());
+/// assert_eq!(plan.actions().len(), 2);
+/// assert!(plan.actions().iter().all(|action| matches!(action, Action::Archive { .. })));
+/// assert_eq!(fs::read_dir(&log_folder)?.count(), 2);
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn rotate_logs_with_plan(
+    config: &LoggingConfig,
+    plan: &mut Plan,
+) -> crate::Result<Vec<RotatedLog>> {
+    let log_folder = config.get_log_folder();
+    if !log_folder.is_dir() {
+        plan.perform(Action::CreateDir(log_folder.to_path_buf()), || {
+            fs::create_dir_all(log_folder)
+        })?;
+        if plan.mode().is_dry_run() {
+            return Ok(Vec::new());
+        }
+    }
+    // a dry run changes nothing, so it doesn't need to keep other rotations out
+    let _rotation_lock = match plan.mode() {
+        Mode::Execute => Some(lock_log_folder(log_folder)?),
+        Mode::DryRun => None,
+    };
+    let latest_log_file = log_folder.join(config.get_live_filename());
+    let mut log_files = Vec::new();
+    if latest_log_file.exists() {
+        log_files.push((latest_log_file.clone(), false));
+    }
+    // logs left behind by crashed runs or a previous filename would otherwise never be archived
+    for entry in fs::read_dir(log_folder)? {
+        let path = entry?.path();
+        if path != latest_log_file && fs::metadata(&path).is_ok_and(|m| is_orphan_log(&path, &m)) {
+            log_files.push((path, true));
+        }
+    }
+    let mut rotated = Vec::new();
+    for (log_file, orphan) in log_files {
+        rotated.extend(archive_log_file(config, &log_file, orphan, plan)?);
+    }
+    Ok(rotated)
+}
+
+/// Takes an exclusive advisory lock on the log folder, blocking until it is available. The lock is
+/// released when the returned lock is dropped.
+pub(crate) fn lock_log_folder(log_folder: &Path) -> io::Result<FileLock> {
+    FileLock::acquire(&log_folder.join(".rotate.lock"), false)
+}
+
+/// Checks whether a file other than the live log is a plain `.log` file which was last modified
+/// before this session started, so no logger of this session can be writing to it.
+pub(crate) fn is_orphan_log(path: &Path, metadata: &fs::Metadata) -> bool {
+    let session_start = SystemTime::from(RunClock::global().started_at());
+    metadata.is_file()
+        && path.extension().is_some_and(|ext| ext == "log")
+        && metadata
+            .modified()
+            .is_ok_and(|modified| modified < session_start)
+}
+
+/// Gets the file stem an orphaned log passes to [`LoggingConfig::archive_candidate`].
+pub(crate) fn orphan_stem(log_file: &Path, orphan: bool) -> Option<String> {
+    orphan.then(|| {
+        log_file
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+/// Compresses `log_file` into an archive next to it and removes the original. Returns `None`
+/// without doing anything if the log file is locked by a running logger.
+fn archive_log_file(
+    config: &LoggingConfig,
+    log_file: &Path,
+    orphan: bool,
+    plan: &mut Plan,
+) -> crate::Result<Option<RotatedLog>> {
+    let log_folder = config.get_log_folder();
+    let orphan = orphan_stem(log_file, orphan);
+    let mut log_handle = fs::File::open(log_file)?;
+    match log_handle.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => return Ok(None),
+        Err(fs::TryLockError::Error(e)) => return Err(e.into()),
+    }
+    let log_time = log_time(&log_handle.metadata()?);
+    if plan.mode().is_dry_run() {
+        let mut attempt = 0;
+        let archive = loop {
+            let candidate =
+                log_folder.join(config.archive_candidate(&log_time, attempt, orphan.as_deref()));
+            // names taken by archives planned earlier in the run would have been created by now
+            let planned = plan.actions().iter().any(
+                |action| matches!(action, Action::Archive { archive, .. } if *archive == candidate),
+            );
+            if !planned && !candidate.exists() {
+                break candidate;
+            }
+            attempt += 1;
+        };
+        plan.record(Action::Archive {
+            source: log_file.to_path_buf(),
+            archive,
+        });
+        return Ok(None);
+    }
+    // create_new fails if the archive exists, so a concurrent rotation can't clobber it
+    // between checking for a free name and creating the file
+    let mut attempt = 0;
+    let (archive_name, archive_path, file_handle) = loop {
+        let archive_name = config.archive_candidate(&log_time, attempt, orphan.as_deref());
+        let archive_path = log_folder.join(&archive_name);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&archive_path)
+        {
+            Ok(file) => break (archive_name, archive_path, file),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e.into()),
+        }
+    };
+    let dated_name = archive_name
+        .strip_suffix(".gz")
+        .unwrap_or(&archive_name)
+        .to_string();
+    let action = Action::Archive {
+        source: log_file.to_path_buf(),
+        archive: archive_path.clone(),
+    };
+    let size = plan.perform(action, || {
+        // read through the locked handle; on Windows, locks block reads from other handles
+        let archive = compress::gzip_stream(&mut log_handle, file_handle, 6, Some(&dated_name))?;
+        let size = archive.metadata()?.len();
+        drop(log_handle);
+        fs::remove_file(log_file)?;
+        Ok(size)
+    })?;
+    Ok(size.map(|size| RotatedLog {
+        source: log_file.to_path_buf(),
+        archive: archive_path,
+        size,
+    }))
+}
+
+/// Gets the time a log file was started from its metadata, falling back to now.
+pub(crate) fn log_time(metadata: &fs::Metadata) -> DateTime<Local> {
+    metadata
+        .created()
+        .or_else(|_| metadata.modified())
+        .map_or_else(|_| Local::now(), DateTime::<Local>::from)
+}
+
+/// Writes the startup banner for `config` to `writer`.
+pub(crate) fn write_banner<W: Write>(config: &LoggingConfig, writer: &mut W) -> io::Result<()> {
+    const RULE: &str = "================================================================";
+    writeln!(writer, "{}", RULE)?;
+    write!(
+        writer,
+        " {}",
+        config.get_package_name().unwrap_or("unknown")
+    )?;
+    if let Some(version) = config.get_package_version() {
+        write!(writer, " v{}", version)?;
+    }
+    if let Some(hash) = config.get_git_hash() {
+        write!(writer, " ({})", hash)?;
+    }
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        " Platform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )?;
+    writeln!(writer, " PID:      {}", std::process::id())?;
+    let clock = RunClock::global();
+    writeln!(
+        writer,
+        " Started:  {}",
+        clock
+            .started_at()
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+    )?;
+    writeln!(writer, " Anchor:   {}", clock.anchor())?;
+    writeln!(writer, "{}", RULE)
+}
+
+/// Creates (or truncates) the live log file and takes a shared lock on it.
+pub(crate) fn open_live_log(config: &LoggingConfig) -> io::Result<fs::File> {
+    let log_folder = config.get_log_folder();
+    let live_log = log_folder.join(config.get_live_filename());
+    // holding the rotation lock keeps other processes from archiving the file while it is
+    // briefly unlocked below
+    let _rotation_lock = lock_log_folder(log_folder)?;
+    let log_file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&live_log)?;
+    match log_file.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{} is in use by another process", live_log.display()),
+            ))
+        }
+        Err(fs::TryLockError::Error(e)) => return Err(e),
+    }
+    log_file.set_len(0)?;
+    // a shared lock still lets other processes read the live log on Windows
+    log_file.unlock()?;
+    log_file.lock_shared()?;
+    Ok(log_file)
+}
+
+/// Initialize the logger with `simplelog`. Logs are outputted to the terminal
+/// as well as the specified file.
+///
+/// This will create a new log file at the given path, but will not rotate the
+/// logs unless [`LoggingConfig::rotate_on_init`] is enabled. There is a dedicated function for
+/// that, [`rotate_logs`](fn@rotate_logs)
+///
+/// If [`LoggingConfig::banner`] is enabled, a startup banner is written to the top of the new
+/// log file.
+///
+/// The log file holds a shared advisory lock for as long as the logger lives, which prevents
+/// [`rotate_logs`](fn@rotate_logs) in other processes from archiving it while it's in use.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` to use.
+///
+/// # Errors
+///
+/// An error is returned if the logs could not be rotated, the log files could not be created for
+/// some reason, or if the log file is in use by another process. In the latter case, consider
+/// enabling [`LoggingConfig::pid_in_filename`].
+///
+/// # Examples
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let path = std::path::PathBuf::from("./path/to/logs");
+/// # assert!(!path.exists());
+/// // path cloned for testing purposes
+/// let config = LoggingConfig::new(path.clone());
+/// init_simple_logger(&config)?;
+/// log::info!("Hello, world!");
+/// # assert!(path.exists());
+/// # std::fs::remove_dir_all("path")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// With [`LoggingConfig::dedup_window`], floods of identical records are collapsed, and the
+/// number of repeats is logged once the window expires:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger};
+/// # use std::{fs, thread, time::Duration};
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_init_dedup");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone()).dedup_window(Duration::from_millis(200));
+/// init_simple_logger(&config)?;
+/// for _ in 0..5 {
+///     log::warn!("retrying");
+/// }
+/// thread::sleep(Duration::from_secs(1));
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert_eq!(contents.matches("retrying").count(), 1);
+/// assert!(contents.contains("last message repeated 4 times"));
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// ANSI escape sequences in messages are stripped from the log file:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_init_ansi");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone());
+/// init_simple_logger(&config)?;
+/// log::info!("\x1b[31mred\x1b[0m alert");
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert!(contents.contains("red alert"));
+/// assert!(!contents.contains('\x1b'));
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// A tag identifies the process in aggregated logs:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_init_tag");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone()).tag("shard-7");
+/// init_simple_logger(&config)?;
+/// log::info!("Hello, world!");
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert!(contents.contains("[shard-7] Hello, world!"));
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Each sink can have its own format:
+///
+/// ```
+/// # use dablenutil::logging::{LogFormat, LoggingConfig, init_simple_logger};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_init_formats");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone())
+///     .term_format(LogFormat::new().show_time(false).show_thread(false))
+///     .file_format(LogFormat::new().show_target(true));
+/// init_simple_logger(&config)?;
+/// log::info!("Hello, world!");
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert!(contents.contains("rust_out"));
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// With [`LoggingConfig::rate_limit`], excess records are dropped:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_init_rate_limit");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone()).rate_limit(10);
+/// init_simple_logger(&config)?;
+/// for i in 0..100 {
+///     log::info!("message {}", i);
+/// }
+/// log::logger().flush();
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert_eq!(contents.matches("message").count(), 10);
+/// assert!(contents.contains("dropped 90 records"));
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The number of dropped records is logged even if the target goes quiet:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger};
+/// # use std::{fs, thread, time::Duration};
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_init_rate_limit_quiet");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone()).rate_limit(10);
+/// init_simple_logger(&config)?;
+/// for i in 0..100 {
+///     log::info!("message {}", i);
+/// }
+/// thread::sleep(Duration::from_millis(2500));
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert!(contents.contains("dropped 90 records"));
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn init_simple_logger(config: &LoggingConfig) -> crate::Result<()> {
+    if config.get_rotate_on_init() {
+        rotate_logs(config)?;
+    }
+    let log_path = config.get_log_folder();
+    create_dir_if_not_exists(log_path)?;
+    let mut log_file = open_live_log(config)?;
+    if config.get_banner() {
+        write_banner(config, &mut log_file)?;
+    }
+    let writer = LiveLogWriter::new(log_file);
+    let live_log = writer.clone();
+    install_logger(config, writer)?;
+    register_live_log(&live_log);
+    Ok(())
+}
+
+/// Makes `writer` the live log of the global logger, so [`reopen_log_file`] can swap its file.
+pub(crate) fn register_live_log(writer: &LiveLogWriter) {
+    // installing the logger only succeeds once, so this can't already be set
+    let _ = LIVE_LOG.set(Arc::clone(&writer.0));
+}
+
+/// Rotates the logs and reopens the live log file of the logger set up by
+/// [`init_simple_logger`], [`init_async_logger`](crate::tokio::init_async_logger), or
+/// `init_tracing_subscriber`, without losing any records. This is what log rotation tools like
+/// `logrotate` expect to happen when they send `SIGHUP`; see [`install_sighup_rotation`].
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` the logger was initialized with.
+///
+/// # Errors
+///
+/// An error is returned if the logger was not initialized with one of those functions, if the
+/// logs could not be rotated, or if the new log file could not be created. When rotation fails,
+/// the live log file is still reopened, without truncating it, so no records are lost before the
+/// error is returned. Only if it can't be reopened are records discarded until the next
+/// successful call.
+///
+/// # Examples
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger, reopen_log_file};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_reopen_log_file");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone());
+/// init_simple_logger(&config)?;
+/// log::info!("before");
+/// let rotated = reopen_log_file(&config)?;
+/// assert_eq!(rotated.len(), 1);
+/// log::info!("after");
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert!(!contents.contains("before"));
+/// assert!(contents.contains("after"));
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// When rotation fails, logging to the file continues:
+///
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger, reopen_log_file};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_reopen_log_file_failed");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone());
+/// init_simple_logger(&config)?;
+/// log::info!("before");
+/// // archives can't be created in a folder which doesn't exist
+/// let broken = config.clone().archive_name_template(Some("missing/{date}.log.gz"));
+/// assert!(reopen_log_file(&broken).is_err());
+/// log::info!("after");
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert!(contents.contains("before"));
+/// assert!(contents.contains("after"));
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn reopen_log_file(config: &LoggingConfig) -> crate::Result<Vec<RotatedLog>> {
+    let live_log = LIVE_LOG.get().ok_or_else(|| {
+        io::Error::other(
+            "the logger was not initialized with init_simple_logger, init_async_logger, or \
+             init_tracing_subscriber",
+        )
+    })?;
+    // holding the lock blocks logging threads until the new file is in place
+    let mut live_log = live_log.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(log_file) = live_log.as_mut() {
+        log_file.flush()?;
+    }
+    // the old file must be closed, and so unlocked, for rotation to archive it
+    *live_log = None;
+    match rotate_logs(config) {
+        Ok(rotated) => {
+            let mut log_file = open_live_log(config)?;
+            if config.get_banner() {
+                write_banner(config, &mut log_file)?;
+            }
+            *live_log = Some(log_file);
+            Ok(rotated)
+        }
+        Err(e) => {
+            // the old contents may not have been archived, so keep them and carry on after them
+            *live_log = Some(append_live_log(config)?);
+            Err(e)
+        }
+    }
+}
+
+/// Opens the live log file for appending, creating it if needed, and takes a shared lock on it.
+fn append_live_log(config: &LoggingConfig) -> io::Result<fs::File> {
+    let log_folder = config.get_log_folder();
+    let _rotation_lock = lock_log_folder(log_folder)?;
+    let log_file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_folder.join(config.get_live_filename()))?;
+    log_file.lock_shared()?;
+    Ok(log_file)
+}
+
+/// Spawns a thread which calls [`reopen_log_file`] whenever the process receives `SIGHUP`, the
+/// convention `logrotate` and service managers expect. Errors are logged. This function is only
+/// available on Unix.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` the logger was initialized with.
+///
+/// # Errors
+///
+/// An error is returned if the signal handler could not be registered or the thread could not be
+/// spawned.
+///
+/// # Examples
+/// ```no_run
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger, install_sighup_rotation};
+/// # fn main() -> dablenutil::Result<()> {
+/// let config = LoggingConfig::new(std::path::PathBuf::from("logs"));
+/// init_simple_logger(&config)?;
+/// install_sighup_rotation(&config)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(unix)]
+pub fn install_sighup_rotation(config: &LoggingConfig) -> crate::Result<()> {
+    let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])?;
+    let config = config.clone();
+    std::thread::Builder::new()
+        .name("sighup-rotation".to_string())
+        .spawn(move || {
+            for _ in signals.forever() {
+                match reopen_log_file(&config) {
+                    Ok(rotated) => log::info!("Reopened log file, rotated {} logs", rotated.len()),
+                    Err(e) => log::error!("Failed to reopen log file: {}", e),
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Installs a panic hook which logs panics as errors under the `panic` target and remembers the
+/// last panic message for [`create_crash_report`]. The previous hook still runs afterwards, so the
+/// usual message is printed to stderr too.
+///
+/// # Examples
+/// ```
+/// # use dablenutil::logging::{install_panic_hook, last_panic};
+/// install_panic_hook();
+/// let _ = std::panic::catch_unwind(|| panic!("oh no"));
+/// assert!(last_panic().unwrap().contains("oh no"));
+/// ```
+pub fn install_panic_hook() {
+    let _ = LAST_PANIC.set(Mutex::new(None));
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let thread = std::thread::current();
+        let mut message = format!(
+            "thread '{}' panicked: {}",
+            thread.name().unwrap_or("<unnamed>"),
+            payload
+        );
+        if let Some(location) = info.location() {
+            message = format!("{} at {}", message, location);
+        }
+        log::error!(target: "panic", "{}", message);
+        if let Some(last_panic) = LAST_PANIC.get() {
+            *last_panic.lock().unwrap_or_else(PoisonError::into_inner) = Some(message);
+        }
+        previous(info);
+    }));
+}
+
+/// Gets the message of the last panic, if [`install_panic_hook`] was called and anything has
+/// panicked since.
+pub fn last_panic() -> Option<String> {
+    LAST_PANIC
+        .get()?
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Bundles the live log, the most recent archives, a summary of the system, and the last panic
+/// message (see [`install_panic_hook`]) into a zip file at `dest`, for users to attach to bug
+/// reports. Only available when the `crash_report` feature is enabled.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` of the logs to include.
+/// * `dest` - The path of the zip file to create. It is overwritten if it exists.
+///
+/// # Errors
+///
+/// An error is returned if the logs could not be read or the zip file could not be written.
+///
+/// # Examples
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, create_crash_report, rotate_logs};
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_crash_report");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let config = LoggingConfig::new(log_folder.clone()).package_name(Some("my-app"));
+/// # fs::create_dir_all(&log_folder)?;
+/// fs::write(log_folder.join("latest.log"), "old run")?;
+/// rotate_logs(&config)?;
+/// fs::write(log_folder.join("latest.log"), "this run")?;
+/// let report = log_folder.join("report.zip");
+/// create_crash_report(&config, &report)?;
+/// let archive = zip::ZipArchive::new(fs::File::open(&report)?).unwrap();
+/// let mut names: Vec<&str> = archive.file_names().collect();
+/// names.sort_unstable();
+/// assert_eq!(names.len(), 3);
+/// assert_eq!(names[0], "latest.log");
+/// assert!(names[1].ends_with(".log.gz"));
+/// assert_eq!(names[2], "system-info.txt");
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "crash_report")]
+pub fn create_crash_report(config: &LoggingConfig, dest: &Path) -> crate::Result<()> {
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    const MAX_ARCHIVES: usize = 5;
+
+    let log_folder = config.get_log_folder();
+    let mut zip = ZipWriter::new(fs::File::create(dest)?);
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    // archives are already compressed
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+
+    let live_filename = config.get_live_filename();
+    let live_log = log_folder.join(&live_filename);
+    if live_log.is_file() {
+        zip.start_file(live_filename.as_str(), deflated)?;
+        io::copy(&mut fs::File::open(live_log)?, &mut zip)?;
+    }
+
+    let mut archives = Vec::new();
+    for entry in fs::read_dir(log_folder)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "gz") {
+            archives.push((log_time(&entry.metadata()?), path));
+        }
+    }
+    archives.sort_unstable_by_key(|(time, _)| std::cmp::Reverse(*time));
+    for (_, path) in archives.into_iter().take(MAX_ARCHIVES) {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        zip.start_file(name, stored)?;
+        io::copy(&mut fs::File::open(path)?, &mut zip)?;
+    }
+
+    zip.start_file("system-info.txt", deflated)?;
+    write_system_info(config, &mut zip)?;
+
+    if let Some(panic) = last_panic() {
+        zip.start_file("panic.txt", deflated)?;
+        writeln!(zip, "{}", panic)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// Writes a plain-text summary of the app and system for [`create_crash_report`].
+#[cfg(feature = "crash_report")]
+fn write_system_info<W: Write>(config: &LoggingConfig, writer: &mut W) -> io::Result<()> {
+    use std::env::consts::{ARCH, FAMILY, OS};
+
+    writeln!(
+        writer,
+        "package: {}",
+        config.get_package_name().unwrap_or("unknown")
+    )?;
+    writeln!(
+        writer,
+        "version: {}",
+        config.get_package_version().unwrap_or("unknown")
+    )?;
+    if let Some(hash) = config.get_git_hash() {
+        writeln!(writer, "git hash: {}", hash)?;
+    }
+    writeln!(writer, "os: {} ({})", OS, FAMILY)?;
+    writeln!(writer, "arch: {}", ARCH)?;
+    writeln!(writer, "pid: {}", std::process::id())?;
+    writeln!(writer, "time: {}", crate::time_utils::now_iso8601())?;
+    writeln!(
+        writer,
+        "uptime: {:.1}s",
+        RunClock::global().uptime().as_secs_f64()
+    )?;
+    if let Ok(exe) = std::env::current_exe() {
+        writeln!(writer, "executable: {}", exe.display())?;
+    }
+    Ok(())
+}
+
+/// Writes to the live log file, which [`reopen_log_file`] can swap out. Records written while no
+/// file is open are discarded.
+#[derive(Clone)]
+pub(crate) struct LiveLogWriter(Arc<Mutex<Option<fs::File>>>);
+
+impl LiveLogWriter {
+    /// Constructs a writer to `log_file`.
+    pub(crate) fn new(log_file: fs::File) -> Self {
+        Self(Arc::new(Mutex::new(Some(log_file))))
+    }
+}
+
+impl Write for LiveLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            Some(log_file) => log_file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            Some(log_file) => log_file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sets the global logger to a terminal logger plus a file logger writing to `log_writer`, wrapped
+/// in the rate limiting and deduplication layers enabled in `config`.
+pub(crate) fn install_logger<W: Write + Send + 'static>(
+    config: &LoggingConfig,
+    log_writer: W,
+) -> crate::Result<()> {
+    let term_format = config.effective_format(config.get_term_format());
+    let file_format = config.effective_format(config.get_file_format());
+    let max_level = config
+        .get_term_level_filter()
+        .max(config.get_file_level_filter());
+    // errors must get through to the reporter even if both sinks are off
+    #[cfg(feature = "error_reporter")]
+    let max_level = if config.error_reporting.is_some() {
+        max_level.max(LevelFilter::Error)
+    } else {
+        max_level
+    };
+    let sinks = Arc::new(Sinks {
+        fixed: CombinedLogger::new(vec![
+            TermLogger::new(
+                config.get_term_level_filter(),
+                term_format.simplelog_config(),
+                TerminalMode::Mixed,
+                ColorChoice::Auto,
+            ),
+            WriteLogger::new(
+                config.get_file_level_filter(),
+                file_format.simplelog_config(),
+                AnsiStripWriter::new(log_writer),
+            ),
+        ]),
+        extra: RwLock::new(Vec::new()),
+        next_id: AtomicU64::new(0),
+        format: file_format,
+        base_level: max_level,
+    });
+    let logger: Box<dyn Log> = Box::new(SharedSinks(Arc::clone(&sinks)));
+    // tagging sits below everything else so summary lines are tagged too
+    let logger = match config.get_tag() {
+        Some(tag) => Box::new(TagLogger {
+            inner: logger,
+            tag: tag.to_string(),
+        }),
+        None => logger,
+    };
+    #[cfg(feature = "error_reporter")]
+    let logger = match config.error_reporting.clone() {
+        Some(reporting) => Box::new(report::ReportingLogger::new(logger, reporting)?),
+        None => logger,
+    };
+    // rate limiting sits below deduplication so collapsed repeats don't count against the limit
+    let logger = match config.get_rate_limit() {
+        Some(records_per_sec) => Box::new(RateLimitLogger::new(logger, records_per_sec)?),
+        None => logger,
+    };
+    let logger = match config.get_dedup_window() {
+        Some(window) => Box::new(DedupLogger::new(logger, window)?),
+        None => logger,
+    };
+    log::set_boxed_logger(logger)?;
+    log::set_max_level(max_level);
+    // installing the logger only succeeds once, so this can't already be set
+    let _ = SINKS.set(sinks);
+    set_active_log(config);
+    Ok(())
+}
+
+/// Remembers the log file and folder used by the logger set up with `config`, for
+/// [`current_log_file`] and [`current_log_folder`].
+fn set_active_log(config: &LoggingConfig) {
+    let folder = config.get_log_folder();
+    let folder = std::path::absolute(folder).unwrap_or_else(|_| folder.to_path_buf());
+    let file = folder.join(config.get_live_filename());
+    let _ = ACTIVE_LOG.set((file, folder));
+}
+
+/// Gets the absolute path of the live log file of the global logger, as resolved when it was
+/// initialized. Returns `None` if the logger was not initialized by this crate.
+///
+/// # Examples
+/// ```
+/// # use dablenutil::logging::{
+/// #     LoggingConfig, current_log_file, current_log_folder, init_simple_logger,
+/// # };
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_current_log_file");
+/// # let _ = std::fs::remove_dir_all(&log_folder);
+/// assert_eq!(current_log_file(), None);
+/// init_simple_logger(&LoggingConfig::new(log_folder.clone()))?;
+/// assert_eq!(current_log_file(), Some(log_folder.join("latest.log").as_path()));
+/// assert_eq!(current_log_folder(), Some(log_folder.as_path()));
+/// # std::fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn current_log_file() -> Option<&'static Path> {
+    ACTIVE_LOG.get().map(|(file, _)| file.as_path())
+}
+
+/// Gets the absolute path of the folder holding the live log file and archives of the global
+/// logger, as resolved when it was initialized. Returns `None` if the logger was not initialized
+/// by this crate.
+pub fn current_log_folder() -> Option<&'static Path> {
+    ACTIVE_LOG.get().map(|(_, folder)| folder.as_path())
+}
+
+/// Identifies a sink added with [`LoggerHandle::add_writer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SinkId(u64);
+
+/// A handle to the logger set up by [`init_simple_logger`] (or
+/// [`init_async_logger`](crate::tokio::init_async_logger)), for changing it after initialization.
+#[derive(Clone)]
+pub struct LoggerHandle {
+    sinks: Arc<Sinks>,
+}
+
+impl LoggerHandle {
+    /// Gets a handle to the current logger, or `None` if it was not initialized by this module.
+    pub fn current() -> Option<Self> {
+        SINKS.get().map(|sinks| Self {
+            sinks: Arc::clone(sinks),
+        })
+    }
+
+    /// Attaches an extra sink, such as an in-app console, which receives records at `level` and
+    /// more severe. Records are formatted like the log file, without ANSI escape sequences.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The writer to send formatted records to.
+    /// * `level` - The level filter for the sink.
+    ///
+    /// # Examples
+    /// ```
+    /// # use dablenutil::logging::{LoggerHandle, LoggingConfig, init_simple_logger};
+    /// # use log::LevelFilter;
+    /// # use std::{io::{self, Write}, sync::{Arc, Mutex}};
+    /// # fn main() -> dablenutil::Result<()> {
+    /// #[derive(Clone, Default)]
+    /// struct Console(Arc<Mutex<Vec<u8>>>);
+    ///
+    /// impl Write for Console {
+    ///     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    ///         self.0.lock().unwrap().write(buf)
+    ///     }
+    ///
+    ///     fn flush(&mut self) -> io::Result<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let log_folder = std::env::temp_dir().join("dablenutil_logger_handle");
+    /// # let _ = std::fs::remove_dir_all(&log_folder);
+    /// init_simple_logger(&LoggingConfig::new(log_folder.clone()))?;
+    /// let handle = LoggerHandle::current().expect("logger was initialized");
+    /// let console = Console::default();
+    /// let id = handle.add_writer(Box::new(console.clone()), LevelFilter::Debug);
+    /// log::debug!("only in the console");
+    /// assert!(handle.remove_writer(id));
+    /// assert_eq!(log::max_level(), LevelFilter::Info);
+    /// log::info!("not in the console");
+    /// let contents = String::from_utf8(console.0.lock().unwrap().clone()).unwrap();
+    /// assert!(contents.contains("only in the console"));
+    /// assert!(!contents.contains("not in the console"));
+    /// # std::fs::remove_dir_all(&log_folder)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_writer(&self, writer: Box<dyn Write + Send>, level: LevelFilter) -> SinkId {
+        let id = SinkId(self.sinks.next_id.fetch_add(1, Ordering::Relaxed));
+        let sink = WriteLogger::new(
+            level,
+            self.sinks.format.simplelog_config(),
+            AnsiStripWriter::new(writer),
+        );
+        self.sinks
+            .extra
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((id, sink));
+        if level > log::max_level() {
+            log::set_max_level(level);
+        }
+        id
+    }
+
+    /// Detaches a sink added with [`add_writer`](LoggerHandle::add_writer). Returns `false` if
+    /// the sink was already removed. The global max level is lowered again to what the remaining
+    /// sinks need, so records no sink wants are skipped cheaply.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The sink to remove.
+    pub fn remove_writer(&self, id: SinkId) -> bool {
+        let mut extra = self
+            .sinks
+            .extra
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let len = extra.len();
+        extra.retain(|(sink_id, _)| *sink_id != id);
+        let max_level = extra
+            .iter()
+            .map(|(_, sink)| sink.level())
+            .fold(self.sinks.base_level, Ord::max);
+        log::set_max_level(max_level);
+        extra.len() != len
+    }
+}
+
+/// The terminal and file loggers, plus any sinks attached through a [`LoggerHandle`].
+struct Sinks {
+    fixed: Box<CombinedLogger>,
+    extra: RwLock<Vec<(SinkId, Box<dyn SharedLogger>)>>,
+    next_id: AtomicU64,
+    format: LogFormat,
+    // the max level needed by the terminal and file loggers
+    base_level: LevelFilter,
+}
+
+/// Lets the global logger and [`LoggerHandle`]s share the same [`Sinks`].
+struct SharedSinks(Arc<Sinks>);
+
+impl Log for SharedSinks {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.fixed.enabled(metadata)
+            || self
+                .0
+                .extra
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .any(|(_, sink)| sink.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        self.0.fixed.log(record);
+        for (_, sink) in self
+            .0
+            .extra
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            sink.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.0.fixed.flush();
+        for (_, sink) in self
+            .0
+            .extra
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            sink.flush();
+        }
+    }
+}
+
+/// Initialize a `tracing` subscriber equivalent to [`init_simple_logger`]. Events are outputted
+/// to the terminal as well as the specified file, using the same levels, filename, locking, and
+/// banner. Records from the `log` crate are forwarded to the subscriber too.
+///
+/// Like with [`init_simple_logger`], the logs are only rotated first if
+/// [`LoggingConfig::rotate_on_init`] is enabled, and the log file is shared with
+/// [`reopen_log_file`], so rotating on `SIGHUP` works too. [`LoggingConfig::dedup_window`],
+/// [`LoggingConfig::rate_limit`], and [`LoggingConfig::tag`] are not supported.
+///
+/// This function is only available when the `tracing` feature is enabled.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` to use.
+///
+/// # Errors
+///
+/// An `InvalidInput` error is returned if `config` sets a dedup window, rate limit, or tag. Other
+/// errors are returned if the logs could not be rotated, if the log files could not be created for
+/// some reason, if the log file is in use by another process, or if a global subscriber or logger
+/// is already set.
+///
+/// # Examples
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_tracing_subscriber, reopen_log_file};
+/// # use std::{fs, time::Duration};
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_init_tracing");
+/// # let _ = fs::remove_dir_all(&log_folder);
+/// let deduplicated = LoggingConfig::new(log_folder.clone()).dedup_window(Duration::from_secs(5));
+/// assert!(init_tracing_subscriber(&deduplicated).is_err());
+///
+/// let config = LoggingConfig::new(log_folder.clone());
+/// init_tracing_subscriber(&config)?;
+/// tracing::info!("from tracing");
+/// log::info!("from log");
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert!(contents.contains("from tracing"));
+/// assert!(contents.contains("from log"));
+///
+/// reopen_log_file(&config)?;
+/// tracing::info!("after reopening");
+/// let contents = fs::read_to_string(log_folder.join("latest.log"))?;
+/// assert!(!contents.contains("from tracing"));
+/// assert!(contents.contains("after reopening"));
+/// # fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "tracing")]
+pub fn init_tracing_subscriber(config: &LoggingConfig) -> crate::Result<()> {
+    use std::io::IsTerminal;
+    use tracing_subscriber::{
+        filter,
+        fmt::{time::ChronoLocal, writer::MakeWriterExt, MakeWriter},
+        layer::SubscriberExt,
+        util::SubscriberInitExt,
+        Layer, Registry,
+    };
+
+    type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+    fn tracing_filter(level_filter: LevelFilter) -> filter::LevelFilter {
+        match level_filter {
+            LevelFilter::Off => filter::LevelFilter::OFF,
+            LevelFilter::Error => filter::LevelFilter::ERROR,
+            LevelFilter::Warn => filter::LevelFilter::WARN,
+            LevelFilter::Info => filter::LevelFilter::INFO,
+            LevelFilter::Debug => filter::LevelFilter::DEBUG,
+            LevelFilter::Trace => filter::LevelFilter::TRACE,
+        }
+    }
+
+    fn fmt_layer<W>(format: LogFormat, writer: W, ansi: bool, level: LevelFilter) -> BoxedLayer
+    where
+        W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+    {
+        let layer = tracing_subscriber::fmt::layer()
+            .with_writer(writer)
+            .with_ansi(ansi)
+            .with_level(format.get_show_level())
+            .with_target(format.get_show_target())
+            .with_thread_names(format.get_show_thread())
+            .with_thread_ids(format.get_show_thread())
+            .with_file(format.get_show_location())
+            .with_line_number(format.get_show_location());
+        if format.get_show_time() {
+            layer
+                .with_timer(ChronoLocal::new("[%H:%M:%S]".to_string()))
+                .with_filter(tracing_filter(level))
+                .boxed()
+        } else {
+            layer
+                .without_time()
+                .with_filter(tracing_filter(level))
+                .boxed()
+        }
+    }
+
+    if config.get_dedup_window().is_some()
+        || config.get_rate_limit().is_some()
+        || config.get_tag().is_some()
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "dedup windows, rate limits, and tags are not supported by init_tracing_subscriber",
+        )
+        .into());
+    }
+    if config.get_rotate_on_init() {
+        rotate_logs(config)?;
+    }
+    let log_path = config.get_log_folder();
+    create_dir_if_not_exists(log_path)?;
+    let mut log_file = open_live_log(config)?;
+    if config.get_banner() {
+        write_banner(config, &mut log_file)?;
+    }
+    let live_log = LiveLogWriter::new(log_file);
+    // like simplelog's mixed mode, errors go to stderr and everything else to stdout
+    let term_writer = io::stderr
+        .with_max_level(tracing::Level::ERROR)
+        .or_else(io::stdout);
+    // escape codes would end up in redirected output
+    let term_ansi = io::stdout().is_terminal() && io::stderr().is_terminal();
+    let layers = vec![
+        fmt_layer(
+            config.effective_format(config.get_term_format()),
+            term_writer,
+            term_ansi,
+            config.get_term_level_filter(),
+        ),
+        fmt_layer(
+            config.effective_format(config.get_file_format()),
+            Mutex::new(AnsiStripWriter::new(live_log.clone())),
+            false,
+            config.get_file_level_filter(),
+        ),
+    ];
+    tracing_subscriber::registry().with(layers).try_init()?;
+    register_live_log(&live_log);
+    set_active_log(config);
+    Ok(())
+}
+
+/// Where an [`AnsiStripWriter`] is within an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AnsiState {
+    Text,
+    Escape,
+    Csi,
+    Osc,
+    OscEscape,
+}
+
+/// A writer which strips ANSI escape sequences (such as colors) before writing to another writer,
+/// so log files stay plain text. Sequences split across writes are handled.
+struct AnsiStripWriter<W> {
+    inner: W,
+    state: AnsiState,
+}
+
+impl<W: Write> AnsiStripWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            state: AnsiState::Text,
+        }
+    }
+}
+
+impl<W: Write> Write for AnsiStripWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        const ESC: u8 = 0x1b;
+        const BEL: u8 = 0x07;
+        let mut plain = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            self.state = match (self.state, byte) {
+                (AnsiState::Text, ESC) => AnsiState::Escape,
+                (AnsiState::Text, _) => {
+                    plain.push(byte);
+                    AnsiState::Text
+                }
+                (AnsiState::Escape, b'[') => AnsiState::Csi,
+                (AnsiState::Escape, b']') => AnsiState::Osc,
+                // CSI sequences end with a byte in `@` to `~`; other escapes are two bytes long
+                (AnsiState::Csi, 0x40..=0x7e) | (AnsiState::Escape | AnsiState::OscEscape, _) => {
+                    AnsiState::Text
+                }
+                (AnsiState::Csi, _) => AnsiState::Csi,
+                (AnsiState::Osc, BEL) => AnsiState::Text,
+                (AnsiState::Osc, ESC) => AnsiState::OscEscape,
+                (AnsiState::Osc, _) => AnsiState::Osc,
+            };
+        }
+        self.inner.write_all(&plain)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A logger which prefixes every record with a tag before passing it to another logger.
+struct TagLogger {
+    inner: Box<dyn Log>,
+    tag: String,
+}
+
+impl Log for TagLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.inner.log(
+            &Record::builder()
+                .metadata(record.metadata().clone())
+                .args(format_args!("[{}] {}", self.tag, record.args()))
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// The current one-second window of a target in a [`RateLimitLogger`].
+struct RateWindow {
+    start: Instant,
+    logged: u32,
+    dropped: u64,
+}
+
+/// The state of a [`RateLimitLogger`], shared with the thread logging its summaries.
+struct RateLimits {
+    inner: Box<dyn Log>,
+    records_per_sec: u32,
+    windows: Mutex<HashMap<String, RateWindow>>,
+}
+
+impl RateLimits {
+    /// Logs a summary of the records dropped from `target` and resets the count.
+    fn log_dropped(&self, target: &str, window: &mut RateWindow) {
+        if window.dropped == 0 {
+            return;
+        }
+        self.inner.log(
+            &Record::builder()
+                .args(format_args!(
+                    "dropped {} records exceeding the rate limit of {}/s",
+                    window.dropped, self.records_per_sec
+                ))
+                .level(Level::Warn)
+                .target(target)
+                .build(),
+        );
+        window.dropped = 0;
+    }
+
+    /// Logs the summaries of every window which has expired by `now`, and starts new ones.
+    fn log_expired(&self, windows: &mut HashMap<String, RateWindow>, now: Instant) {
+        for (target, window) in windows.iter_mut() {
+            if now.saturating_duration_since(window.start) >= RateLimitLogger::WINDOW {
+                self.log_dropped(target, window);
+                window.start = now;
+                window.logged = 0;
+            }
+        }
+    }
+}
+
+/// A logger which drops records beyond a per-target rate before passing them to another logger.
+struct RateLimitLogger(Arc<RateLimits>);
+
+impl RateLimitLogger {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    /// Wraps `inner`, spawning the thread which logs the summaries of expired windows, so targets
+    /// which go quiet after a burst still report their drops.
+    fn new(inner: Box<dyn Log>, records_per_sec: u32) -> io::Result<Self> {
+        let limits = Arc::new(RateLimits {
+            inner,
+            records_per_sec,
+            windows: Mutex::new(HashMap::new()),
+        });
+        let weak = Arc::downgrade(&limits);
+        std::thread::Builder::new()
+            .name("rate-limit".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(Self::WINDOW);
+                // the logger is gone, so there is nothing left to report
+                let Some(limits) = weak.upgrade() else {
+                    return;
+                };
+                let mut windows = limits
+                    .windows
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                limits.log_expired(&mut windows, Instant::now());
+            })?;
+        Ok(Self(limits))
+    }
+}
+
+impl Log for RateLimitLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let limits = &self.0;
+        let mut windows = limits
+            .windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        limits.log_expired(&mut windows, now);
+        let window = windows
+            .entry(record.target().to_string())
+            .or_insert(RateWindow {
+                start: now,
+                logged: 0,
+                dropped: 0,
+            });
+        if window.logged < limits.records_per_sec {
+            window.logged += 1;
+            limits.inner.log(record);
+        } else {
+            window.dropped = window.dropped.saturating_add(1);
+        }
+    }
+
+    fn flush(&self) {
+        let limits = &self.0;
+        let mut windows = limits
+            .windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (target, window) in windows.iter_mut() {
+            limits.log_dropped(target, window);
+        }
+        limits.inner.flush();
+    }
+}
+
+/// The last record seen by a [`DedupLogger`].
+struct LastRecord {
+    level: Level,
+    target: String,
+    message: String,
+    first_seen: Instant,
+    repeats: u32,
+}
+
+/// The state of a [`DedupLogger`], shared with the thread logging the summaries of expired
+/// windows.
+struct DedupState {
+    inner: Box<dyn Log>,
+    window: Duration,
+    last: Mutex<Option<LastRecord>>,
+}
+
+impl DedupState {
+    /// Logs the "repeated" summary for `last`, if any records were suppressed, and resets the
+    /// count.
+    fn log_repeats(&self, last: &mut LastRecord) {
+        if last.repeats == 0 {
+            return;
+        }
+        let plural = if last.repeats == 1 { "" } else { "s" };
+        self.inner.log(
+            &Record::builder()
+                .args(format_args!(
+                    "last message repeated {} time{}",
+                    last.repeats, plural
+                ))
+                .level(last.level)
+                .target(&last.target)
+                .build(),
+        );
+        last.repeats = 0;
+    }
+}
+
+/// A logger which collapses identical consecutive records before passing them to another logger.
+struct DedupLogger(Arc<DedupState>);
+
+impl DedupLogger {
+    /// Wraps `inner`, spawning the thread which logs the summary once the window of the last
+    /// record expires, so a flood which stops still reports its repeats without waiting for the
+    /// next record.
+    fn new(inner: Box<dyn Log>, window: Duration) -> io::Result<Self> {
+        let state = Arc::new(DedupState {
+            inner,
+            window,
+            last: Mutex::new(None),
+        });
+        let weak = Arc::downgrade(&state);
+        let interval = window.min(Duration::from_secs(1));
+        std::thread::Builder::new()
+            .name("dedup".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(interval);
+                // the logger is gone, so there is nothing left to report
+                let Some(state) = weak.upgrade() else {
+                    return;
+                };
+                let mut last = state.last.lock().unwrap_or_else(PoisonError::into_inner);
+                if let Some(last) = last.as_mut() {
+                    if last.first_seen.elapsed() >= state.window {
+                        state.log_repeats(last);
+                    }
+                }
+            })?;
+        Ok(Self(state))
+    }
+}
+
+impl Log for DedupLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let state = &self.0;
+        let message = record.args().to_string();
+        // the lock is held while logging so summaries stay in order with the records around them
+        let mut last = state.last.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(last) = last.as_mut() {
+            let is_repeat = last.level == record.level()
+                && last.target == record.target()
+                && last.message == message
+                && last.first_seen.elapsed() < state.window;
+            if is_repeat {
+                last.repeats = last.repeats.saturating_add(1);
+                return;
+            }
+            state.log_repeats(last);
+        }
+        state.inner.log(record);
+        *last = Some(LastRecord {
+            level: record.level(),
+            target: record.target().to_string(),
+            message,
+            first_seen: Instant::now(),
+            repeats: 0,
+        });
+    }
+
+    fn flush(&self) {
+        let state = &self.0;
+        let mut last = state.last.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(last) = last.as_mut() {
+            state.log_repeats(last);
+        }
+        state.inner.flush();
+    }
 }
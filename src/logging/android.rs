@@ -0,0 +1,90 @@
+//! Logs to logcat on Android, where apps can't write log files next to the executable. Only
+//! available when the `android` feature is enabled and compiling for Android.
+
+use std::ffi::{c_char, c_int, CString};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use super::LoggingConfig;
+
+/// The logcat priorities from `android/log.h`.
+const ANDROID_LOG_VERBOSE: c_int = 2;
+const ANDROID_LOG_DEBUG: c_int = 3;
+const ANDROID_LOG_INFO: c_int = 4;
+const ANDROID_LOG_WARN: c_int = 5;
+const ANDROID_LOG_ERROR: c_int = 6;
+
+#[link(name = "log")]
+extern "C" {
+    fn __android_log_write(prio: c_int, tag: *const c_char, text: *const c_char) -> c_int;
+}
+
+/// Converts `s` to a C string, replacing interior NUL bytes, which logcat can't print anyway.
+fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "\u{FFFD}")).unwrap_or_default()
+}
+
+/// A logger writing to logcat under a fixed tag.
+struct AndroidLogger {
+    tag: CString,
+    level: LevelFilter,
+}
+
+impl Log for AndroidLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let priority = match record.level() {
+            Level::Error => ANDROID_LOG_ERROR,
+            Level::Warn => ANDROID_LOG_WARN,
+            Level::Info => ANDROID_LOG_INFO,
+            Level::Debug => ANDROID_LOG_DEBUG,
+            Level::Trace => ANDROID_LOG_VERBOSE,
+        };
+        let text = c_string(&format!("[{}] {}", record.target(), record.args()));
+        // SAFETY: both pointers are valid, NUL-terminated strings which outlive the call
+        unsafe {
+            __android_log_write(priority, self.tag.as_ptr(), text.as_ptr());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Sets the global logger to one writing to logcat, tagged with the package name from `config`.
+/// Only the package name and the terminal level filter are used; nothing is written to the log
+/// folder.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` to use.
+///
+/// # Errors
+///
+/// An error is returned if a logger was already set.
+///
+/// # Examples
+/// ```no_run
+/// # use dablenutil::logging::{LoggingConfig, init_android_logger};
+/// # fn main() -> dablenutil::Result<()> {
+/// let config = LoggingConfig::new(std::path::PathBuf::new())
+///     .package_name(Some("com.example.app"));
+/// init_android_logger(&config)?;
+/// log::info!("Hello, logcat!");
+/// # Ok(())
+/// # }
+/// ```
+pub fn init_android_logger(config: &LoggingConfig) -> crate::Result<()> {
+    let level = config.get_term_level_filter();
+    log::set_boxed_logger(Box::new(AndroidLogger {
+        tag: c_string(config.get_package_name().unwrap_or("rust")),
+        level,
+    }))?;
+    log::set_max_level(level);
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! Forwards error-level records to an external error tracker, such as Sentry or a webhook. This
+//! module is only available when the `error_reporter` feature is enabled.
+//!
+//! Set a reporter with [`LoggingConfig::error_reporter`](super::LoggingConfig::error_reporter).
+//! Records are collected on a background thread and handed to the reporter in batches, so slow
+//! network calls never block the thread that logged. Call `log::logger().flush()` before exiting
+//! to report any records still waiting in the current batch.
+
+use std::{
+    fmt,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use log::{Level, Log, Metadata, Record};
+
+/// Receives batches of error-level records.
+///
+/// # Examples
+/// ```
+/// # use dablenutil::logging::{LoggingConfig, init_simple_logger};
+/// use dablenutil::logging::report::{ErrorEvent, ErrorReporter};
+/// use std::sync::{Arc, Mutex};
+///
+/// #[derive(Clone, Default)]
+/// struct Collector(Arc<Mutex<Vec<String>>>);
+///
+/// impl ErrorReporter for Collector {
+///     fn report(&self, batch: &[ErrorEvent]) {
+///         let mut messages = self.0.lock().unwrap();
+///         messages.extend(batch.iter().map(|event| event.message.clone()));
+///     }
+/// }
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_error_reporter");
+/// # let _ = std::fs::remove_dir_all(&log_folder);
+/// let collector = Collector::default();
+/// let config = LoggingConfig::new(log_folder.clone()).error_reporter(collector.clone());
+/// init_simple_logger(&config)?;
+/// log::warn!("not reported");
+/// log::error!("disk on fire");
+/// log::logger().flush();
+/// assert_eq!(*collector.0.lock().unwrap(), ["disk on fire"]);
+/// # std::fs::remove_dir_all(&log_folder)?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait ErrorReporter: Send + Sync {
+    /// Reports a batch of records. This is called on a background thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `batch` - The records, oldest first.
+    fn report(&self, batch: &[ErrorEvent]);
+
+    /// Flushes anything the reporter buffers itself. This is called after the final batch when the
+    /// logger is flushed. Does nothing by default.
+    fn flush(&self) {}
+}
+
+/// An error-level record, as passed to an [`ErrorReporter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorEvent {
+    /// The formatted message.
+    pub message: String,
+    /// The target of the record.
+    pub target: String,
+    /// The module the record was logged from, if known.
+    pub module_path: Option<String>,
+    /// The source file the record was logged from, if known.
+    pub file: Option<String>,
+    /// The line the record was logged from, if known.
+    pub line: Option<u32>,
+    /// The name of the thread the record was logged from, if it has one.
+    pub thread: Option<String>,
+    /// When the record was logged.
+    pub time: DateTime<Local>,
+}
+
+impl ErrorEvent {
+    fn from_record(record: &Record) -> Self {
+        Self {
+            message: record.args().to_string(),
+            target: record.target().to_string(),
+            module_path: record.module_path().map(str::to_string),
+            file: record.file().map(str::to_string),
+            line: record.line(),
+            thread: thread::current().name().map(str::to_string),
+            time: Local::now(),
+        }
+    }
+}
+
+/// An [`ErrorReporter`] and its batching settings, as stored in a
+/// [`LoggingConfig`](super::LoggingConfig).
+#[derive(Clone)]
+pub(crate) struct Reporting {
+    pub(crate) reporter: Arc<dyn ErrorReporter>,
+    pub(crate) batch_size: usize,
+    pub(crate) batch_interval: Duration,
+}
+
+impl fmt::Debug for Reporting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reporting")
+            .field("batch_size", &self.batch_size)
+            .field("batch_interval", &self.batch_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message to the reporting thread.
+enum Message {
+    Event(ErrorEvent),
+    Flush(mpsc::SyncSender<()>),
+}
+
+/// A logger which sends error-level records to an [`ErrorReporter`] before passing every record to
+/// another logger.
+pub(crate) struct ReportingLogger {
+    inner: Box<dyn Log>,
+    sender: Mutex<Sender<Message>>,
+}
+
+impl ReportingLogger {
+    /// Wraps `inner`, spawning the thread which batches records for `reporting`.
+    pub(crate) fn new(inner: Box<dyn Log>, reporting: Reporting) -> std::io::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("error-reporter".to_string())
+            .spawn(move || {
+                let Reporting {
+                    reporter,
+                    batch_size,
+                    batch_interval,
+                } = reporting;
+                let mut batch = Vec::with_capacity(batch_size);
+                let mut deadline = Instant::now() + batch_interval;
+                loop {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    match receiver.recv_timeout(timeout) {
+                        Ok(Message::Event(event)) => {
+                            batch.push(event);
+                            if batch.len() >= batch_size {
+                                reporter.report(&batch);
+                                batch.clear();
+                                deadline = Instant::now() + batch_interval;
+                            }
+                        }
+                        Ok(Message::Flush(done)) => {
+                            if !batch.is_empty() {
+                                reporter.report(&batch);
+                                batch.clear();
+                            }
+                            reporter.flush();
+                            let _ = done.send(());
+                        }
+                        Err(RecvTimeoutError::Timeout) => {
+                            if !batch.is_empty() {
+                                reporter.report(&batch);
+                                batch.clear();
+                            }
+                            deadline = Instant::now() + batch_interval;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => {
+                            if !batch.is_empty() {
+                                reporter.report(&batch);
+                            }
+                            reporter.flush();
+                            return;
+                        }
+                    }
+                }
+            })?;
+        Ok(Self {
+            inner,
+            sender: Mutex::new(sender),
+        })
+    }
+
+    fn send(&self, message: Message) -> bool {
+        self.sender
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .send(message)
+            .is_ok()
+    }
+}
+
+impl Log for ReportingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() == Level::Error || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() == Level::Error {
+            self.send(Message::Event(ErrorEvent::from_record(record)));
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+        let (done, wait) = mpsc::sync_channel(1);
+        if self.send(Message::Flush(done)) {
+            let _ = wait.recv();
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Loads plugins from dynamic libraries. This module is only available when the `plugins` feature
+//! is enabled.
+//!
+//! A plugin is a shared library named after the platform it was built for, the same way
+//! [`platform_specific_executable_name`](crate::platform_specific_executable_name) names
+//! executables: `{DLL_PREFIX}{name}_{OS}_{ARCH}{DLL_SUFFIX}`, e.g. `libgreeter_linux_x86_64.so`
+//! or `greeter_windows_x86_64.dll`. Libraries built for other platforms are ignored.
+//!
+//! Every plugin must export a function reporting which ABI version it was built against:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub extern "C" fn dablenutil_plugin_abi() -> u32 {
+//!     1 // dablenutil::plugins::ABI_VERSION
+//! }
+//! ```
+//!
+//! Plugins compiled to WebAssembly can be run in a sandbox with the [`wasm`] module instead.
+
+#[cfg(feature = "wasm_plugins")]
+pub mod wasm;
+
+use std::{
+    env::consts::{DLL_PREFIX, DLL_SUFFIX},
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use libloading::{Library, Symbol};
+
+use crate::PLATFORM_SUFFIX;
+
+/// The plugin ABI version supported by this version of the crate. Plugins reporting a different
+/// version are rejected.
+pub const ABI_VERSION: u32 = 1;
+
+/// The name of the function every plugin exports to report its ABI version.
+pub const ABI_SYMBOL: &str = "dablenutil_plugin_abi";
+
+/// A loaded plugin. The library stays loaded for as long as this value lives.
+#[derive(Debug)]
+pub struct Plugin {
+    name: String,
+    path: PathBuf,
+    library: Library,
+}
+
+impl Plugin {
+    /// Loads a single plugin and checks its ABI version.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the shared library.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the library could not be loaded, does not export
+    /// [`ABI_SYMBOL`], or reports an ABI version other than [`ABI_VERSION`].
+    ///
+    /// # Safety
+    ///
+    /// Loading a library runs its initialization code, and the ABI function is called without any
+    /// way to check its signature. Only load libraries you trust.
+    pub unsafe fn load<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let library = Library::new(path)?;
+        let abi: Symbol<extern "C" fn() -> u32> = library.get(ABI_SYMBOL.as_bytes())?;
+        let found = abi();
+        if found != ABI_VERSION {
+            return Err(crate::Error::PluginAbiMismatch {
+                path: path.to_path_buf(),
+                expected: ABI_VERSION,
+                found,
+            });
+        }
+        let name = plugin_name(path).unwrap_or_default().to_string();
+        Ok(Self {
+            name,
+            path: path.to_path_buf(),
+            library,
+        })
+    }
+
+    /// Gets the name of the plugin, which is its filename without the platform-specific parts.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the path the plugin was loaded from.
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets a typed entry point exported by the plugin.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The name of the exported function or static.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the plugin does not export `symbol`.
+    ///
+    /// # Safety
+    ///
+    /// `T` must match the actual type of the exported symbol.
+    pub unsafe fn entry_point<T>(&self, symbol: &str) -> crate::Result<Symbol<'_, T>> {
+        Ok(self.library.get(symbol.as_bytes())?)
+    }
+}
+
+/// Discovers and loads every plugin built for the current platform in `dir`. Other files are
+/// ignored. Plugins are returned sorted by path.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to search. Subdirectories are not searched.
+///
+/// # Errors
+///
+/// An error is returned if `dir` could not be read, or if any plugin fails to load (see
+/// [`Plugin::load`]).
+///
+/// # Safety
+///
+/// See [`Plugin::load`]. Every matching library in `dir` is loaded, so `dir` must only contain
+/// trusted libraries.
+///
+/// # Examples
+/// ```
+/// # use std::fs;
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_plugins_load");
+/// # fs::create_dir_all(&dir)?;
+/// fs::write(dir.join("notes.txt"), "not a plugin")?;
+/// let plugins = unsafe { dablenutil::plugins::load(&dir)? };
+/// assert!(plugins.is_empty());
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub unsafe fn load<P: AsRef<Path>>(dir: P) -> crate::Result<Vec<Plugin>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && plugin_name(&path).is_some() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths.into_iter().map(|path| Plugin::load(path)).collect()
+}
+
+/// Gets the plugin name from a library path, or `None` if the path doesn't follow the naming
+/// convention for the current platform.
+fn plugin_name(path: &Path) -> Option<&str> {
+    let file_name = path.file_name().and_then(OsStr::to_str)?;
+    let name = file_name
+        .strip_prefix(DLL_PREFIX)?
+        .strip_suffix(DLL_SUFFIX)?
+        .strip_suffix(PLATFORM_SUFFIX)?;
+    (!name.is_empty()).then_some(name)
+}
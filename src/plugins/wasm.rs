@@ -0,0 +1,357 @@
+//! Runs plugins compiled to WebAssembly. This module is only available when the `wasm_plugins`
+//! feature is enabled.
+//!
+//! Unlike dynamic library plugins, WebAssembly plugins run in a sandbox: they cannot touch the
+//! host's memory, and can only interact with the outside world through a small host API imported
+//! from the `dablenutil` module:
+//!
+//! * `log(level: i32, ptr: i32, len: i32)` - Logs the UTF-8 message at `ptr` with the `log` crate.
+//!   Levels 1 to 5 map to error, warn, info, debug, and trace.
+//! * `read_file(path_ptr: i32, path_len: i32, buf_ptr: i32, buf_len: i32) -> i64` - Reads a file
+//!   into the buffer at `buf_ptr`, truncating it to `buf_len` bytes. Returns the full length of
+//!   the file, or -1 on error.
+//! * `write_file(path_ptr: i32, path_len: i32, data_ptr: i32, data_len: i32) -> i32` - Writes the
+//!   data at `data_ptr` to a file, replacing it if it exists. Returns 0 on success, or -1 on error.
+//!
+//! File paths are relative to the plugin's filesystem root (see [`WasmPlugin::fs_root`]) and may
+//! not leave it, not even through symbolic links. Without a root, every file operation fails.
+//! Plugins using `log`, `read_file`, or `write_file` must export their memory as `memory`, and
+//! each buffer they pass must lie within it and be at most [`MAX_TRANSFER`] bytes long.
+//!
+//! Every call into a plugin may execute at most a budget of instructions (see
+//! [`WasmPlugin::fuel`]), so a plugin stuck in a loop traps instead of hanging the host.
+//!
+//! Like dynamic library plugins, every plugin must export a `dablenutil_plugin_abi` function
+//! returning [`ABI_VERSION`].
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use log::Level;
+use wasmi::{
+    Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, WasmParams,
+    WasmResults,
+};
+
+use super::{ABI_SYMBOL, ABI_VERSION};
+
+/// The largest buffer, in bytes, a plugin may pass to a host function.
+pub const MAX_TRANSFER: u32 = 16 * 1024 * 1024;
+
+/// The default number of instructions a single call into a plugin may execute, roughly a second
+/// of work.
+pub const DEFAULT_FUEL: u64 = 1_000_000_000;
+
+/// The state available to host functions.
+struct HostState {
+    name: String,
+    fs_root: Option<PathBuf>,
+    fuel: u64,
+}
+
+/// A loaded WebAssembly plugin.
+pub struct WasmPlugin {
+    store: Store<HostState>,
+    instance: Instance,
+}
+
+impl WasmPlugin {
+    /// Gets the name of the plugin, which is its filename without the extension.
+    pub fn get_name(&self) -> &str {
+        &self.store.data().name
+    }
+
+    /// Gets the directory the plugin's file operations are confined to, if any.
+    pub fn get_fs_root(&self) -> Option<&Path> {
+        self.store.data().fs_root.as_deref()
+    }
+
+    /// Sets the directory the plugin's file operations are confined to. `None` denies all file
+    /// operations, which is the default.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory the plugin may read and write files in.
+    pub fn fs_root<P: Into<PathBuf>>(mut self, root: Option<P>) -> Self {
+        self.store.data_mut().fs_root = root.map(Into::into);
+        self
+    }
+
+    /// Gets the number of instructions a single call into the plugin may execute.
+    pub fn get_fuel(&self) -> u64 {
+        self.store.data().fuel
+    }
+
+    /// Sets the number of instructions a single call into the plugin may execute before it traps.
+    /// The default is [`DEFAULT_FUEL`].
+    ///
+    /// # Arguments
+    ///
+    /// * `fuel` - The instruction budget of each call.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::plugins::wasm;
+    /// # use std::fs;
+    ///
+    /// # fn main() -> dablenutil::Result<()> {
+    /// // exports `dablenutil_plugin_abi() -> i32` and `spin()`, which loops forever
+    /// let module = [
+    ///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    ///     0x01, 0x08, 0x02, 0x60, 0x00, 0x01, 0x7f, 0x60, 0x00, 0x00, // types
+    ///     0x03, 0x03, 0x02, 0x00, 0x01, // functions
+    ///     0x07, 0x20, 0x02, 0x15, b'd', b'a', b'b', b'l', b'e', b'n', b'u', b't', b'i', b'l', b'_',
+    ///     b'p', b'l', b'u', b'g', b'i', b'n', b'_', b'a', b'b', b'i', 0x00, 0x00,
+    ///     0x04, b's', b'p', b'i', b'n', 0x00, 0x01, // exports
+    ///     0x0a, 0x0e, 0x02, 0x04, 0x00, 0x41, 0x01, 0x0b,
+    ///     0x07, 0x00, 0x03, 0x40, 0x0c, 0x00, 0x0b, 0x0b, // code
+    /// ];
+    /// let file = std::env::temp_dir().join("dablenutil_wasm_fuel.wasm");
+    /// fs::write(&file, module)?;
+    /// let mut plugin = wasm::load(&file)?.fuel(10_000);
+    /// assert!(plugin.call::<(), ()>("spin", ()).is_err());
+    /// # fs::remove_file(&file)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fuel(mut self, fuel: u64) -> Self {
+        self.store.data_mut().fuel = fuel;
+        self
+    }
+
+    /// Calls a function exported by the plugin.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the exported function.
+    /// * `params` - The parameters to pass, e.g. `()` or `(1, 2)`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the plugin does not export a function `name` with a matching
+    /// signature, or if the function traps, including when it runs out of fuel.
+    pub fn call<Params: WasmParams, Results: WasmResults>(
+        &mut self,
+        name: &str,
+        params: Params,
+    ) -> crate::Result<Results> {
+        let func = self
+            .instance
+            .get_typed_func::<Params, Results>(&self.store, name)?;
+        let fuel = self.store.data().fuel;
+        self.store.set_fuel(fuel).map_err(wasmi::Error::from)?;
+        Ok(func.call(&mut self.store, params)?)
+    }
+}
+
+/// Loads a WebAssembly plugin and checks its ABI version.
+///
+/// # Arguments
+///
+/// * `file` - The path to the `.wasm` file.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be read, is not a valid WebAssembly module, imports
+/// anything other than the host API, or reports an ABI version other than [`ABI_VERSION`].
+///
+/// # Examples
+/// ```
+/// use dablenutil::plugins::wasm;
+/// # use std::fs;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// // exports `dablenutil_plugin_abi() -> i32` and `add(i32, i32) -> i32`
+/// let module = [
+///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+///     0x01, 0x0b, 0x02, 0x60, 0x00, 0x01, 0x7f, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, // types
+///     0x03, 0x03, 0x02, 0x00, 0x01, // functions
+///     0x07, 0x1f, 0x02, 0x15, b'd', b'a', b'b', b'l', b'e', b'n', b'u', b't', b'i', b'l', b'_',
+///     b'p', b'l', b'u', b'g', b'i', b'n', b'_', b'a', b'b', b'i', 0x00, 0x00,
+///     0x03, b'a', b'd', b'd', 0x00, 0x01, // exports
+///     0x0a, 0x0e, 0x02, 0x04, 0x00, 0x41, 0x01, 0x0b,
+///     0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b, // code
+/// ];
+/// let file = std::env::temp_dir().join("dablenutil_wasm_load.wasm");
+/// fs::write(&file, module)?;
+/// let mut plugin = wasm::load(&file)?;
+/// assert_eq!(plugin.get_name(), "dablenutil_wasm_load");
+/// let sum: i32 = plugin.call("add", (2, 3))?;
+/// assert_eq!(sum, 5);
+/// # fs::remove_file(&file)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load<P: AsRef<Path>>(file: P) -> crate::Result<WasmPlugin> {
+    let file = file.as_ref();
+    let wasm = fs::read(file)?;
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+    let module = Module::new(&engine, &wasm)?;
+    let name = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut store = Store::new(
+        &engine,
+        HostState {
+            name,
+            fs_root: None,
+            fuel: DEFAULT_FUEL,
+        },
+    );
+    // the start function runs during instantiation, so it needs fuel too
+    store.set_fuel(DEFAULT_FUEL).map_err(wasmi::Error::from)?;
+    let linker = host_linker(&engine)?;
+    let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+    let mut plugin = WasmPlugin { store, instance };
+    let found: u32 = plugin.call(ABI_SYMBOL, ())?;
+    if found != ABI_VERSION {
+        return Err(crate::Error::PluginAbiMismatch {
+            path: file.to_path_buf(),
+            expected: ABI_VERSION,
+            found,
+        });
+    }
+    Ok(plugin)
+}
+
+/// Builds a linker providing the host API.
+fn host_linker(engine: &Engine) -> Result<Linker<HostState>, wasmi::Error> {
+    let mut linker = Linker::new(engine);
+    linker
+        .func_wrap("dablenutil", "log", host_log)?
+        .func_wrap("dablenutil", "read_file", host_read_file)?
+        .func_wrap("dablenutil", "write_file", host_write_file)?;
+    Ok(linker)
+}
+
+/// Gets the memory exported by the calling plugin.
+fn memory(caller: &Caller<'_, HostState>) -> Result<Memory, wasmi::Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("plugin does not export its memory"))
+}
+
+/// Checks that the `len` bytes at `ptr` lie within `memory` and are at most [`MAX_TRANSFER`]
+/// long, and returns their range.
+fn checked_range(
+    caller: &Caller<'_, HostState>,
+    memory: Memory,
+    ptr: u32,
+    len: u32,
+) -> Result<std::ops::Range<usize>, wasmi::Error> {
+    if len > MAX_TRANSFER {
+        return Err(wasmi::Error::new(format!(
+            "buffer of {} bytes exceeds the limit of {} bytes",
+            len, MAX_TRANSFER
+        )));
+    }
+    let start = ptr as usize;
+    let end = start + len as usize;
+    if end > memory.data(caller).len() {
+        return Err(wasmi::Error::new(
+            "buffer is out of bounds of the plugin's memory",
+        ));
+    }
+    Ok(start..end)
+}
+
+/// Reads `len` bytes at `ptr` from the calling plugin's memory.
+fn read_bytes(caller: &Caller<'_, HostState>, ptr: u32, len: u32) -> Result<Vec<u8>, wasmi::Error> {
+    let memory = memory(caller)?;
+    let range = checked_range(caller, memory, ptr, len)?;
+    Ok(memory.data(caller)[range].to_vec())
+}
+
+/// Resolves a plugin-supplied path against the filesystem root, or `None` if there is no root or
+/// the path would escape it. Symbolic links are resolved, so a link inside the root can't lead
+/// out of it.
+fn sandboxed_path(state: &HostState, path: &[u8]) -> Option<PathBuf> {
+    let path = Path::new(std::str::from_utf8(path).ok()?);
+    let is_contained = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !is_contained || path.components().next().is_none() {
+        return None;
+    }
+    let root = fs::canonicalize(state.fs_root.as_ref()?).ok()?;
+    let joined = root.join(path);
+    let resolved = match fs::canonicalize(&joined) {
+        Ok(resolved) => resolved,
+        // a new file; a dangling link in its place would be followed by the write
+        Err(_) if fs::symlink_metadata(&joined).is_err() => fs::canonicalize(joined.parent()?)
+            .ok()?
+            .join(joined.file_name()?),
+        Err(_) => return None,
+    };
+    resolved.starts_with(&root).then_some(resolved)
+}
+
+// wasmi requires host functions to take the caller by value
+#[allow(clippy::needless_pass_by_value)]
+fn host_log(
+    caller: Caller<'_, HostState>,
+    level: i32,
+    ptr: u32,
+    len: u32,
+) -> Result<(), wasmi::Error> {
+    let level = match level {
+        1 => Level::Error,
+        2 => Level::Warn,
+        3 => Level::Info,
+        4 => Level::Debug,
+        _ => Level::Trace,
+    };
+    let message = read_bytes(&caller, ptr, len)?;
+    let target = format!("plugin::{}", caller.data().name);
+    log::log!(target: &target, level, "{}", String::from_utf8_lossy(&message));
+    Ok(())
+}
+
+fn host_read_file(
+    mut caller: Caller<'_, HostState>,
+    path_ptr: u32,
+    path_len: u32,
+    buf_ptr: u32,
+    buf_len: u32,
+) -> Result<i64, wasmi::Error> {
+    let path = read_bytes(&caller, path_ptr, path_len)?;
+    let memory = memory(&caller)?;
+    let range = checked_range(&caller, memory, buf_ptr, buf_len)?;
+    let Some(file) =
+        sandboxed_path(caller.data(), &path).and_then(|path| fs::File::open(path).ok())
+    else {
+        return Ok(-1);
+    };
+    let Ok(metadata) = file.metadata() else {
+        return Ok(-1);
+    };
+    // only the part which fits is read, straight into the plugin's memory
+    let mut buffer = &mut memory.data_mut(&mut caller)[range];
+    if io::copy(&mut file.take(u64::from(buf_len)), &mut buffer).is_err() {
+        return Ok(-1);
+    }
+    Ok(i64::try_from(metadata.len()).unwrap_or(i64::MAX))
+}
+
+// wasmi requires host functions to take the caller by value
+#[allow(clippy::needless_pass_by_value)]
+fn host_write_file(
+    caller: Caller<'_, HostState>,
+    path_ptr: u32,
+    path_len: u32,
+    data_ptr: u32,
+    data_len: u32,
+) -> Result<i32, wasmi::Error> {
+    let path = read_bytes(&caller, path_ptr, path_len)?;
+    let data = read_bytes(&caller, data_ptr, data_len)?;
+    let written =
+        sandboxed_path(caller.data(), &path).is_some_and(|path| fs::write(path, data).is_ok());
+    Ok(if written { 0 } else { -1 })
+}
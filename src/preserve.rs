@@ -0,0 +1,212 @@
+//! Captures and restores file metadata which copying a file loses, so backups restore faithfully.
+//!
+//! [`capture`] records the access and modification times, the permissions, and on Unix, the owner
+//! and group, or on Windows, the file attributes. [`apply`] restores them onto another path.
+//!
+//! # Examples
+//! ```
+//! use dablenutil::preserve;
+//! use std::{fs, time::{Duration, UNIX_EPOCH}};
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! let dir = std::env::temp_dir().join("dablenutil_preserve");
+//! # let _ = fs::remove_dir_all(&dir);
+//! fs::create_dir_all(&dir)?;
+//! let original = dir.join("original.txt");
+//! fs::write(&original, "data")?;
+//! let old = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+//! fs::File::options().write(true).open(&original)?.set_modified(old)?;
+//!
+//! let metadata = preserve::capture(&original)?;
+//! let copy = dir.join("copy.txt");
+//! fs::copy(&original, &copy)?;
+//! preserve::apply(&copy, &metadata)?;
+//! assert_eq!(fs::metadata(&copy)?.modified()?, old);
+//! # fs::remove_dir_all(&dir)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fs::{self, FileTimes},
+    io,
+    path::Path,
+    time::SystemTime,
+};
+
+/// The Windows file attributes which can be set with `SetFileAttributesW`: read-only, hidden,
+/// system, archive, temporary, offline, and not content indexed.
+#[cfg(windows)]
+const SETTABLE_ATTRIBUTES: u32 = 0x1 | 0x2 | 0x4 | 0x20 | 0x100 | 0x1000 | 0x2000;
+
+/// Metadata captured from a file or directory by [`capture`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMetadata {
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+    readonly: bool,
+    #[cfg(unix)]
+    mode: u32,
+    #[cfg(unix)]
+    uid: u32,
+    #[cfg(unix)]
+    gid: u32,
+    #[cfg(windows)]
+    attributes: u32,
+}
+
+impl FileMetadata {
+    /// Gets the last access time, if the platform supports it.
+    pub fn get_accessed(&self) -> Option<SystemTime> {
+        self.accessed
+    }
+
+    /// Gets the last modification time, if the platform supports it.
+    pub fn get_modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Checks if the file is read-only.
+    pub fn get_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Gets the Unix permission bits, including the file type bits. Only available on Unix.
+    #[cfg(unix)]
+    pub fn get_mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// Gets the user ID of the owner. Only available on Unix.
+    #[cfg(unix)]
+    pub fn get_uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Gets the group ID of the owner. Only available on Unix.
+    #[cfg(unix)]
+    pub fn get_gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Gets the file attributes. Only available on Windows.
+    #[cfg(windows)]
+    pub fn get_attributes(&self) -> u32 {
+        self.attributes
+    }
+}
+
+impl From<&fs::Metadata> for FileMetadata {
+    fn from(metadata: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        use std::os::unix::fs::MetadataExt;
+        #[cfg(windows)]
+        use std::os::windows::fs::MetadataExt;
+
+        Self {
+            accessed: metadata.accessed().ok(),
+            modified: metadata.modified().ok(),
+            readonly: metadata.permissions().readonly(),
+            #[cfg(unix)]
+            mode: metadata.mode(),
+            #[cfg(unix)]
+            uid: metadata.uid(),
+            #[cfg(unix)]
+            gid: metadata.gid(),
+            #[cfg(windows)]
+            attributes: metadata.file_attributes(),
+        }
+    }
+}
+
+/// Captures the metadata of a file or directory, following symbolic links.
+///
+/// # Arguments
+///
+/// * `path` - The path to capture the metadata of.
+///
+/// # Errors
+///
+/// An error is returned if the metadata could not be read.
+pub fn capture(path: &Path) -> crate::Result<FileMetadata> {
+    Ok(FileMetadata::from(&fs::metadata(path)?))
+}
+
+/// Applies captured metadata to a file or directory. Ownership is only changed when the process is
+/// privileged enough to; otherwise, it is silently left alone.
+///
+/// # Arguments
+///
+/// * `path` - The path to apply the metadata to.
+/// * `metadata` - The metadata to apply.
+///
+/// # Errors
+///
+/// An error is returned if the times, permissions, or attributes could not be set.
+pub fn apply(path: &Path, metadata: &FileMetadata) -> crate::Result<()> {
+    let mut times = FileTimes::new();
+    if let Some(accessed) = metadata.accessed {
+        times = times.set_accessed(accessed);
+    }
+    if let Some(modified) = metadata.modified {
+        times = times.set_modified(modified);
+    }
+    open_for_attributes(path)?.set_times(times)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        // chown clears the setuid and setgid bits, so it must come before chmod
+        match std::os::unix::fs::chown(path, Some(metadata.uid), Some(metadata.gid)) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+            result => result?,
+        }
+        fs::set_permissions(path, fs::Permissions::from_mode(metadata.mode))?;
+    }
+
+    #[cfg(windows)]
+    set_file_attributes(path, metadata.attributes & SETTABLE_ATTRIBUTES)?;
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let mut permissions = fs::metadata(path)?.permissions();
+        permissions.set_readonly(metadata.readonly);
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+/// Opens a file or directory with just enough access to change its times.
+fn open_for_attributes(path: &Path) -> io::Result<fs::File> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+        // needed to open directories
+        const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+        fs::OpenOptions::new()
+            .access_mode(FILE_WRITE_ATTRIBUTES)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+            .open(path)
+    }
+    #[cfg(not(windows))]
+    {
+        fs::File::open(path)
+    }
+}
+
+/// Sets the attributes of a file with `SetFileAttributesW`.
+#[cfg(windows)]
+pub(crate) fn set_file_attributes(path: &Path, attributes: u32) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::SetFileAttributesW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    // SAFETY: `wide` is a valid, NUL-terminated UTF-16 string which outlives the call
+    if unsafe { SetFileAttributesW(wide.as_ptr(), attributes) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
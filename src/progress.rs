@@ -0,0 +1,126 @@
+//! Progress tracking for long-running transfers, such as downloads, copies, and archiving.
+
+use std::time::{Duration, Instant};
+
+use crate::stats::Ema;
+
+/// The smoothing factor used for the transfer rate.
+const RATE_SMOOTHING: f64 = 0.3;
+
+/// A snapshot of the progress of a transfer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The number of bytes transferred so far.
+    pub done: u64,
+    /// The total number of bytes to transfer.
+    pub total: u64,
+    /// The smoothed transfer rate, in bytes per second.
+    pub rate: f64,
+    /// The estimated time remaining, or `None` if it cannot be estimated yet.
+    pub eta: Option<Duration>,
+    /// The percentage of bytes transferred, from 0 to 100.
+    pub percent: f64,
+}
+
+/// Tracks the progress of a transfer, computing a smoothed transfer rate and ETA.
+///
+/// All utilities reporting progress use this type, so their numbers are consistent.
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    total: u64,
+    last: (Instant, u64),
+    rate: Ema,
+}
+
+impl ProgressTracker {
+    /// Constructs a new `ProgressTracker` for a transfer of `total_bytes` bytes, starting now.
+    ///
+    /// # Arguments
+    ///
+    /// * `total_bytes` - The total number of bytes to transfer.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::progress::ProgressTracker;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let start = Instant::now();
+    /// let mut tracker = ProgressTracker::starting_at(1000, start);
+    /// let progress = tracker.update_at(250, start + Duration::from_secs(1));
+    /// assert_eq!(progress.rate, 250.0);
+    /// assert_eq!(progress.percent, 25.0);
+    /// assert_eq!(progress.eta, Some(Duration::from_secs(3)));
+    /// ```
+    pub fn new(total_bytes: u64) -> Self {
+        Self::starting_at(total_bytes, Instant::now())
+    }
+
+    /// Constructs a new `ProgressTracker` for a transfer of `total_bytes` bytes which started at
+    /// `start`.
+    ///
+    /// # Arguments
+    ///
+    /// * `total_bytes` - The total number of bytes to transfer.
+    /// * `start` - When the transfer started.
+    pub fn starting_at(total_bytes: u64, start: Instant) -> Self {
+        Self {
+            total: total_bytes,
+            last: (start, 0),
+            rate: Ema::new(RATE_SMOOTHING),
+        }
+    }
+
+    /// Gets the total number of bytes to transfer.
+    pub fn get_total(&self) -> u64 {
+        self.total
+    }
+
+    /// Updates the tracker with the number of bytes transferred so far and returns the current
+    /// progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `done` - The total number of bytes transferred so far.
+    pub fn update(&mut self, done: u64) -> Progress {
+        self.update_at(done, Instant::now())
+    }
+
+    /// Updates the tracker as if `done` bytes had been transferred at `now`. This is mostly useful
+    /// for testing.
+    ///
+    /// # Arguments
+    ///
+    /// * `done` - The total number of bytes transferred so far.
+    /// * `now` - The time of the update.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn update_at(&mut self, done: u64, now: Instant) -> Progress {
+        let (last_time, last_done) = self.last;
+        let elapsed = now.saturating_duration_since(last_time).as_secs_f64();
+        if elapsed > 0.0 {
+            let instant_rate = done.saturating_sub(last_done) as f64 / elapsed;
+            self.rate.update(instant_rate);
+            self.last = (now, done);
+        }
+        let rate = self.rate.value().unwrap_or_default();
+        let remaining = self.total.saturating_sub(done);
+        let eta = if remaining == 0 {
+            Some(Duration::ZERO)
+        } else if rate > 0.0 {
+            Duration::try_from_secs_f64(remaining as f64 / rate).ok()
+        } else {
+            None
+        };
+        let percent = if self.total == 0 {
+            100.0
+        } else {
+            (done as f64 / self.total as f64 * 100.0).min(100.0)
+        };
+        Progress {
+            done,
+            total: self.total,
+            rate,
+            eta,
+            percent,
+        }
+    }
+}
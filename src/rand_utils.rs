@@ -0,0 +1,241 @@
+//! Small randomness utilities, such as shuffling and sampling slices.
+//!
+//! These are not cryptographically secure. They are meant for things like jitter and generating test
+//! data, where pulling in a full-blown random number crate is overkill.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A small, fast pseudo-random number generator based on `SplitMix64`.
+///
+/// An `Rng` can either be seeded randomly with [`Rng::new`] or deterministically with
+/// [`Rng::seeded`], which is useful for reproducible tests.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Constructs a new `Rng` seeded from the system clock and the process' random hasher state.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::rand_utils::Rng;
+    ///
+    /// let mut rng = Rng::new();
+    /// let roll = rng.below(6) + 1;
+    /// assert!((1..=6).contains(&roll));
+    /// ```
+    pub fn new() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        if let Ok(elapsed) = SystemTime::now().duration_since(UNIX_EPOCH) {
+            hasher.write_u128(elapsed.as_nanos());
+        }
+        hasher.write_u32(std::process::id());
+        Self::seeded(hasher.finish())
+    }
+
+    /// Constructs a new `Rng` from a fixed seed. Two generators with the same seed produce the same
+    /// sequence of numbers.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed to use.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::rand_utils::Rng;
+    ///
+    /// let mut a = Rng::seeded(42);
+    /// let mut b = Rng::seeded(42);
+    /// assert_eq!(a.next_u64(), b.next_u64());
+    /// ```
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Generates the next random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Generates a random `f64` in the range `[0, 1)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::rand_utils::Rng;
+    ///
+    /// let mut rng = Rng::seeded(7);
+    /// let x = rng.next_f64();
+    /// assert!((0.0..1.0).contains(&x));
+    /// ```
+    pub fn next_f64(&mut self) -> f64 {
+        // fill the mantissa of a float in [1, 2) and shift it down to [0, 1)
+        f64::from_bits(0x3FF0_0000_0000_0000 | (self.next_u64() >> 12)) - 1.0
+    }
+
+    /// Generates a random `usize` in the range `[0, bound)`. Returns 0 if `bound` is 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `bound` - The exclusive upper bound.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn below(&mut self, bound: usize) -> usize {
+        if bound == 0 {
+            return 0;
+        }
+        // Lemire's multiply-shift method with rejection to avoid modulo bias
+        let bound = bound as u64;
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let m = u128::from(self.next_u64()) * u128::from(bound);
+            if (m as u64) >= threshold {
+                // the high 64 bits are always < bound, which fits in a usize
+                return (m >> 64) as usize;
+            }
+        }
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shuffles a slice in place using the Fisher-Yates algorithm.
+///
+/// # Arguments
+///
+/// * `slice` - The slice to shuffle.
+/// * `rng` - The random number generator to use.
+///
+/// # Examples
+/// ```
+/// use dablenutil::rand_utils::{shuffle, Rng};
+///
+/// let mut numbers = [1, 2, 3, 4, 5];
+/// shuffle(&mut numbers, &mut Rng::seeded(1));
+/// numbers.sort_unstable();
+/// assert_eq!(numbers, [1, 2, 3, 4, 5]);
+/// ```
+pub fn shuffle<T>(slice: &mut [T], rng: &mut Rng) {
+    for i in (1..slice.len()).rev() {
+        slice.swap(i, rng.below(i + 1));
+    }
+}
+
+/// Chooses a random element from a slice. Returns `None` if the slice is empty.
+///
+/// # Arguments
+///
+/// * `slice` - The slice to choose from.
+/// * `rng` - The random number generator to use.
+///
+/// # Examples
+/// ```
+/// use dablenutil::rand_utils::{choose, Rng};
+///
+/// let mut rng = Rng::new();
+/// let colors = ["red", "green", "blue"];
+/// assert!(colors.contains(choose(&colors, &mut rng).unwrap()));
+/// assert_eq!(choose::<u8>(&[], &mut rng), None);
+/// ```
+pub fn choose<'a, T>(slice: &'a [T], rng: &mut Rng) -> Option<&'a T> {
+    if slice.is_empty() {
+        None
+    } else {
+        slice.get(rng.below(slice.len()))
+    }
+}
+
+/// Samples `n` distinct elements from a slice, in random order. If `n` is greater than the length
+/// of the slice, every element is returned.
+///
+/// # Arguments
+///
+/// * `slice` - The slice to sample from.
+/// * `n` - The number of elements to sample.
+/// * `rng` - The random number generator to use.
+///
+/// # Examples
+/// ```
+/// use dablenutil::rand_utils::{sample_n, Rng};
+///
+/// let numbers: Vec<u32> = (0..100).collect();
+/// let mut sample = sample_n(&numbers, 10, &mut Rng::seeded(3));
+/// assert_eq!(sample.len(), 10);
+/// sample.sort_unstable();
+/// sample.dedup();
+/// assert_eq!(sample.len(), 10);
+/// ```
+pub fn sample_n<'a, T>(slice: &'a [T], n: usize, rng: &mut Rng) -> Vec<&'a T> {
+    let n = n.min(slice.len());
+    let mut indices: Vec<usize> = (0..slice.len()).collect();
+    // partial Fisher-Yates; only the first n positions need to be shuffled
+    for i in 0..n {
+        let j = i + rng.below(indices.len() - i);
+        indices.swap(i, j);
+    }
+    indices[..n].iter().map(|&i| &slice[i]).collect()
+}
+
+/// Chooses a random element from a slice, where each element is picked with a probability
+/// proportional to its weight. Negative and non-finite weights are treated as zero.
+///
+/// Returns `None` if the slice is empty, the lengths of `slice` and `weights` differ, or all
+/// weights are zero.
+///
+/// # Arguments
+///
+/// * `slice` - The slice to choose from.
+/// * `weights` - The weight of each element.
+/// * `rng` - The random number generator to use.
+///
+/// # Examples
+/// ```
+/// use dablenutil::rand_utils::{weighted_choice, Rng};
+///
+/// let mut rng = Rng::new();
+/// let items = ["common", "never"];
+/// assert_eq!(weighted_choice(&items, &[1.0, 0.0], &mut rng), Some(&"common"));
+/// assert_eq!(weighted_choice(&items, &[0.0, 0.0], &mut rng), None);
+/// ```
+pub fn weighted_choice<'a, T>(slice: &'a [T], weights: &[f64], rng: &mut Rng) -> Option<&'a T> {
+    fn sanitize(weight: f64) -> f64 {
+        if weight.is_finite() && weight > 0.0 {
+            weight
+        } else {
+            0.0
+        }
+    }
+    if slice.is_empty() || slice.len() != weights.len() {
+        return None;
+    }
+    let total: f64 = weights.iter().copied().map(sanitize).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut target = rng.next_f64() * total;
+    let mut last_positive = None;
+    for (item, &weight) in slice.iter().zip(weights) {
+        let weight = sanitize(weight);
+        if weight <= 0.0 {
+            continue;
+        }
+        if target < weight {
+            return Some(item);
+        }
+        target -= weight;
+        last_positive = Some(item);
+    }
+    // floating point error can leave a tiny remainder; fall back to the last valid item
+    last_positive
+}
@@ -0,0 +1,164 @@
+//! Helpers for reading and writing string values in the Windows registry, for installers which
+//! persist settings, `PATH` changes, or file associations. This module is only available on
+//! Windows when the `registry` feature is enabled.
+//!
+//! Keys are given as paths relative to a [`Hive`], like `Software\dablenparty\app`. Writing to
+//! [`Hive::LocalMachine`] needs administrator rights; without them, an `Io` error with the kind
+//! `PermissionDenied` is returned.
+//!
+//! # Examples
+//! ```no_run
+//! use dablenutil::registry::{self, Hive};
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! let key = r"Software\dablenparty\app";
+//! registry::write_string(Hive::CurrentUser, key, "InstallDir", r"C:\Games\app")?;
+//! assert_eq!(
+//!     registry::read_string(Hive::CurrentUser, key, "InstallDir")?.as_deref(),
+//!     Some(r"C:\Games\app")
+//! );
+//! registry::delete_key(Hive::CurrentUser, key)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{ffi::OsStr, io, os::windows::ffi::OsStrExt};
+
+use winreg::{
+    enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_SET_VALUE, REG_EXPAND_SZ},
+    RegKey, RegValue,
+};
+
+/// A registry root key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hive {
+    /// `HKEY_CURRENT_USER`, for per-user settings.
+    CurrentUser,
+    /// `HKEY_LOCAL_MACHINE`, for machine-wide settings.
+    LocalMachine,
+}
+
+impl Hive {
+    fn key(self) -> RegKey {
+        RegKey::predef(match self {
+            Hive::CurrentUser => HKEY_CURRENT_USER,
+            Hive::LocalMachine => HKEY_LOCAL_MACHINE,
+        })
+    }
+}
+
+/// Reads a string value, which may be stored as `REG_SZ` or `REG_EXPAND_SZ`. Environment variables
+/// in `REG_EXPAND_SZ` values are not expanded.
+///
+/// # Arguments
+///
+/// * `hive` - The root key.
+/// * `key` - The path of the key within `hive`.
+/// * `name` - The name of the value, or `""` for the default value of the key.
+///
+/// # Errors
+///
+/// An error is returned if the key could not be opened for any reason other than not existing, or
+/// if the value is not a string.
+pub fn read_string(hive: Hive, key: &str, name: &str) -> crate::Result<Option<String>> {
+    let value = hive
+        .key()
+        .open_subkey(key)
+        .and_then(|key| key.get_value::<String, _>(name));
+    match value {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes a `REG_SZ` string value, creating the key if it doesn't exist.
+///
+/// # Arguments
+///
+/// * `hive` - The root key.
+/// * `key` - The path of the key within `hive`.
+/// * `name` - The name of the value, or `""` for the default value of the key.
+/// * `value` - The value to write.
+///
+/// # Errors
+///
+/// An error is returned if the key could not be created or the value could not be written.
+pub fn write_string(hive: Hive, key: &str, name: &str, value: &str) -> crate::Result<()> {
+    let (key, _) = hive.key().create_subkey(key)?;
+    key.set_value(name, &value)?;
+    Ok(())
+}
+
+/// Writes a `REG_EXPAND_SZ` string value, creating the key if it doesn't exist. Use this for values
+/// which refer to environment variables like `%USERPROFILE%`, such as `PATH`.
+///
+/// # Arguments
+///
+/// * `hive` - The root key.
+/// * `key` - The path of the key within `hive`.
+/// * `name` - The name of the value, or `""` for the default value of the key.
+/// * `value` - The value to write.
+///
+/// # Errors
+///
+/// An error is returned if the key could not be created or the value could not be written.
+pub fn write_expand_string(hive: Hive, key: &str, name: &str, value: &str) -> crate::Result<()> {
+    let (key, _) = hive.key().create_subkey(key)?;
+    let bytes = OsStr::new(value)
+        .encode_wide()
+        .chain([0])
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    key.set_raw_value(
+        name,
+        &RegValue {
+            bytes,
+            vtype: REG_EXPAND_SZ,
+        },
+    )?;
+    Ok(())
+}
+
+/// Deletes a key, along with all of its values and subkeys. Returns `true` if the key was deleted,
+/// or `false` if it didn't exist.
+///
+/// # Arguments
+///
+/// * `hive` - The root key.
+/// * `key` - The path of the key within `hive`.
+///
+/// # Errors
+///
+/// An error is returned if the key exists but could not be deleted.
+pub fn delete_key(hive: Hive, key: &str) -> crate::Result<bool> {
+    match hive.key().delete_subkey_all(key) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Deletes a value. Returns `true` if the value was deleted, or `false` if it or its key didn't
+/// exist.
+///
+/// # Arguments
+///
+/// * `hive` - The root key.
+/// * `key` - The path of the key within `hive`.
+/// * `name` - The name of the value, or `""` for the default value of the key.
+///
+/// # Errors
+///
+/// An error is returned if the value exists but could not be deleted.
+pub fn delete_value(hive: Hive, key: &str, name: &str) -> crate::Result<bool> {
+    let deleted = hive
+        .key()
+        .open_subkey_with_flags(key, KEY_SET_VALUE)
+        .and_then(|key| key.delete_value(name));
+    match deleted {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
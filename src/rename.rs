@@ -0,0 +1,384 @@
+//! Batch renaming with pattern rules. This module is only available when the `rename` feature is
+//! enabled.
+//!
+//! [`plan`] applies a list of [`Rule`]s to the name of every file, producing a [`RenamePlan`] which
+//! can be previewed before [`apply`] performs it. Rules apply to the whole file name, including the
+//! extension, in order.
+//!
+//! # Examples
+//! ```
+//! use dablenutil::rename::{self, Case, Rule};
+//! use std::fs;
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! let dir = std::env::temp_dir().join("dablenutil_rename");
+//! # let _ = fs::remove_dir_all(&dir);
+//! fs::create_dir_all(&dir)?;
+//! let files = ["IMG_1234.JPG", "IMG_1240.JPG"].map(|name| dir.join(name));
+//! for file in &files {
+//!     fs::write(file, "")?;
+//! }
+//!
+//! let rules = [
+//!     Rule::replace(r"^IMG_\d+\.(\w+)$", "holiday-{n}.$1")?,
+//!     Rule::number(1, 2),
+//!     Rule::case(Case::Lower),
+//! ];
+//! let plan = rename::plan(&files, &rules);
+//! assert!(plan.conflicts.is_empty());
+//! assert_eq!(plan.renames[1].to, dir.join("holiday-02.jpg"));
+//! rename::apply(&plan)?;
+//! assert!(dir.join("holiday-01.jpg").exists());
+//! # fs::remove_dir_all(&dir)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::fs_utils::rename_no_replace;
+
+/// A case transformation for [`Rule::Case`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Case {
+    /// `all lowercase`
+    Lower,
+    /// `ALL UPPERCASE`
+    Upper,
+    /// `First Letter Of Every Word Uppercase`, where words are separated by anything that isn't
+    /// alphanumeric.
+    Title,
+}
+
+impl Case {
+    fn apply(self, s: &str) -> String {
+        match self {
+            Case::Lower => s.to_lowercase(),
+            Case::Upper => s.to_uppercase(),
+            Case::Title => {
+                let mut title = String::with_capacity(s.len());
+                let mut word_start = true;
+                for c in s.chars() {
+                    if word_start {
+                        title.extend(c.to_uppercase());
+                    } else {
+                        title.extend(c.to_lowercase());
+                    }
+                    word_start = !c.is_alphanumeric();
+                }
+                title
+            }
+        }
+    }
+}
+
+/// A rule transforming a file name.
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// Replaces every match of a regex. The replacement can refer to capture groups as `$1` or
+    /// `${name}`, like `Regex::replace_all`.
+    Replace {
+        /// The regex to match.
+        pattern: Regex,
+        /// The replacement.
+        replacement: String,
+    },
+    /// Replaces every `{n}` in the name with the position of the file in the list passed to
+    /// [`plan`], counting from `start` and zero-padded to `width` digits.
+    Number {
+        /// The number of the first file.
+        start: usize,
+        /// The minimum number of digits.
+        width: usize,
+    },
+    /// Transforms the case of the name.
+    Case(Case),
+}
+
+impl Rule {
+    /// Constructs a [`Rule::Replace`].
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The regex to match.
+    /// * `replacement` - The replacement, which can refer to capture groups.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `pattern` is not a valid regex.
+    pub fn replace<S: Into<String>>(pattern: &str, replacement: S) -> crate::Result<Self> {
+        Ok(Rule::Replace {
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.into(),
+        })
+    }
+
+    /// Constructs a [`Rule::Number`].
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The number of the first file.
+    /// * `width` - The minimum number of digits.
+    pub fn number(start: usize, width: usize) -> Self {
+        Rule::Number { start, width }
+    }
+
+    /// Constructs a [`Rule::Case`].
+    ///
+    /// # Arguments
+    ///
+    /// * `case` - The case to transform the name to.
+    pub fn case(case: Case) -> Self {
+        Rule::Case(case)
+    }
+
+    fn apply(&self, name: &str, index: usize) -> String {
+        match self {
+            Rule::Replace {
+                pattern,
+                replacement,
+            } => pattern.replace_all(name, replacement.as_str()).into_owned(),
+            Rule::Number { start, width } => {
+                name.replace("{n}", &format!("{:0width$}", start + index, width = *width))
+            }
+            Rule::Case(case) => case.apply(name),
+        }
+    }
+}
+
+/// A single rename in a [`RenamePlan`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rename {
+    /// The current path.
+    pub from: PathBuf,
+    /// The new path.
+    pub to: PathBuf,
+}
+
+/// A rename which can't be performed, because its target would clash with another file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Conflict {
+    /// The clashing target path.
+    pub to: PathBuf,
+    /// The files which would be renamed to `to`. When only one is listed, `to` already exists.
+    pub sources: Vec<PathBuf>,
+}
+
+/// A file whose rules produced a name which isn't a valid file name: an empty name, `.`, `..`, or
+/// a name containing a path separator.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InvalidName {
+    /// The file being renamed.
+    pub from: PathBuf,
+    /// The name the rules produced.
+    pub name: String,
+}
+
+/// The renames produced by [`plan`], for previewing before calling [`apply`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenamePlan {
+    /// The files whose names change, in the order they were given. Unchanged files are left out.
+    pub renames: Vec<Rename>,
+    /// The renames which would clash with another file. [`apply`] refuses plans with conflicts.
+    pub conflicts: Vec<Conflict>,
+    /// The files whose new names are invalid. They are left out of `renames`, and [`apply`]
+    /// refuses plans with invalid names.
+    pub invalid: Vec<InvalidName>,
+}
+
+/// Applies `rules` to the name of each file, in order, and checks the results for collisions.
+/// Nothing is renamed until the plan is passed to [`apply`]. A new name which would leave the
+/// file's directory, or isn't a file name at all, is reported in [`RenamePlan::invalid`].
+///
+/// # Arguments
+///
+/// * `files` - The files to rename. Their position in the list is used by [`Rule::Number`].
+/// * `rules` - The rules to apply.
+///
+/// # Examples
+/// ```
+/// use dablenutil::rename::{self, Rule};
+/// use std::path::PathBuf;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let files = [PathBuf::from("a-1.txt"), PathBuf::from("a-2.txt")];
+/// let plan = rename::plan(&files, &[Rule::replace(r"-\d", "")?]);
+/// assert_eq!(plan.conflicts.len(), 1);
+/// assert_eq!(plan.conflicts[0].to, PathBuf::from("a.txt"));
+///
+/// let plan = rename::plan(&[PathBuf::from("dir/up.txt")], &[Rule::replace("^.*$", "..")?]);
+/// assert!(plan.renames.is_empty());
+/// assert_eq!(plan.invalid[0].name, "..");
+/// # Ok(())
+/// # }
+/// ```
+pub fn plan<P: AsRef<Path>>(files: &[P], rules: &[Rule]) -> RenamePlan {
+    let mut renames = Vec::new();
+    let mut invalid = Vec::new();
+    for (index, file) in files.iter().enumerate() {
+        let from = file.as_ref();
+        let Some(name) = from.file_name() else {
+            continue;
+        };
+        let name = name.to_string_lossy();
+        let new_name = rules
+            .iter()
+            .fold(name.to_string(), |name, rule| rule.apply(&name, index));
+        if new_name == name {
+            continue;
+        }
+        if is_valid_name(&new_name) {
+            renames.push(Rename {
+                from: from.to_path_buf(),
+                to: from.with_file_name(new_name),
+            });
+        } else {
+            invalid.push(InvalidName {
+                from: from.to_path_buf(),
+                name: new_name,
+            });
+        }
+    }
+
+    let mut targets: HashMap<&Path, Vec<PathBuf>> = HashMap::new();
+    for rename in &renames {
+        targets
+            .entry(&rename.to)
+            .or_default()
+            .push(rename.from.clone());
+    }
+    let mut conflicts = Vec::new();
+    for rename in &renames {
+        let Some(sources) = targets.remove(rename.to.as_path()) else {
+            continue;
+        };
+        // a target which exists is fine if it is renamed away too, or is the same file in another
+        // case on a case-insensitive filesystem
+        let taken = rename.to.exists()
+            && !renames.iter().any(|other| other.from == rename.to)
+            && !is_same_file(&rename.from, &rename.to);
+        if sources.len() > 1 || taken {
+            conflicts.push(Conflict {
+                to: rename.to.clone(),
+                sources,
+            });
+        }
+    }
+    RenamePlan {
+        renames,
+        conflicts,
+        invalid,
+    }
+}
+
+/// Checks if `name` names a file in the directory it is joined to, rather than the directory
+/// itself, its parent, or a path elsewhere.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.chars().any(std::path::is_separator)
+}
+
+/// Checks if two paths refer to the same file.
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    matches!((fs::canonicalize(a), fs::canonicalize(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Performs the renames in `plan`. Every file is first moved to a temporary name next to it, so
+/// swaps and chains like `a -> b -> c` work. No rename replaces an existing file, so a file created
+/// at a target after planning fails the rename instead of being lost. If any rename fails, the
+/// completed ones are rolled back.
+///
+/// # Arguments
+///
+/// * `plan` - The plan to perform.
+///
+/// # Errors
+///
+/// An error is returned if the plan has conflicts or invalid names, or if a file could not be renamed, including
+/// with an `AlreadyExists` error when its target has been taken since planning. In the latter
+/// case, the files are restored to their original names as far as possible.
+///
+/// # Examples
+/// ```
+/// use dablenutil::rename::{self, Rule};
+/// use std::{fs, io};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_rename_apply");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// let files = ["a.txt", "b.txt"].map(|name| dir.join(name));
+/// for file in &files {
+///     fs::write(file, "original")?;
+/// }
+/// let plan = rename::plan(&files, &[Rule::replace(r"\.txt$", ".md")?]);
+/// fs::write(dir.join("b.md"), "created since planning")?;
+///
+/// let Err(dablenutil::Error::Io(e)) = rename::apply(&plan) else {
+///     panic!("b.md was replaced");
+/// };
+/// assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+/// assert_eq!(fs::read_to_string(dir.join("b.md"))?, "created since planning");
+/// assert!(files.iter().all(|file| file.exists()));
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn apply(plan: &RenamePlan) -> crate::Result<()> {
+    if let Some(conflict) = plan.conflicts.first() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("renaming would overwrite {}", conflict.to.display()),
+        )
+        .into());
+    }
+    if let Some(invalid) = plan.invalid.first() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{:?} is not a valid new name for {}",
+                invalid.name,
+                invalid.from.display()
+            ),
+        )
+        .into());
+    }
+    let staged: Vec<PathBuf> = plan
+        .renames
+        .iter()
+        .enumerate()
+        .map(|(i, rename)| {
+            rename
+                .from
+                .with_file_name(format!(".dablenutil-rename-{}-{}", std::process::id(), i))
+        })
+        .collect();
+    // each completed step is a (from, to) pair to undo in reverse
+    let mut done: Vec<(&Path, &Path)> = Vec::new();
+    let steps = plan
+        .renames
+        .iter()
+        .zip(&staged)
+        .map(|(rename, temp)| (rename.from.as_path(), temp.as_path()))
+        .chain(
+            plan.renames
+                .iter()
+                .zip(&staged)
+                .map(|(rename, temp)| (temp.as_path(), rename.to.as_path())),
+        );
+    for (from, to) in steps {
+        if let Err(e) = rename_no_replace(from, to) {
+            for (from, to) in done.into_iter().rev() {
+                let _ = rename_no_replace(to, from);
+            }
+            return Err(e.into());
+        }
+        done.push((from, to));
+    }
+    Ok(())
+}
@@ -0,0 +1,523 @@
+//! Runs an executable as a service managed by the operating system: a systemd unit on Linux, a
+//! launchd job on macOS, or a Windows service registered with `sc`.
+//!
+//! [`generate`] only writes the unit file (or, on Windows, creates the service), while [`install`]
+//! also enables and starts it. [`uninstall`] undoes both, and [`status`] asks the service manager
+//! whether the service is running.
+//!
+//! System-wide services need root or administrator rights. On Linux and macOS,
+//! [`ServiceSpec::user`] installs a per-user service instead; Windows has no per-user services, so
+//! it is ignored there. Note that on Windows, the executable has to implement the service control
+//! protocol, or the service manager kills it shortly after it starts.
+//!
+//! # Examples
+//! ```no_run
+//! use dablenutil::service::{self, ServiceSpec, ServiceStatus};
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! let spec = ServiceSpec::new("dablenparty-server")
+//!     .description("Game server manager")
+//!     .args(["--headless"])
+//!     .user(true);
+//! service::install(&spec)?;
+//! assert_eq!(service::status(&spec)?, ServiceStatus::Running);
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(unix)]
+use std::fs;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// The description of a service.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServiceSpec {
+    name: String,
+    description: Option<String>,
+    exec: Option<PathBuf>,
+    args: Vec<String>,
+    working_dir: Option<PathBuf>,
+    user: bool,
+    restart: bool,
+}
+
+impl ServiceSpec {
+    /// Constructs a new `ServiceSpec` with the default values.
+    /// The default values are:
+    /// * `description`: `None`, which uses the name
+    /// * `exec`: `None`, which uses the current executable
+    /// * `args`: `[]`
+    /// * `working_dir`: `None`
+    /// * `user`: `false`
+    /// * `restart`: `true`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the service. On macOS, this is the launchd label, which is usually
+    ///   reverse-DNS, like `com.dablenparty.server`.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            exec: None,
+            args: Vec::new(),
+            working_dir: None,
+            user: false,
+            restart: true,
+        }
+    }
+
+    /// Gets the name of the service.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the description of the service, if one was set.
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Sets the human-readable description of the service.
+    ///
+    /// # Arguments
+    /// * `description` - The description.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Gets the executable the service runs, if one was set.
+    pub fn get_exec(&self) -> Option<&Path> {
+        self.exec.as_deref()
+    }
+
+    /// Sets the executable the service runs, instead of the current executable.
+    ///
+    /// # Arguments
+    /// * `exec` - The path to the executable.
+    pub fn exec<P: Into<PathBuf>>(mut self, exec: P) -> Self {
+        self.exec = Some(exec.into());
+        self
+    }
+
+    /// Gets the arguments the service is started with.
+    pub fn get_args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Sets the arguments the service is started with.
+    ///
+    /// # Arguments
+    /// * `args` - The arguments.
+    pub fn args<I: IntoIterator<Item = S>, S: Into<String>>(mut self, args: I) -> Self {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Gets the working directory of the service, if one was set.
+    pub fn get_working_dir(&self) -> Option<&Path> {
+        self.working_dir.as_deref()
+    }
+
+    /// Sets the working directory of the service.
+    ///
+    /// # Arguments
+    /// * `working_dir` - The working directory.
+    pub fn working_dir<P: Into<PathBuf>>(mut self, working_dir: P) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// Gets whether the service is installed for the current user only.
+    pub fn get_user(&self) -> bool {
+        self.user
+    }
+
+    /// Sets whether the service is installed for the current user only, rather than system-wide.
+    /// Ignored on Windows.
+    ///
+    /// # Arguments
+    /// * `user` - Whether to install a per-user service.
+    pub fn user(mut self, user: bool) -> Self {
+        self.user = user;
+        self
+    }
+
+    /// Gets whether the service is restarted when it fails.
+    pub fn get_restart(&self) -> bool {
+        self.restart
+    }
+
+    /// Sets whether the service is restarted when it fails.
+    ///
+    /// # Arguments
+    /// * `restart` - Whether to restart on failure.
+    pub fn restart(mut self, restart: bool) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    fn exec_path(&self) -> io::Result<PathBuf> {
+        match &self.exec {
+            Some(exec) => Ok(exec.clone()),
+            None => std::env::current_exe(),
+        }
+    }
+}
+
+/// What [`generate`] and [`install`] created.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InstalledPaths {
+    /// The executable the service runs.
+    pub exec: PathBuf,
+    /// The systemd unit or launchd plist, or `None` on Windows, where services live in the
+    /// registry.
+    pub unit: Option<PathBuf>,
+}
+
+/// Whether a service is running, as reported by [`status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServiceStatus {
+    /// The service is running.
+    Running,
+    /// The service is installed but not running.
+    Stopped,
+    /// The service isn't installed.
+    NotInstalled,
+}
+
+/// Writes the systemd unit or launchd plist for a service, or creates the Windows service, without
+/// starting it.
+///
+/// # Arguments
+///
+/// * `spec` - The service to generate.
+///
+/// # Errors
+///
+/// An error is returned if the current executable could not be found, the unit could not be
+/// written, or `sc create` fails.
+pub fn generate(spec: &ServiceSpec) -> crate::Result<InstalledPaths> {
+    let exec = spec.exec_path()?;
+    #[cfg(windows)]
+    {
+        let mut command_line = quote_arg(&exec.to_string_lossy());
+        for arg in &spec.args {
+            command_line.push(' ');
+            command_line.push_str(&quote_arg(arg));
+        }
+        run(Command::new("sc")
+            .args(["create", &spec.name, "binPath=", &command_line])
+            .args(["start=", "auto", "DisplayName="])
+            .arg(spec.description.as_deref().unwrap_or(&spec.name)))?;
+        if spec.restart {
+            run(Command::new("sc").args([
+                "failure",
+                &spec.name,
+                "reset=",
+                "86400",
+                "actions=",
+                "restart/5000",
+            ]))?;
+        }
+        Ok(InstalledPaths { exec, unit: None })
+    }
+    #[cfg(unix)]
+    {
+        let unit = unit_path(spec)?;
+        if let Some(parent) = unit.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&unit, unit_contents(spec, &exec))?;
+        Ok(InstalledPaths {
+            exec,
+            unit: Some(unit),
+        })
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = exec;
+        Err(io::Error::from(io::ErrorKind::Unsupported).into())
+    }
+}
+
+/// Generates a service with [`generate`], then enables it so it starts at boot (or login, for
+/// per-user services) and starts it now.
+///
+/// # Arguments
+///
+/// * `spec` - The service to install.
+///
+/// # Errors
+///
+/// An error is returned if generating the service fails, or if the service manager fails to
+/// enable or start it.
+pub fn install(spec: &ServiceSpec) -> crate::Result<InstalledPaths> {
+    let paths = generate(spec)?;
+    #[cfg(target_os = "macos")]
+    {
+        if let Some(unit) = &paths.unit {
+            run(Command::new("launchctl").arg("load").arg("-w").arg(unit))?;
+        }
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        run(systemctl(spec).arg("daemon-reload"))?;
+        run(systemctl(spec).args(["enable", "--now", &spec.name]))?;
+    }
+    #[cfg(windows)]
+    run(Command::new("sc").args(["start", &spec.name]))?;
+    Ok(paths)
+}
+
+/// Stops a service, disables it, and removes its unit file or Windows service. Does nothing if the
+/// service isn't installed.
+///
+/// # Arguments
+///
+/// * `spec` - The service to uninstall.
+///
+/// # Errors
+///
+/// An error is returned if the service manager fails to remove the service, or the unit file could
+/// not be removed.
+pub fn uninstall(spec: &ServiceSpec) -> crate::Result<()> {
+    if status(spec)? == ServiceStatus::NotInstalled {
+        return Ok(());
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let unit = unit_path(spec)?;
+        run(Command::new("launchctl").arg("unload").arg("-w").arg(&unit))?;
+        crate::remove_file_if_exists(&unit)?;
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        run(systemctl(spec).args(["disable", "--now", &spec.name]))?;
+        crate::remove_file_if_exists(&unit_path(spec)?)?;
+        run(systemctl(spec).arg("daemon-reload"))?;
+    }
+    #[cfg(windows)]
+    {
+        // stopping fails if the service isn't running, which is fine
+        let _ = Command::new("sc").args(["stop", &spec.name]).output();
+        run(Command::new("sc").args(["delete", &spec.name]))?;
+    }
+    Ok(())
+}
+
+/// Asks the service manager whether a service is running.
+///
+/// # Arguments
+///
+/// * `spec` - The service to check.
+///
+/// # Errors
+///
+/// An error is returned if the service manager could not be run.
+pub fn status(spec: &ServiceSpec) -> crate::Result<ServiceStatus> {
+    #[cfg(target_os = "macos")]
+    {
+        if !unit_path(spec)?.try_exists()? {
+            return Ok(ServiceStatus::NotInstalled);
+        }
+        let output = Command::new("launchctl")
+            .args(["list", &spec.name])
+            .output()?;
+        // the job is only listed once loaded, and only has a PID while running
+        let running =
+            output.status.success() && String::from_utf8_lossy(&output.stdout).contains("\"PID\"");
+        Ok(if running {
+            ServiceStatus::Running
+        } else {
+            ServiceStatus::Stopped
+        })
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        if !unit_path(spec)?.try_exists()? {
+            return Ok(ServiceStatus::NotInstalled);
+        }
+        let output = systemctl(spec).args(["is-active", &spec.name]).output()?;
+        Ok(if output.status.success() {
+            ServiceStatus::Running
+        } else {
+            ServiceStatus::Stopped
+        })
+    }
+    #[cfg(windows)]
+    {
+        // the exit code when the service doesn't exist
+        const ERROR_SERVICE_DOES_NOT_EXIST: i32 = 1060;
+
+        let output = Command::new("sc").args(["query", &spec.name]).output()?;
+        if output.status.code() == Some(ERROR_SERVICE_DOES_NOT_EXIST) {
+            return Ok(ServiceStatus::NotInstalled);
+        }
+        check(&output, "sc")?;
+        Ok(
+            if String::from_utf8_lossy(&output.stdout).contains("RUNNING") {
+                ServiceStatus::Running
+            } else {
+                ServiceStatus::Stopped
+            },
+        )
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = spec;
+        Err(io::Error::from(io::ErrorKind::Unsupported).into())
+    }
+}
+
+/// Runs a command, failing if it exits unsuccessfully.
+fn run(command: &mut Command) -> crate::Result<()> {
+    let output = command.output()?;
+    check(&output, &command.get_program().to_string_lossy())?;
+    Ok(())
+}
+
+/// Turns an unsuccessful exit into an error including the stderr of the command.
+fn check(output: &Output, program: &str) -> io::Result<()> {
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} failed with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Builds a `systemctl` command for the user or system instance.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn systemctl(spec: &ServiceSpec) -> Command {
+    let mut command = Command::new("systemctl");
+    if spec.user {
+        command.arg("--user");
+    }
+    command
+}
+
+/// Gets the path of the systemd unit for a service.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn unit_path(spec: &ServiceSpec) -> io::Result<PathBuf> {
+    let dir = if spec.user {
+        crate::xdg_dir("XDG_CONFIG_HOME", ".config")?.join("systemd/user")
+    } else {
+        PathBuf::from("/etc/systemd/system")
+    };
+    Ok(dir.join(format!("{}.service", spec.name)))
+}
+
+/// Builds the systemd unit for a service.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn unit_contents(spec: &ServiceSpec, exec: &Path) -> String {
+    // systemd expands specifiers and variables, so `%` and `$` are doubled, and quoted arguments
+    // escape quotes and backslashes
+    let quote = |arg: &str| {
+        let escaped = arg
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+            .replace('$', "$$");
+        format!("\"{}\"", escaped)
+    };
+    let exec_start = std::iter::once(quote(&exec.to_string_lossy()))
+        .chain(spec.args.iter().map(|arg| quote(arg)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut service = format!("ExecStart={}\n", exec_start);
+    if let Some(working_dir) = &spec.working_dir {
+        service.push_str("WorkingDirectory=");
+        service.push_str(&working_dir.to_string_lossy().replace('%', "%%"));
+        service.push('\n');
+    }
+    if spec.restart {
+        service.push_str("Restart=on-failure\n");
+    }
+    format!(
+        "[Unit]
+Description={}
+After=network.target
+
+[Service]
+{}
+[Install]
+WantedBy={}
+",
+        spec.description.as_deref().unwrap_or(&spec.name),
+        service,
+        if spec.user {
+            "default.target"
+        } else {
+            "multi-user.target"
+        }
+    )
+}
+
+/// Gets the path of the launchd plist for a service.
+#[cfg(target_os = "macos")]
+fn unit_path(spec: &ServiceSpec) -> io::Result<PathBuf> {
+    let dir = if spec.user {
+        std::env::home_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?
+            .join("Library/LaunchAgents")
+    } else {
+        PathBuf::from("/Library/LaunchDaemons")
+    };
+    Ok(dir.join(format!("{}.plist", spec.name)))
+}
+
+/// Builds the launchd plist for a service.
+#[cfg(target_os = "macos")]
+fn unit_contents(spec: &ServiceSpec, exec: &Path) -> String {
+    let mut extra = String::new();
+    if let Some(working_dir) = &spec.working_dir {
+        extra.push_str("  <key>WorkingDirectory</key>\n  <string>");
+        extra.push_str(&crate::xml_escape(&working_dir.to_string_lossy()));
+        extra.push_str("</string>\n");
+    }
+    if spec.restart {
+        extra.push_str(
+            "  <key>KeepAlive</key>\n  <dict>\n    <key>SuccessfulExit</key>\n    <false/>\n  </dict>\n",
+        );
+    }
+    crate::launchd_plist(&spec.name, exec, &spec.args, &extra)
+}
+
+/// Quotes a Windows command line argument if it contains spaces or quotes.
+#[cfg(windows)]
+pub(crate) fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        // backslashes are only special before a quote, where they must be doubled and the quote
+        // escaped
+        let count = if c == '"' {
+            backslashes * 2 + 1
+        } else {
+            backslashes
+        };
+        quoted.extend(std::iter::repeat_n('\\', count));
+        backslashes = 0;
+        quoted.push(c);
+    }
+    // the closing quote counts too
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,81 @@
+//! Makes sure only one instance of an app runs at a time.
+//!
+//! [`acquire`] takes an exclusive [`FileLock`] on a lock file in the per-user runtime directory:
+//! `$XDG_RUNTIME_DIR` on Linux when set, and the temporary directory otherwise, which is already
+//! per-user on Windows and macOS. The operating system releases the lock when the process exits,
+//! even if it crashes, so a stale lock file never blocks the next launch.
+
+use std::{
+    io::{Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::fs_utils::FileLock;
+
+/// Proof that this is the only running instance of an app. The next instance can start once it
+/// is dropped.
+#[derive(Debug)]
+pub struct InstanceGuard {
+    lock: FileLock,
+}
+
+impl InstanceGuard {
+    /// Gets the path of the lock file.
+    pub fn path(&self) -> &Path {
+        self.lock.path()
+    }
+}
+
+/// Claims the single instance of an app for this process. The lock file holds the ID of the
+/// process which owns it, for diagnostics.
+///
+/// # Arguments
+///
+/// * `app_name` - A name unique to the app, which names the lock file. Path separators are
+///   replaced.
+///
+/// # Errors
+///
+/// An [`Error::AlreadyRunning`](crate::Error::AlreadyRunning) is returned if another instance
+/// holds the lock. Other errors are returned if the lock file could not be created or written.
+///
+/// # Examples
+/// ```
+/// use dablenutil::single_instance;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let guard = single_instance::acquire("dablenutil-single-instance")?;
+/// let second = single_instance::acquire("dablenutil-single-instance");
+/// assert_eq!(second.unwrap_err().kind(), "already_running");
+///
+/// drop(guard);
+/// assert!(single_instance::acquire("dablenutil-single-instance").is_ok());
+/// # Ok(())
+/// # }
+/// ```
+pub fn acquire(app_name: &str) -> crate::Result<InstanceGuard> {
+    let dir = runtime_dir();
+    crate::create_dir_if_not_exists(&dir)?;
+    let file_name = format!("{}.lock", app_name.replace(['/', '\\'], "_"));
+    let Some(lock) = FileLock::try_lock(&dir.join(file_name))? else {
+        return Err(crate::Error::AlreadyRunning(app_name.to_string()));
+    };
+    let mut file = lock.file();
+    file.set_len(0)?;
+    file.rewind()?;
+    writeln!(file, "{}", std::process::id())?;
+    Ok(InstanceGuard { lock })
+}
+
+/// Gets the per-user directory for runtime files like locks.
+fn runtime_dir() -> PathBuf {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    // relative XDG directories are invalid and must be ignored
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+    {
+        return dir;
+    }
+    std::env::temp_dir()
+}
@@ -0,0 +1,206 @@
+//! Basic statistics helpers for smoothing and summarizing streams of samples, such as transfer
+//! speeds or progress rates.
+
+use std::collections::VecDeque;
+
+/// An exponential moving average.
+///
+/// Each new sample is weighted by `alpha`, while the previous average is weighted by `1 - alpha`.
+/// Higher values of `alpha` react faster to changes, lower values produce smoother output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ema {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    /// Constructs a new `Ema` with the given smoothing factor. The factor is clamped to `(0, 1]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - The smoothing factor.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::stats::Ema;
+    ///
+    /// let mut ema = Ema::new(0.5);
+    /// assert_eq!(ema.value(), None);
+    /// assert_eq!(ema.update(10.0), 10.0);
+    /// assert_eq!(ema.update(20.0), 15.0);
+    /// assert_eq!(ema.value(), Some(15.0));
+    /// ```
+    pub fn new(alpha: f64) -> Self {
+        let alpha = if alpha.is_nan() {
+            1.0
+        } else {
+            alpha.clamp(f64::EPSILON, 1.0)
+        };
+        Self { alpha, value: None }
+    }
+
+    /// Gets the smoothing factor.
+    pub fn get_alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Gets the current average, or `None` if no samples have been added yet.
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    /// Adds a sample and returns the new average. The first sample becomes the average as-is.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample` - The sample to add.
+    pub fn update(&mut self, sample: f64) -> f64 {
+        let value = match self.value {
+            Some(value) => self.alpha.mul_add(sample - value, value),
+            None => sample,
+        };
+        self.value = Some(value);
+        value
+    }
+
+    /// Clears the current average.
+    pub fn reset(&mut self) {
+        self.value = None;
+    }
+}
+
+/// A fixed-size window over the most recent samples.
+///
+/// Once the window is full, adding a sample evicts the oldest one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollingWindow {
+    capacity: usize,
+    samples: VecDeque<f64>,
+}
+
+impl RollingWindow {
+    /// Constructs a new, empty `RollingWindow` which holds at most `capacity` samples. A capacity of
+    /// 0 is treated as 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of samples to keep.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::stats::RollingWindow;
+    ///
+    /// let mut window = RollingWindow::new(3);
+    /// for sample in [1.0, 2.0, 3.0, 4.0] {
+    ///     window.push(sample);
+    /// }
+    /// assert_eq!(window.len(), 3);
+    /// assert_eq!(window.mean(), Some(3.0));
+    /// assert_eq!(window.min(), Some(2.0));
+    /// assert_eq!(window.max(), Some(4.0));
+    /// ```
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Gets the maximum number of samples in the window.
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Gets the number of samples currently in the window.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns `true` if the window contains no samples.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Adds a sample, evicting the oldest one if the window is full.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample` - The sample to add.
+    pub fn push(&mut self, sample: f64) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Removes all samples from the window.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Gets the mean of the samples, or `None` if the window is empty.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            None
+        } else {
+            Some(self.samples.iter().sum::<f64>() / self.samples.len() as f64)
+        }
+    }
+
+    /// Gets the smallest sample, or `None` if the window is empty.
+    pub fn min(&self) -> Option<f64> {
+        self.samples.iter().copied().reduce(f64::min)
+    }
+
+    /// Gets the largest sample, or `None` if the window is empty.
+    pub fn max(&self) -> Option<f64> {
+        self.samples.iter().copied().reduce(f64::max)
+    }
+
+    /// Gets the given percentile of the samples using the nearest-rank method, or `None` if the
+    /// window is empty. The percentile is clamped to `[0, 100]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `percentile` - The percentile to get, from 0 to 100.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::stats::RollingWindow;
+    ///
+    /// let mut window = RollingWindow::new(100);
+    /// for sample in 1..=100 {
+    ///     window.push(f64::from(sample));
+    /// }
+    /// assert_eq!(window.percentile(50.0), Some(50.0));
+    /// assert_eq!(window.p95(), Some(95.0));
+    /// assert_eq!(window.percentile(100.0), Some(100.0));
+    /// ```
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn percentile(&self, percentile: f64) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.samples.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let percentile = if percentile.is_nan() {
+            0.0
+        } else {
+            percentile.clamp(0.0, 100.0)
+        };
+        // nearest-rank: the smallest value such that `percentile`% of samples are <= it
+        let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1)])
+    }
+
+    /// Gets the 95th percentile of the samples, or `None` if the window is empty.
+    pub fn p95(&self) -> Option<f64> {
+        self.percentile(95.0)
+    }
+}
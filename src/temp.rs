@@ -0,0 +1,320 @@
+//! Temporary files and directories which clean up after themselves.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::rand_utils::Rng;
+
+/// Counts the temporary paths created by this process, so their names never collide.
+static TEMP_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Generates a unique name starting with `prefix`.
+fn unique_name(prefix: &str) -> String {
+    format!(
+        "{}-{}-{}-{:x}",
+        prefix,
+        std::process::id(),
+        TEMP_COUNT.fetch_add(1, Ordering::Relaxed),
+        Rng::new().next_u64()
+    )
+}
+
+/// A uniquely named directory which is deleted, along with everything in it, when the `TempDir` is
+/// dropped. On Unix, only its owner can access it.
+///
+/// # Examples
+/// ```
+/// use dablenutil::temp::TempDir;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = TempDir::new("download")?;
+/// std::fs::write(dir.path().join("server.zip"), "")?;
+/// let path = dir.path().to_path_buf();
+/// drop(dir);
+/// assert!(!path.exists());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TempDir {
+    /// The path of the directory, or empty once kept.
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Creates a new directory in the system temporary directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The start of the directory name, which is followed by a unique suffix.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the directory could not be created.
+    pub fn new(prefix: &str) -> crate::Result<Self> {
+        Self::new_in(&std::env::temp_dir(), prefix)
+    }
+
+    /// Creates a new directory in `parent`, which must exist. Creating it on the same filesystem as
+    /// its final destination lets its contents be moved there with a cheap rename.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The directory to create the directory in.
+    /// * `prefix` - The start of the directory name, which is followed by a unique suffix.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the directory could not be created.
+    pub fn new_in(parent: &Path, prefix: &str) -> crate::Result<Self> {
+        let mut builder = fs::DirBuilder::new();
+        // other users of a shared temporary directory shouldn't see into it
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+
+            builder.mode(0o700);
+        }
+        loop {
+            let path = parent.join(unique_name(prefix));
+            match builder.create(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Gets the path of the directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keeps the directory instead of deleting it on drop, and returns its path.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::temp::TempDir;
+    ///
+    /// # fn main() -> dablenutil::Result<()> {
+    /// let path = TempDir::new("kept")?.keep();
+    /// assert!(path.exists());
+    /// # std::fs::remove_dir_all(&path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn keep(mut self) -> PathBuf {
+        std::mem::take(&mut self.path)
+    }
+
+    /// Deletes the directory now, reporting any error instead of ignoring it like dropping does.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the directory could not be deleted.
+    pub fn close(mut self) -> crate::Result<()> {
+        let path = std::mem::take(&mut self.path);
+        crate::remove_dir_if_exists(&path)
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// A uniquely named file which is deleted when the `TempFile` is dropped, unless it is
+/// [persisted](TempFile::persist). The file is created exclusively, so it never reuses an existing
+/// file, and is open for writing. On Unix, only its owner can read or write it.
+///
+/// # Examples
+/// ```
+/// use dablenutil::temp::TempFile;
+/// use std::io::Write;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_temp_file");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// std::fs::create_dir_all(&dir)?;
+/// let mut file = TempFile::new_in(&dir, "settings")?;
+/// writeln!(file, "volume = 11")?;
+/// # #[cfg(unix)]
+/// # {
+/// use std::os::unix::fs::PermissionsExt;
+///
+/// assert_eq!(file.as_file().metadata()?.permissions().mode() & 0o777, 0o600);
+/// # }
+/// file.persist(&dir.join("settings.toml"))?;
+/// assert_eq!(std::fs::read_to_string(dir.join("settings.toml"))?, "volume = 11\n");
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TempFile {
+    // fields drop in order, so the file is closed before it is deleted, which Windows requires
+    file: fs::File,
+    path: TempPath,
+}
+
+/// Deletes the file at a path when dropped, unless the path is empty.
+#[derive(Debug)]
+pub(crate) struct TempPath(PathBuf);
+
+impl TempPath {
+    /// Renames the file to `dest` and stops deleting it on drop. The file is still deleted if the
+    /// rename fails.
+    pub(crate) fn persist(mut self, dest: &Path) -> io::Result<()> {
+        fs::rename(&self.0, dest)?;
+        self.0 = PathBuf::new();
+        Ok(())
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if !self.0.as_os_str().is_empty() {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+}
+
+impl TempFile {
+    /// Creates a new file in the system temporary directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The start of the file name, which is followed by a unique suffix.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be created.
+    pub fn new(prefix: &str) -> crate::Result<Self> {
+        Self::new_in(&std::env::temp_dir(), prefix)
+    }
+
+    /// Creates a new file in `dir`, which must exist. To [`persist`](TempFile::persist) the file
+    /// atomically, it must be on the same filesystem as its destination, so this is usually the
+    /// destination's directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to create the file in.
+    /// * `prefix` - The start of the file name, which is followed by a unique suffix.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be created.
+    pub fn new_in(dir: &Path, prefix: &str) -> crate::Result<Self> {
+        let mut options = fs::OpenOptions::new();
+        options.read(true).write(true).create_new(true);
+        // other users of a shared temporary directory shouldn't be able to read it
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+
+            options.mode(0o600);
+        }
+        loop {
+            let path = dir.join(unique_name(prefix));
+            match options.open(&path) {
+                Ok(file) => {
+                    return Ok(Self {
+                        file,
+                        path: TempPath(path),
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Gets the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path.0
+    }
+
+    /// Gets the open file.
+    pub fn as_file(&self) -> &fs::File {
+        &self.file
+    }
+
+    /// Gets the open file mutably, for seeking or reading back what was written.
+    pub fn as_file_mut(&mut self) -> &mut fs::File {
+        &mut self.file
+    }
+
+    /// Closes the file and renames it to `dest`, replacing any file there, so readers of `dest`
+    /// see either the old or the new file, never a partial one.
+    ///
+    /// # Arguments
+    ///
+    /// * `dest` - The path to keep the file at.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be renamed, including when `dest` is on another
+    /// filesystem. The temporary file is deleted in that case.
+    pub fn persist(self, dest: &Path) -> crate::Result<()> {
+        let TempFile { file, path } = self;
+        // Windows can't rename open files
+        drop(file);
+        path.persist(dest)?;
+        Ok(())
+    }
+
+    /// Splits the file from the guard deleting it, so it can be written with another API.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn into_parts(self) -> (fs::File, TempPath) {
+        (self.file, self.path)
+    }
+
+    /// Closes the file and keeps it at its temporary path instead of deleting it on drop, and
+    /// returns the path.
+    pub fn keep(self) -> PathBuf {
+        let TempFile { file, mut path } = self;
+        drop(file);
+        std::mem::take(&mut path.0)
+    }
+}
+
+impl io::Write for TempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl io::Read for TempFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl io::Seek for TempFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl AsRef<Path> for TempFile {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
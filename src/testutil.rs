@@ -0,0 +1,357 @@
+//! Helpers for writing concise tests of filesystem and time-dependent code. This module is only
+//! available when the `test-util` feature is enabled, which is meant for `[dev-dependencies]`.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    clock::Clock,
+    rand_utils::{choose, Rng},
+    temp::TempDir,
+};
+
+/// A directory tree in a fresh temporary directory, which is deleted when the `TempTree` is
+/// dropped.
+#[derive(Debug)]
+pub struct TempTree {
+    root: TempDir,
+}
+
+impl TempTree {
+    /// Creates an empty tree.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the temporary directory could not be created.
+    pub fn new() -> crate::Result<Self> {
+        let root = TempDir::new("dablenutil-tree")?;
+        Ok(Self { root })
+    }
+
+    /// Creates a tree from a declarative spec, with one entry per line:
+    ///
+    /// * `dir/` - An empty directory.
+    /// * `file.txt` - An empty file.
+    /// * `file.txt = contents` - A file with the given contents.
+    ///
+    /// Parent directories are created as needed, and blank lines and surrounding whitespace are
+    /// ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - The spec of the tree.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a file or directory could not be created.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::testutil::TempTree;
+    ///
+    /// # fn main() -> dablenutil::Result<()> {
+    /// let tree = TempTree::build(
+    ///     "
+    ///     src/main.rs = fn main() {}
+    ///     assets/
+    ///     README.md
+    ///     ",
+    /// )?;
+    /// assert_eq!(std::fs::read_to_string(tree.join("src/main.rs"))?, "fn main() {}");
+    /// assert_eq!(tree.list()?, ["README.md", "assets/", "src/", "src/main.rs"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build(spec: &str) -> crate::Result<Self> {
+        let tree = Self::new()?;
+        for line in spec.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line.split_once('=') {
+                Some((path, contents)) => tree.write(path.trim(), contents.trim())?,
+                None if line.ends_with('/') => fs::create_dir_all(tree.join(line))?,
+                None => tree.write(line, "")?,
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Creates a tree of `files` files with random names, nesting, and contents.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The number of files to create.
+    /// * `rng` - The random number generator to use. Use a seeded one for reproducible trees.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a file or directory could not be created.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::{rand_utils::Rng, testutil::TempTree};
+    ///
+    /// # fn main() -> dablenutil::Result<()> {
+    /// let tree = TempTree::random(20, &mut Rng::seeded(1))?;
+    /// let files = tree.list()?.into_iter().filter(|p| !p.ends_with('/')).count();
+    /// assert_eq!(files, 20);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn random(files: usize, rng: &mut Rng) -> crate::Result<Self> {
+        let tree = Self::new()?;
+        let mut dirs = vec![PathBuf::new()];
+        let mut created = 0;
+        while created < files {
+            let parent = choose(&dirs, rng).cloned().unwrap_or_default();
+            let path = parent.join(random_file_name(rng));
+            if tree.join(&path).exists() {
+                continue;
+            }
+            // roughly one in four entries is a directory, to get some nesting
+            if rng.below(4) == 0 {
+                fs::create_dir(tree.join(&path))?;
+                dirs.push(path);
+            } else {
+                let contents = random_bytes(rng.below(1024), rng);
+                fs::write(tree.join(&path), contents)?;
+                created += 1;
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Gets the root directory of the tree.
+    pub fn path(&self) -> &Path {
+        self.root.path()
+    }
+
+    /// Joins a path relative to the root of the tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The relative path.
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.root.path().join(path)
+    }
+
+    /// Writes a file relative to the root of the tree, creating its parent directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The relative path of the file.
+    /// * `contents` - The contents of the file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file or its parents could not be created.
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, path: P, contents: C) -> crate::Result<()> {
+        let path = self.join(path);
+        crate::ensure_parent_dir(&path)?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Lists every entry in the tree as a sorted, `/`-separated path relative to the root.
+    /// Directories end with `/`. This makes comparing trees in tests a simple `assert_eq!`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a directory could not be read.
+    pub fn list(&self) -> crate::Result<Vec<String>> {
+        fn visit(dir: &Path, prefix: &str, entries: &mut Vec<String>) -> io::Result<()> {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+                if entry.file_type()?.is_dir() {
+                    let name = format!("{}/", name);
+                    visit(&entry.path(), &name, entries)?;
+                    entries.push(name);
+                } else {
+                    entries.push(name);
+                }
+            }
+            Ok(())
+        }
+        let mut entries = Vec::new();
+        visit(self.root.path(), "", &mut entries)?;
+        entries.sort();
+        Ok(entries)
+    }
+}
+
+/// Generates `len` random bytes.
+///
+/// # Arguments
+///
+/// * `len` - The number of bytes to generate.
+/// * `rng` - The random number generator to use.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{rand_utils::Rng, testutil::random_bytes};
+///
+/// assert_eq!(random_bytes(100, &mut Rng::new()).len(), 100);
+/// ```
+pub fn random_bytes(len: usize, rng: &mut Rng) -> Vec<u8> {
+    let mut bytes: Vec<u8> = (0..len.div_ceil(8))
+        .flat_map(|_| rng.next_u64().to_le_bytes())
+        .collect();
+    bytes.truncate(len);
+    bytes
+}
+
+/// Generates `len` bytes of random printable ASCII text, broken into lines.
+///
+/// # Arguments
+///
+/// * `len` - The number of characters to generate.
+/// * `rng` - The random number generator to use.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{rand_utils::Rng, testutil::random_text};
+///
+/// let text = random_text(200, &mut Rng::new());
+/// assert_eq!(text.len(), 200);
+/// assert!(text.chars().all(|c| c == '\n' || c.is_ascii_graphic() || c == ' '));
+/// ```
+pub fn random_text(len: usize, rng: &mut Rng) -> String {
+    const CHARSET: &[u8] =
+        b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 .,;:!?\n";
+    (0..len)
+        .map(|_| char::from(CHARSET[rng.below(CHARSET.len())]))
+        .collect()
+}
+
+/// Generates a random file name of lowercase letters, digits, `-`, and `_`, which is valid on
+/// every platform.
+///
+/// # Arguments
+///
+/// * `rng` - The random number generator to use.
+pub fn random_file_name(rng: &mut Rng) -> String {
+    const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-_";
+    let len = 1 + rng.below(12);
+    // start with a letter so names never look like options or hidden files
+    let mut name = String::with_capacity(len);
+    name.push(char::from(CHARSET[rng.below(26)]));
+    name.extend((1..len).map(|_| char::from(CHARSET[rng.below(CHARSET.len())])));
+    name
+}
+
+/// A [`Clock`] which only moves when told to. Sleeping advances the clock instantly instead of
+/// blocking, so retries with backoff run at full speed in tests.
+///
+/// Clones share the same time, so a test can keep one and hand the other to the code under test.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{clock::Clock, testutil::MockClock};
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new();
+/// let start = clock.now();
+/// let shared = clock.clone();
+/// shared.advance(Duration::from_secs(60));
+/// shared.sleep(Duration::from_secs(5));
+/// assert_eq!(clock.now() - start, Duration::from_secs(65));
+/// assert_eq!(clock.elapsed(), Duration::from_secs(65));
+/// ```
+///
+/// Pruning by age, without waiting or backdating files:
+///
+/// ```
+/// use dablenutil::{
+///     dry_run::{Mode, Plan},
+///     fs_utils::prune_older_than_with_clock,
+///     testutil::{MockClock, TempTree},
+/// };
+/// use std::time::Duration;
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let tree = TempTree::build("cache/a.bin = a")?;
+/// let clock = MockClock::new();
+/// let day = Duration::from_secs(24 * 60 * 60);
+/// let cache = tree.join("cache");
+/// let mut plan = Plan::new(Mode::Execute);
+/// assert!(prune_older_than_with_clock(&cache, day, "*", &clock, &mut plan)?.is_empty());
+/// clock.advance(2 * day);
+/// let pruned = prune_older_than_with_clock(&cache, day, "*", &clock, &mut plan)?;
+/// assert_eq!(pruned, [cache.join("a.bin")]);
+/// assert!(!cache.join("a.bin").exists());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    start: Instant,
+    start_time: SystemTime,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl MockClock {
+    /// Constructs a new `MockClock` starting at the current time.
+    pub fn new() -> Self {
+        Self::starting_at(SystemTime::now())
+    }
+
+    /// Constructs a new `MockClock` whose wall-clock time starts at `time`.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The starting wall-clock time.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::{clock::Clock, testutil::MockClock};
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let clock = MockClock::starting_at(UNIX_EPOCH);
+    /// clock.advance(Duration::from_secs(86400));
+    /// assert_eq!(clock.system_time(), UNIX_EPOCH + Duration::from_secs(86400));
+    /// ```
+    pub fn starting_at(time: SystemTime) -> Self {
+        Self {
+            start: Instant::now(),
+            start_time: time,
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Moves the clock forward.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - How far to move the clock.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+    }
+
+    /// Gets how far the clock has moved since it was constructed.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.start_time + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
@@ -0,0 +1,437 @@
+//! Timestamp utilities built on `chrono`. Only available when the `time_utils` feature is enabled.
+//!
+//! Currently, this module contains helpers to format timestamps, to parse timestamps in most of
+//! the shapes found in logs and manifests, DST-safe scheduling helpers, and a clock tracking the
+//! uptime of the process.
+
+use std::{
+    fmt,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use chrono::{
+    DateTime, Days, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset, SecondsFormat,
+    TimeZone, Utc,
+};
+
+static RUN_CLOCK: OnceLock<RunClock> = OnceLock::new();
+
+/// Naive date-time formats accepted by [`parse_flexible`], interpreted in the local time zone.
+const NAIVE_DATE_TIME_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y/%m/%d %H:%M",
+    "%Y-%m-%d_%H-%M-%S",
+    "%Y%m%dT%H%M%S",
+];
+
+/// Naive date formats accepted by [`parse_flexible`], interpreted as local midnight.
+const NAIVE_DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d"];
+
+/// Gets the current time in UTC as an ISO-8601 (RFC-3339) string with millisecond precision.
+///
+/// # Examples
+/// ```
+/// use dablenutil::time_utils::{now_iso8601, parse_flexible};
+///
+/// let now = now_iso8601();
+/// assert!(now.ends_with('Z'));
+/// assert!(parse_flexible(&now).is_some());
+/// ```
+pub fn now_iso8601() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parses a timestamp in one of many common shapes, returning `None` if none of them match.
+///
+/// The following shapes are accepted, in order:
+/// * RFC-3339 / ISO-8601 with an offset, e.g. `2024-05-01T12:30:00+02:00` or `2024-05-01T10:30:00Z`
+/// * RFC-2822, e.g. `Wed, 01 May 2024 12:30:00 +0200`
+/// * Date and time without an offset, e.g. `2024-05-01 12:30:00`, `2024-05-01T12:30`,
+///   `2024/05/01 12:30:00`, or `2024-05-01_12-30-00` (the log archive format). These are
+///   interpreted in the local time zone.
+/// * A date alone, e.g. `2024-05-01`, interpreted as local midnight.
+/// * A compact date of eight digits, e.g. `20240501`, interpreted as local midnight. Eight digits
+///   which aren't a valid date are read as a Unix timestamp instead.
+/// * A Unix timestamp in seconds or, if it has more than 11 digits, milliseconds.
+///
+/// # Arguments
+///
+/// * `s` - The string to parse. Surrounding whitespace is ignored.
+///
+/// # Examples
+/// ```
+/// use dablenutil::time_utils::parse_flexible;
+///
+/// let expected = parse_flexible("2024-05-01T10:30:00Z").unwrap();
+/// assert_eq!(parse_flexible("2024-05-01T12:30:00+02:00"), Some(expected));
+/// assert_eq!(parse_flexible("Wed, 01 May 2024 12:30:00 +0200"), Some(expected));
+/// assert_eq!(parse_flexible("1714559400"), Some(expected));
+/// assert_eq!(parse_flexible("1714559400000"), Some(expected));
+/// assert!(parse_flexible("2024-05-01 12:30:00").is_some());
+/// assert!(parse_flexible("2024-05-01").is_some());
+/// assert_eq!(parse_flexible("20240501"), parse_flexible("2024-05-01"));
+/// assert_eq!(parse_flexible("99999999").map(|time| time.timestamp()), Some(99_999_999));
+/// assert_eq!(parse_flexible("yesterday"), None);
+/// ```
+pub fn parse_flexible(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc2822(s) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        // checked before the timestamp, which would read `20240501` as a day in 1970
+        if s.len() == 8 {
+            if let Ok(date) = NaiveDate::parse_from_str(s, "%Y%m%d") {
+                return local_to_utc(&date.and_time(NaiveTime::MIN));
+            }
+        }
+        let value: i64 = s.parse().ok()?;
+        return if s.len() > 11 {
+            DateTime::from_timestamp_millis(value)
+        } else {
+            DateTime::from_timestamp(value, 0)
+        };
+    }
+    let naive = NAIVE_DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| {
+            NAIVE_DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(s, format).ok())
+                .map(|date| date.and_time(NaiveTime::MIN))
+        })?;
+    local_to_utc(&naive)
+}
+
+/// Converts a local date-time to UTC, picking the earlier time when it is ambiguous.
+fn local_to_utc(naive: &NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local
+        .from_local_datetime(naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Formats a point in time in the local time zone using a `chrono` format string.
+///
+/// # Arguments
+///
+/// * `time` - The time to format. Anything convertible to a UTC `DateTime`, such as a
+///   `SystemTime`, is accepted.
+/// * `format` - The `chrono` format string.
+///
+/// # Examples
+/// ```
+/// use dablenutil::time_utils::format_local;
+/// use std::time::SystemTime;
+///
+/// let formatted = format_local(SystemTime::now(), "%Y-%m-%d");
+/// assert_eq!(formatted.len(), 10);
+/// ```
+pub fn format_local<T: Into<DateTime<Utc>>>(time: T, format: &str) -> String {
+    time.into().with_timezone(&Local).format(format).to_string()
+}
+
+/// Formats a point in time in UTC using a `chrono` format string.
+///
+/// # Arguments
+///
+/// * `time` - The time to format. Anything convertible to a UTC `DateTime`, such as a
+///   `SystemTime`, is accepted.
+/// * `format` - The `chrono` format string.
+///
+/// # Examples
+/// ```
+/// use dablenutil::time_utils::format_utc;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let time = UNIX_EPOCH + Duration::from_secs(86_400);
+/// assert_eq!(format_utc(time, "%Y-%m-%d %H:%M:%S"), "1970-01-02 00:00:00");
+/// ```
+pub fn format_utc<T: Into<DateTime<Utc>>>(time: T, format: &str) -> String {
+    time.into().format(format).to_string()
+}
+
+/// Resolves a local date and time in `tz` to a single point in time, handling DST transitions.
+///
+/// If the local time is ambiguous (e.g. it occurs twice when clocks fall back), the earliest
+/// occurrence is used. If it doesn't exist (e.g. it is skipped when clocks spring forward), it is
+/// shifted forward by the length of the gap, so 02:30 becomes 03:30 when clocks jump from 02:00 to
+/// 03:00. Returns `None` if the time is out of range.
+///
+/// # Arguments
+///
+/// * `tz` - The time zone to resolve in.
+/// * `local` - The local date and time.
+///
+/// # Examples
+/// ```
+/// use chrono::{FixedOffset, NaiveDate};
+/// use dablenutil::time_utils::resolve_local;
+///
+/// let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+/// let local = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(12, 0, 0).unwrap();
+/// let resolved = resolve_local(&tz, local).unwrap();
+/// assert_eq!(resolved.to_rfc3339(), "2024-05-01T12:00:00+02:00");
+/// ```
+pub fn resolve_local<Tz: TimeZone>(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Tz>> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => Some(time),
+        LocalResult::Ambiguous(a, b) => Some(a.min(b)),
+        LocalResult::None => {
+            // interpret the local time with the offset in effect before the gap, which lands on
+            // the equivalent instant after it
+            let before = local.checked_sub_days(Days::new(1))?;
+            let offset = tz.from_local_datetime(&before).earliest()?.offset().fix();
+            let utc = local.checked_sub_signed(chrono::Duration::seconds(i64::from(
+                offset.local_minus_utc(),
+            )))?;
+            Some(tz.from_utc_datetime(&utc))
+        }
+    }
+}
+
+/// Gets the next time the local wall clock in `tz` shows `time_of_day`, strictly after now.
+///
+/// DST transitions are handled as described in [`resolve_local`], so a job scheduled for
+/// "03:00 local" runs once per day at 03:00 local time, regardless of the UTC offset.
+///
+/// # Arguments
+///
+/// * `time_of_day` - The local time of day.
+/// * `tz` - The time zone of the wall clock.
+///
+/// # Examples
+/// ```
+/// use chrono::{Local, NaiveTime};
+/// use dablenutil::time_utils::next_occurrence;
+///
+/// let three_am = NaiveTime::from_hms_opt(3, 0, 0).unwrap();
+/// let next = next_occurrence(three_am, &Local).unwrap();
+/// assert!(next > Local::now());
+/// assert_eq!(next.time(), three_am);
+/// ```
+pub fn next_occurrence<Tz: TimeZone>(time_of_day: NaiveTime, tz: &Tz) -> Option<DateTime<Tz>> {
+    next_occurrence_after(time_of_day, &Utc::now().with_timezone(tz))
+}
+
+/// Gets the next time the local wall clock shows `time_of_day`, strictly after `after`. The time
+/// zone of `after` is used for the wall clock.
+///
+/// # Arguments
+///
+/// * `time_of_day` - The local time of day.
+/// * `after` - The point in time to search from.
+///
+/// # Examples
+/// ```
+/// use chrono::{DateTime, NaiveTime};
+/// use dablenutil::time_utils::next_occurrence_after;
+///
+/// let three_am = NaiveTime::from_hms_opt(3, 0, 0).unwrap();
+/// let before = DateTime::parse_from_rfc3339("2024-05-01T01:00:00+02:00").unwrap();
+/// let after = DateTime::parse_from_rfc3339("2024-05-01T03:00:00+02:00").unwrap();
+/// assert_eq!(
+///     next_occurrence_after(three_am, &before).unwrap().to_rfc3339(),
+///     "2024-05-01T03:00:00+02:00"
+/// );
+/// assert_eq!(
+///     next_occurrence_after(three_am, &after).unwrap().to_rfc3339(),
+///     "2024-05-02T03:00:00+02:00"
+/// );
+/// ```
+pub fn next_occurrence_after<Tz: TimeZone>(
+    time_of_day: NaiveTime,
+    after: &DateTime<Tz>,
+) -> Option<DateTime<Tz>> {
+    let tz = after.timezone();
+    let today = after.date_naive();
+    let candidate = resolve_local(&tz, today.and_time(time_of_day))?;
+    if candidate > *after {
+        return Some(candidate);
+    }
+    // two days covers the case where the candidate was shifted across midnight by a gap
+    (1..=2).find_map(|days| {
+        let date = today.checked_add_days(Days::new(days))?;
+        resolve_local(&tz, date.and_time(time_of_day)).filter(|next| next > after)
+    })
+}
+
+/// Adds a number of calendar days to a point in time, keeping the local wall-clock time instead
+/// of adding a fixed 24 hours per day. Across a DST transition, the result is therefore 23 or 25
+/// hours away per day. Returns `None` if the result is out of range.
+///
+/// # Arguments
+///
+/// * `time` - The point in time to add to.
+/// * `days` - The number of days to add. May be negative.
+///
+/// # Examples
+/// ```
+/// use chrono::DateTime;
+/// use dablenutil::time_utils::add_local_days;
+///
+/// let time = DateTime::parse_from_rfc3339("2024-05-01T03:00:00+02:00").unwrap();
+/// assert_eq!(
+///     add_local_days(&time, 30).unwrap().to_rfc3339(),
+///     "2024-05-31T03:00:00+02:00"
+/// );
+/// assert_eq!(
+///     add_local_days(&time, -1).unwrap().to_rfc3339(),
+///     "2024-04-30T03:00:00+02:00"
+/// );
+/// ```
+pub fn add_local_days<Tz: TimeZone>(time: &DateTime<Tz>, days: i64) -> Option<DateTime<Tz>> {
+    let local = time.naive_local();
+    let magnitude = Days::new(days.unsigned_abs());
+    let shifted = if days >= 0 {
+        local.checked_add_days(magnitude)?
+    } else {
+        local.checked_sub_days(magnitude)?
+    };
+    resolve_local(&time.timezone(), shifted)
+}
+
+/// A clock capturing when the process started, used to measure its uptime.
+///
+/// The uptime is measured with a monotonic clock, so it is unaffected by changes to the system
+/// time. To relate monotonic uptimes to wall-clock times, take a [`ClockAnchor`].
+#[derive(Debug, Clone, Copy)]
+pub struct RunClock {
+    started_at: DateTime<Utc>,
+    started: Instant,
+}
+
+impl RunClock {
+    /// Constructs a new `RunClock` starting now.
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Gets the process-wide `RunClock`. The clock starts the first time this is called, so it
+    /// should be called as early as possible in `main`.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::time_utils::RunClock;
+    ///
+    /// let clock = RunClock::global();
+    /// assert_eq!(clock.started_at(), RunClock::global().started_at());
+    /// assert!(clock.uptime() <= RunClock::global().uptime());
+    /// ```
+    pub fn global() -> &'static Self {
+        RUN_CLOCK.get_or_init(Self::new)
+    }
+
+    /// Gets the wall-clock time at which the clock started.
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    /// Gets the time elapsed since the clock started.
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Takes an anchor pairing the current wall-clock time with the current uptime.
+    pub fn anchor(&self) -> ClockAnchor {
+        ClockAnchor {
+            wall: Utc::now(),
+            uptime: self.uptime(),
+        }
+    }
+}
+
+impl Default for RunClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A wall-clock time paired with the uptime of a [`RunClock`] at that moment.
+///
+/// Anchors can be serialized with `to_string` and read back with [`ClockAnchor::parse`]. The
+/// format is the RFC-3339 wall-clock time followed by `@` and the uptime in milliseconds, e.g.
+/// `2024-05-01T10:30:00.000Z@1500`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockAnchor {
+    /// The wall-clock time of the anchor.
+    pub wall: DateTime<Utc>,
+    /// The uptime at the anchor.
+    pub uptime: Duration,
+}
+
+impl ClockAnchor {
+    /// Parses an anchor serialized with `to_string`, returning `None` if it is malformed.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - The string to parse.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::time_utils::{ClockAnchor, RunClock};
+    ///
+    /// let anchor = RunClock::global().anchor();
+    /// let parsed = ClockAnchor::parse(&anchor.to_string()).unwrap();
+    /// assert_eq!(parsed.wall.timestamp_millis(), anchor.wall.timestamp_millis());
+    /// assert_eq!(parsed.uptime.as_millis(), anchor.uptime.as_millis());
+    /// assert_eq!(ClockAnchor::parse("garbage"), None);
+    /// ```
+    pub fn parse(s: &str) -> Option<Self> {
+        let (wall, uptime) = s.trim().rsplit_once('@')?;
+        Some(Self {
+            wall: DateTime::parse_from_rfc3339(wall).ok()?.with_timezone(&Utc),
+            uptime: Duration::from_millis(uptime.parse().ok()?),
+        })
+    }
+
+    /// Converts an uptime of the same [`RunClock`] into a wall-clock time, relative to this
+    /// anchor. Returns `None` if the result is out of range.
+    ///
+    /// # Arguments
+    ///
+    /// * `uptime` - The uptime to convert.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::time_utils::ClockAnchor;
+    /// use std::time::Duration;
+    ///
+    /// let anchor = ClockAnchor::parse("2024-05-01T10:30:00.000Z@60000").unwrap();
+    /// let wall = anchor.wall_time_at(Duration::from_secs(30)).unwrap();
+    /// assert_eq!(wall.to_rfc3339(), "2024-05-01T10:29:30+00:00");
+    /// ```
+    pub fn wall_time_at(&self, uptime: Duration) -> Option<DateTime<Utc>> {
+        if let Some(ahead) = uptime.checked_sub(self.uptime) {
+            self.wall
+                .checked_add_signed(chrono::Duration::from_std(ahead).ok()?)
+        } else {
+            let behind = self.uptime.saturating_sub(uptime);
+            self.wall
+                .checked_sub_signed(chrono::Duration::from_std(behind).ok()?)
+        }
+    }
+}
+
+impl fmt::Display for ClockAnchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}@{}",
+            self.wall.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.uptime.as_millis()
+        )
+    }
+}
@@ -1,6 +1,33 @@
 //! Async `tokio` utilities. Only available when the `tokio` feature is enabled.
 
-use std::path::Path;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+#[cfg(feature = "logging")]
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use tokio::io::AsyncWriteExt;
+use tokio::task::JoinSet;
+#[cfg(feature = "logging")]
+use tokio::{runtime::RuntimeFlavor, sync::mpsc::error::TrySendError};
+
+#[cfg(feature = "logging")]
+use crate::logging::{self, LiveLogWriter, LoggingConfig, RotatedLog};
+use crate::{
+    dry_run::{Action, Mode, Plan},
+    fs_utils::{CopyOptions, CopyProgress, Overwrite, Symlinks},
+    temp::TempFile,
+};
+
+/// How many files [`async_copy_dir_recursive`] copies at once.
+pub const MAX_CONCURRENT_COPIES: usize = 16;
 
 /// Asynchronously creates a directory and all of its parent directories if they don't exist.
 /// If the directory already exists, the error is ignored.
@@ -40,3 +67,915 @@ pub async fn async_create_dir_if_not_exists(dir_path: &Path) -> crate::Result<()
         Ok(())
     }
 }
+
+/// Asynchronously removes a directory and everything in it if it exists.
+/// If the directory doesn't exist, the error is ignored.
+///
+/// # Arguments
+///
+/// * `dir` - The path to the directory to remove.
+///
+/// # Errors
+///
+/// An error is returned if the directory could not be removed for some reason
+/// (see `tokio::fs::remove_dir_all` for more information), ignoring the error when the
+/// directory doesn't exist.
+///
+/// # Examples
+/// ```
+/// use dablenutil::tokio::async_remove_dir_if_exists;
+///
+/// # #[tokio::main]
+/// # async fn main() -> dablenutil::Result<()> {
+/// let path = std::env::temp_dir().join("dablenutil_async_remove_dir_if_exists");
+/// tokio::fs::create_dir_all(path.join("nested")).await?;
+/// async_remove_dir_if_exists(&path).await?;
+/// assert!(!path.exists());
+/// async_remove_dir_if_exists(&path).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn async_remove_dir_if_exists(dir: &Path) -> crate::Result<()> {
+    match tokio::fs::remove_dir_all(dir).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Asynchronously removes a file if it exists.
+/// If the file doesn't exist, the error is ignored.
+///
+/// # Arguments
+///
+/// * `file` - The path to the file to remove.
+///
+/// # Errors
+///
+/// An error is returned if the file could not be removed for some reason
+/// (see `tokio::fs::remove_file` for more information), ignoring the error when the
+/// file doesn't exist.
+///
+/// # Examples
+/// ```
+/// use dablenutil::tokio::async_remove_file_if_exists;
+///
+/// # #[tokio::main]
+/// # async fn main() -> dablenutil::Result<()> {
+/// let path = std::env::temp_dir().join("dablenutil_async_remove_file_if_exists.txt");
+/// tokio::fs::write(&path, "").await?;
+/// async_remove_file_if_exists(&path).await?;
+/// assert!(!path.exists());
+/// async_remove_file_if_exists(&path).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn async_remove_file_if_exists(file: &Path) -> crate::Result<()> {
+    match tokio::fs::remove_file(file).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Asynchronously makes a file executable, like [`set_executable`](crate::fs_utils::set_executable).
+///
+/// # Arguments
+///
+/// * `path` - The file to make executable.
+///
+/// # Errors
+///
+/// An error is returned if the file doesn't exist or its permissions could not be changed.
+///
+/// # Examples
+/// ```
+/// use dablenutil::tokio::async_set_executable;
+///
+/// # #[tokio::main]
+/// # async fn main() -> dablenutil::Result<()> {
+/// let file = std::env::temp_dir().join("dablenutil_async_set_executable.sh");
+/// tokio::fs::write(&file, "#!/bin/sh\n").await?;
+/// async_set_executable(&file).await?;
+/// # tokio::fs::remove_file(&file).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn async_set_executable(path: &Path) -> crate::Result<()> {
+    let metadata = tokio::fs::metadata(path).await?;
+    if let Some(permissions) = crate::fs_utils::executable_permissions(&metadata.permissions()) {
+        tokio::fs::set_permissions(path, permissions).await?;
+    }
+    Ok(())
+}
+
+/// Copies a directory and everything in it to `dst` like
+/// [`copy_dir_recursive`](crate::fs_utils::copy_dir_recursive), using `tokio::fs`. Directories are
+/// walked one at a time, while up to [`MAX_CONCURRENT_COPIES`] files are copied concurrently,
+/// which is much faster for trees of many small files. Copies are recorded in the returned
+/// [`Plan`] as they finish, so their order may differ from the sync version.
+///
+/// # Arguments
+///
+/// * `src` - The directory to copy.
+/// * `dst` - The destination directory.
+/// * `options` - The options for the copy.
+///
+/// # Errors
+///
+/// An error is returned if `src` is not a directory, or if an entry could not be read or copied.
+/// Copies still running when an error occurs are cancelled, and entries copied before it are left
+/// in place. An `InvalidInput` error is returned if `dst` is `src` or inside it.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{fs_utils::CopyOptions, tokio::async_copy_dir_recursive};
+/// use std::sync::{
+///     atomic::{AtomicU64, Ordering},
+///     Arc,
+/// };
+///
+/// # #[tokio::main]
+/// # async fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_async_copy_dir_recursive");
+/// # let _ = tokio::fs::remove_dir_all(&dir).await;
+/// tokio::fs::create_dir_all(dir.join("modpack/mods")).await?;
+/// for i in 0..40 {
+///     tokio::fs::write(dir.join(format!("modpack/mods/{}.jar", i)), "jar").await?;
+/// }
+///
+/// let copied = Arc::new(AtomicU64::new(0));
+/// let options = CopyOptions::new()
+///     .exclude(|path| path.ends_with("mods/1.jar"))
+///     .on_progress({
+///         let copied = Arc::clone(&copied);
+///         move |progress| copied.store(progress.done, Ordering::Relaxed)
+///     });
+/// let plan = async_copy_dir_recursive(&dir.join("modpack"), &dir.join("instance"), &options).await?;
+/// assert_eq!(plan.actions().len(), 2 + 39);
+/// assert_eq!(copied.load(Ordering::Relaxed), 39 * 3);
+/// assert_eq!(tokio::fs::read_to_string(dir.join("instance/mods/0.jar")).await?, "jar");
+/// assert!(!dir.join("instance/mods/1.jar").exists());
+/// # tokio::fs::remove_dir_all(&dir).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn async_copy_dir_recursive(
+    src: &Path,
+    dst: &Path,
+    options: &CopyOptions,
+) -> crate::Result<Plan> {
+    if !tokio::fs::metadata(src)
+        .await
+        .is_ok_and(|metadata| metadata.is_dir())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            format!("{} is not a directory", src.display()),
+        )
+        .into());
+    }
+    let (blocking_src, blocking_dst) = (src.to_path_buf(), dst.to_path_buf());
+    tokio::task::spawn_blocking(move || {
+        crate::fs_utils::ensure_outside(&blocking_src, &blocking_dst)
+    })
+    .await
+    .map_err(io::Error::other)??;
+    let mut progress = match CopyProgress::callback(options) {
+        Some(on_progress) => {
+            let mut planned = Plan::new(Mode::DryRun);
+            copy_tree(src, dst, options, &mut planned, None).await?;
+            let symlinks = options.get_symlinks();
+            let total = tokio::task::spawn_blocking(move || {
+                crate::fs_utils::planned_copy_size(&planned, symlinks)
+            })
+            .await
+            .map_err(io::Error::other)??;
+            Some(CopyProgress::new(on_progress, total))
+        }
+        None => None,
+    };
+    let mut plan = Plan::new(options.get_mode());
+    copy_tree(src, dst, options, &mut plan, progress.as_mut()).await?;
+    Ok(plan)
+}
+
+/// Copies the contents of `src` into `dst` for [`async_copy_dir_recursive`], recording what was
+/// copied in `plan` and reporting it to `progress`.
+async fn copy_tree(
+    src: &Path,
+    dst: &Path,
+    options: &CopyOptions,
+    plan: &mut Plan,
+    mut progress: Option<&mut CopyProgress<'_>>,
+) -> crate::Result<()> {
+    let mut copies = JoinSet::new();
+    // directories to visit, with the canonical paths of the directories above them for spotting
+    // link cycles; an explicit stack because async functions can't recurse without boxing
+    let mut pending = vec![(
+        src.to_path_buf(),
+        dst.to_path_buf(),
+        PathBuf::new(),
+        Vec::new(),
+    )];
+    let mut copied_dirs = Vec::new();
+    while let Some((src, dst, relative, mut ancestors)) = pending.pop() {
+        let canonical = tokio::fs::canonicalize(&src).await?;
+        if ancestors.contains(&canonical) {
+            continue;
+        }
+        ancestors.push(canonical);
+        if options.get_preserve_metadata() && !relative.as_os_str().is_empty() {
+            copied_dirs.push((src.clone(), dst.clone()));
+        }
+        if !tokio::fs::metadata(&dst)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir())
+        {
+            if !plan.mode().is_dry_run() {
+                tokio::fs::create_dir_all(&dst).await?;
+            }
+            plan.record(Action::CreateDir(dst.clone()));
+        }
+        let mut entries = tokio::fs::read_dir(&src).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let relative = relative.join(entry.file_name());
+            if options.is_excluded(&relative) {
+                continue;
+            }
+            let from = entry.path();
+            let to = dst.join(entry.file_name());
+            let mut file_type = entry.file_type().await?;
+            if file_type.is_symlink() {
+                match options.get_symlinks() {
+                    Symlinks::Skip => continue,
+                    Symlinks::Preserve => {
+                        async_copy_symlink(&from, &to, options, plan).await?;
+                        continue;
+                    }
+                    Symlinks::Follow => match tokio::fs::metadata(&from).await {
+                        Ok(metadata) => file_type = metadata.file_type(),
+                        // dangling links have nothing to copy
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                        Err(e) => return Err(e.into()),
+                    },
+                }
+            }
+            if file_type.is_dir() {
+                pending.push((from, to, relative, ancestors.clone()));
+            } else if should_copy(&from, &to, options.get_overwrite()).await? {
+                if plan.mode().is_dry_run() {
+                    plan.record(Action::Copy { from, to });
+                    continue;
+                }
+                if copies.len() >= MAX_CONCURRENT_COPIES {
+                    if let Some(copied) = copies.join_next().await {
+                        let (action, len) = copied.map_err(io::Error::other)??;
+                        plan.record(action);
+                        if let Some(progress) = progress.as_mut() {
+                            progress.advance(len);
+                        }
+                    }
+                }
+                copies.spawn(async_copy_file(from, to, options.get_preserve_metadata()));
+            }
+        }
+    }
+    while let Some(copied) = copies.join_next().await {
+        let (action, len) = copied.map_err(io::Error::other)??;
+        plan.record(action);
+        if let Some(progress) = progress.as_mut() {
+            progress.advance(len);
+        }
+    }
+    if !plan.mode().is_dry_run() {
+        // deepest first, since copying into a directory changes its modification time
+        for (from, to) in copied_dirs.into_iter().rev() {
+            tokio::task::spawn_blocking(move || {
+                crate::preserve::apply(&to, &crate::preserve::capture(&from)?)
+            })
+            .await
+            .map_err(io::Error::other)??;
+        }
+    }
+    Ok(())
+}
+
+/// Checks if a file should be copied over `to` according to `overwrite`.
+async fn should_copy(from: &Path, to: &Path, overwrite: Overwrite) -> crate::Result<bool> {
+    let Ok(existing) = tokio::fs::symlink_metadata(to).await else {
+        return Ok(true);
+    };
+    match overwrite {
+        Overwrite::Always => Ok(true),
+        Overwrite::Never => Ok(false),
+        Overwrite::IfNewer => {
+            let source = tokio::fs::metadata(from).await?.modified()?;
+            Ok(existing.modified()? < source)
+        }
+        Overwrite::Error => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        )
+        .into()),
+    }
+}
+
+/// Copies a single file for [`async_copy_dir_recursive`], returning the action it performed and
+/// the number of bytes copied.
+async fn async_copy_file(
+    from: PathBuf,
+    to: PathBuf,
+    preserve_metadata: bool,
+) -> crate::Result<(Action, u64)> {
+    let len = tokio::fs::copy(&from, &to).await?;
+    if preserve_metadata {
+        let (from, to) = (from.clone(), to.clone());
+        tokio::task::spawn_blocking(move || {
+            crate::preserve::apply(&to, &crate::preserve::capture(&from)?)
+        })
+        .await
+        .map_err(io::Error::other)??;
+    }
+    Ok((Action::Copy { from, to }, len))
+}
+
+/// Recreates the symbolic link `from` at `to` for [`async_copy_dir_recursive`].
+async fn async_copy_symlink(
+    from: &Path,
+    to: &Path,
+    options: &CopyOptions,
+    plan: &mut Plan,
+) -> crate::Result<()> {
+    let target = tokio::fs::read_link(from).await?;
+    let dry_run = plan.mode().is_dry_run();
+    if tokio::fs::symlink_metadata(to).await.is_ok() {
+        match options.get_overwrite() {
+            Overwrite::Never | Overwrite::IfNewer => return Ok(()),
+            Overwrite::Error => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", to.display()),
+                )
+                .into())
+            }
+            Overwrite::Always => {
+                if !dry_run {
+                    tokio::fs::remove_file(to).await?;
+                }
+                plan.record(Action::Remove(to.to_path_buf()));
+            }
+        }
+    }
+    if !dry_run {
+        let link = to.to_path_buf();
+        tokio::task::spawn_blocking(move || crate::fs_utils::symlink(&target, &link))
+            .await
+            .map_err(io::Error::other)??;
+    }
+    plan.record(Action::Copy {
+        from: from.to_path_buf(),
+        to: to.to_path_buf(),
+    });
+    Ok(())
+}
+
+/// Writes `contents` to `path` atomically like [`atomic_write`](crate::fs_utils::atomic_write),
+/// using `tokio::fs`: they are written to a temporary file next to `path`, which is then renamed
+/// over it, so readers see either the old or the new contents, never a partial write. If `path`
+/// exists, its permissions are copied to the new file; otherwise, on Unix, only the owner can
+/// read or write it.
+///
+/// With `durable` set, the temporary file is flushed to disk before the rename, and the parent
+/// directory before and after it, so the new contents survive a power loss once this returns.
+/// Windows has no directory flush; there, the rename is journaled by NTFS instead.
+///
+/// # Arguments
+///
+/// * `path` - The path to write to.
+/// * `contents` - The contents to write.
+/// * `durable` - Whether to flush everything to disk before returning.
+///
+/// # Errors
+///
+/// An error is returned if the temporary file could not be written or renamed, in which case it
+/// is removed and `path` is left untouched, or if flushing fails. The temporary file is also
+/// removed if the future is dropped before it completes.
+///
+/// # Examples
+/// ```
+/// use dablenutil::tokio::async_atomic_write;
+///
+/// # #[tokio::main]
+/// # async fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_async_atomic_write");
+/// # let _ = tokio::fs::remove_dir_all(&dir).await;
+/// tokio::fs::create_dir_all(&dir).await?;
+/// let state = dir.join("state.json");
+/// async_atomic_write(&state, r#"{"version":1}"#, false).await?;
+/// async_atomic_write(&state, r#"{"version":2}"#, true).await?;
+/// assert_eq!(tokio::fs::read_to_string(&state).await?, r#"{"version":2}"#);
+/// assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+/// # tokio::fs::remove_dir_all(&dir).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn async_atomic_write<C: AsRef<[u8]>>(
+    path: &Path,
+    contents: C,
+    durable: bool,
+) -> crate::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", path.display()),
+        )
+    })?;
+    let blocking_parent = parent.to_path_buf();
+    let prefix = format!(".{}", file_name.to_string_lossy());
+    // errors, and dropping the future, drop the guard, which deletes the temporary file
+    let (file, temp) = tokio::task::spawn_blocking(move || {
+        TempFile::new_in(&blocking_parent, &prefix).map(TempFile::into_parts)
+    })
+    .await
+    .map_err(io::Error::other)??;
+    let mut file = tokio::fs::File::from_std(file);
+    file.write_all(contents.as_ref()).await?;
+    // tokio writes in the background, so flushing is what surfaces write errors
+    file.flush().await?;
+    match tokio::fs::metadata(path).await {
+        Ok(metadata) => file.set_permissions(metadata.permissions()).await?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    if durable {
+        file.sync_all().await?;
+        async_sync_dir(parent).await?;
+    }
+    // Windows can't rename open files
+    drop(file);
+    let dest = path.to_path_buf();
+    tokio::task::spawn_blocking(move || temp.persist(&dest))
+        .await
+        .map_err(io::Error::other)??;
+    if durable {
+        async_sync_dir(parent).await?;
+    }
+    Ok(())
+}
+
+/// Flushes the entries of a directory to disk. Windows can't open directories for this, so there
+/// it does nothing.
+async fn async_sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    {
+        let _ = dir;
+        Ok(())
+    }
+    #[cfg(not(windows))]
+    {
+        tokio::fs::File::open(dir).await?.sync_all().await
+    }
+}
+
+/// Initialize the logger like [`init_simple_logger`](crate::logging::init_simple_logger), without
+/// blocking the runtime. If [`LoggingConfig::rotate_on_init`] is enabled, logs are rotated with
+/// [`async_rotate_logs`] first. Then a new log file is created.
+///
+/// Opening and locking the log file runs on tokio's blocking thread pool. Complete log lines are
+/// sent over a channel holding up to [`LOG_CHANNEL_CAPACITY`] chunks to a writer task spawned on
+/// the runtime, which hands each batch of lines to the blocking thread pool, so logging never does
+/// file IO on the runtime threads. When the channel is full, new lines are dropped rather than
+/// blocking the logging task, and a line noting how many were dropped is written once there is
+/// room again.
+///
+/// Flushing the logger, with `log::logger().flush()`, waits until everything sent so far is
+/// written, so call it before shutting down the runtime. Inside a multi-threaded runtime, the wait
+/// uses [`tokio::task::block_in_place`]. A current-thread runtime can't run the writer task while
+/// its only thread waits, so there, flushing only asks the writer task to flush and returns.
+///
+/// The live log file is shared with [`reopen_log_file`](crate::logging::reopen_log_file), so
+/// rotating on `SIGHUP` works the same as with the sync logger.
+///
+/// This function is only available when both the `tokio` and `logging` features are enabled.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` to use.
+///
+/// # Errors
+///
+/// An error is returned if the logs could not be rotated, the log file could not be created, or a
+/// global logger is already set.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{logging::LoggingConfig, tokio::init_async_logger};
+///
+/// # #[tokio::main]
+/// # async fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_init_async_logger");
+/// # let _ = tokio::fs::remove_dir_all(&log_folder).await;
+/// # tokio::fs::create_dir_all(&log_folder).await?;
+/// tokio::fs::write(log_folder.join("latest.log"), "previous run").await?;
+/// let config = LoggingConfig::new(log_folder.clone()).rotate_on_init(true);
+/// init_async_logger(&config).await?;
+/// log::info!("Hello, world!");
+/// log::logger().flush();
+/// let contents = tokio::fs::read_to_string(log_folder.join("latest.log")).await?;
+/// assert!(contents.contains("Hello, world!"));
+/// assert!(!contents.contains("previous run"));
+/// # tokio::fs::remove_dir_all(&log_folder).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "logging")]
+pub async fn init_async_logger(config: &LoggingConfig) -> crate::Result<()> {
+    if config.get_rotate_on_init() {
+        async_rotate_logs(config).await?;
+    }
+    async_create_dir_if_not_exists(config.get_log_folder()).await?;
+    let blocking_config = config.clone();
+    // file locks have no async equivalent, so opening the log file runs off the runtime
+    let log_file = tokio::task::spawn_blocking(move || logging::open_live_log(&blocking_config))
+        .await
+        .map_err(io::Error::other)??;
+    let live_log = LiveLogWriter::new(log_file);
+    let (sender, receiver) = tokio::sync::mpsc::channel(LOG_CHANNEL_CAPACITY);
+    let dropped = Arc::new(AtomicU64::new(0));
+    tokio::spawn(write_log_chunks(
+        receiver,
+        live_log.clone(),
+        Arc::clone(&dropped),
+    ));
+    let mut writer = ChannelWriter {
+        sender,
+        pending: Vec::new(),
+        dropped,
+    };
+    if config.get_banner() {
+        logging::write_banner(config, &mut writer)?;
+    }
+    logging::install_logger(config, writer)?;
+    logging::register_live_log(&live_log);
+    Ok(())
+}
+
+/// How many chunks of log lines [`init_async_logger`] queues for its writer task before dropping
+/// new ones.
+#[cfg(feature = "logging")]
+pub const LOG_CHANNEL_CAPACITY: usize = 4096;
+
+/// A message to the writer task spawned by [`init_async_logger`].
+#[cfg(feature = "logging")]
+enum LogMessage {
+    /// Complete lines to write.
+    Lines(Vec<u8>),
+    /// A request to flush, answered once everything before it is written.
+    Flush(tokio::sync::oneshot::Sender<()>),
+}
+
+/// A writer which sends complete lines written to it to the task spawned by
+/// [`init_async_logger`].
+#[cfg(feature = "logging")]
+struct ChannelWriter {
+    sender: tokio::sync::mpsc::Sender<LogMessage>,
+    // the end of a line whose newline hasn't been written yet
+    pending: Vec<u8>,
+    dropped: Arc<AtomicU64>,
+}
+
+#[cfg(feature = "logging")]
+impl ChannelWriter {
+    /// Sends `lines` without blocking, counting them as dropped if the channel is full.
+    #[allow(clippy::naive_bytecount)]
+    fn send_lines(&self, lines: Vec<u8>) -> io::Result<()> {
+        match self.sender.try_send(LogMessage::Lines(lines)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(LogMessage::Lines(lines))) => {
+                let count = lines.iter().filter(|&&byte| byte == b'\n').count().max(1);
+                self.dropped.fetch_add(count as u64, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(_) => Err(writer_stopped()),
+        }
+    }
+}
+
+#[cfg(feature = "logging")]
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        // whole lines are sent, so a full channel drops lines rather than parts of them
+        if let Some(end) = self.pending.iter().rposition(|&byte| byte == b'\n') {
+            let rest = self.pending.split_off(end + 1);
+            let lines = std::mem::replace(&mut self.pending, rest);
+            self.send_lines(lines)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let lines = std::mem::take(&mut self.pending);
+            self.send_lines(lines)?;
+        }
+        let (done, written) = tokio::sync::oneshot::channel();
+        let request = LogMessage::Flush(done);
+        match tokio::runtime::Handle::try_current() {
+            // outside the runtime, blocking is fine
+            Err(_) => {
+                self.sender
+                    .blocking_send(request)
+                    .map_err(|_| writer_stopped())?;
+                written.blocking_recv().map_err(|_| writer_stopped())
+            }
+            // the only thread of the runtime can't wait for the writer task it would have to run
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::CurrentThread => {
+                match self.sender.try_send(request) {
+                    Err(TrySendError::Closed(_)) => Err(writer_stopped()),
+                    _ => Ok(()),
+                }
+            }
+            Ok(handle) => tokio::task::block_in_place(|| {
+                handle.block_on(async {
+                    self.sender
+                        .send(request)
+                        .await
+                        .map_err(|_| writer_stopped())?;
+                    written.await.map_err(|_| writer_stopped())
+                })
+            }),
+        }
+    }
+}
+
+/// The error returned once the writer task of [`init_async_logger`] has stopped.
+#[cfg(feature = "logging")]
+fn writer_stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "log writer task stopped")
+}
+
+/// Writes lines received from a [`ChannelWriter`] to the live log until the channel closes or the
+/// runtime shuts down. Whatever is queued when a batch starts is written in one go on the
+/// blocking thread pool.
+#[cfg(feature = "logging")]
+async fn write_log_chunks(
+    mut receiver: tokio::sync::mpsc::Receiver<LogMessage>,
+    writer: LiveLogWriter,
+    dropped: Arc<AtomicU64>,
+) {
+    while let Some(message) = receiver.recv().await {
+        let mut lines = Vec::new();
+        let mut flushes = Vec::new();
+        let mut next = Some(message);
+        while let Some(message) = next {
+            match message {
+                LogMessage::Lines(chunk) => lines.extend_from_slice(&chunk),
+                LogMessage::Flush(done) => flushes.push(done),
+            }
+            next = receiver.try_recv().ok();
+        }
+        let dropped = dropped.swap(0, Ordering::Relaxed);
+        let mut writer = writer.clone();
+        let flush = !flushes.is_empty();
+        let written = tokio::task::spawn_blocking(move || {
+            if dropped > 0 {
+                let _ = writeln!(
+                    writer,
+                    "[dropped {} log lines: the log writer fell behind]",
+                    dropped
+                );
+            }
+            // a failed write is retried with the next lines, after the file is reopened
+            let _ = writer.write_all(&lines);
+            if flush {
+                let _ = writer.flush();
+            }
+        })
+        .await;
+        if written.is_err() {
+            // the runtime is shutting down
+            return;
+        }
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+/// Rotates the logs like [`rotate_logs`](crate::logging::rotate_logs), using `tokio::fs` so it can
+/// run on the runtime, and producing the same archives as the sync version. The blocking steps,
+/// waiting for the lock on the log folder and streaming each log through the gzip encoder, run on
+/// tokio's blocking thread pool.
+///
+/// This function is only available when both the `tokio` and `logging` features are enabled.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` to use.
+///
+/// # Errors
+///
+/// An error is returned if the logs could not be rotated for some reason.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{logging::LoggingConfig, tokio::async_rotate_logs};
+///
+/// # #[tokio::main]
+/// # async fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_async_rotate_logs");
+/// # let _ = tokio::fs::remove_dir_all(&log_folder).await;
+/// let config = LoggingConfig::new(log_folder.clone()).package_name(Some("app"));
+/// # tokio::fs::create_dir_all(&log_folder).await?;
+/// tokio::fs::write(log_folder.join("latest.log"), "Hello, world!").await?;
+/// let rotated = async_rotate_logs(&config).await?;
+/// assert_eq!(rotated.len(), 1);
+/// assert!(rotated[0].archive.exists());
+/// assert!(!log_folder.join("latest.log").exists());
+/// # tokio::fs::remove_dir_all(&log_folder).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "logging")]
+pub async fn async_rotate_logs(config: &LoggingConfig) -> crate::Result<Vec<RotatedLog>> {
+    async_rotate_logs_with_plan(config, &mut Plan::new(Mode::Execute)).await
+}
+
+/// Rotates the logs like [`async_rotate_logs`], recording every archive in `plan`, as
+/// [`rotate_logs_with_plan`](crate::logging::rotate_logs_with_plan) does. In [`Mode::DryRun`],
+/// nothing is created, archived, or locked, no [`RotatedLog`] is returned, and `plan` lists the
+/// archives which would have been created.
+///
+/// This function is only available when both the `tokio` and `logging` features are enabled.
+///
+/// # Arguments
+///
+/// * `config` - The `LoggingConfig` to use.
+/// * `plan` - The plan to record the archives in.
+///
+/// # Errors
+///
+/// An error is returned if the logs could not be rotated for some reason.
+///
+/// # Examples
+/// ```
+/// use dablenutil::{
+///     dry_run::{Mode, Plan},
+///     logging::LoggingConfig,
+///     tokio::async_rotate_logs_with_plan,
+/// };
+///
+/// # #[tokio::main]
+/// # async fn main() -> dablenutil::Result<()> {
+/// let log_folder = std::env::temp_dir().join("dablenutil_async_rotate_logs_with_plan");
+/// # let _ = tokio::fs::remove_dir_all(&log_folder).await;
+/// let config = LoggingConfig::new(log_folder.clone());
+/// # tokio::fs::create_dir_all(&log_folder).await?;
+/// tokio::fs::write(log_folder.join("latest.log"), "Hello, world!").await?;
+/// let mut plan = Plan::new(Mode::DryRun);
+/// assert!(async_rotate_logs_with_plan(&config, &mut plan).await?.is_empty());
+/// assert_eq!(plan.actions().len(), 1);
+/// assert!(log_folder.join("latest.log").exists());
+/// # tokio::fs::remove_dir_all(&log_folder).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "logging")]
+pub async fn async_rotate_logs_with_plan(
+    config: &LoggingConfig,
+    plan: &mut Plan,
+) -> crate::Result<Vec<RotatedLog>> {
+    let log_folder = config.get_log_folder().to_path_buf();
+    let is_dir = tokio::fs::metadata(&log_folder)
+        .await
+        .is_ok_and(|metadata| metadata.is_dir());
+    if !is_dir {
+        if plan.mode().is_dry_run() {
+            plan.record(Action::CreateDir(log_folder));
+            return Ok(Vec::new());
+        }
+        async_create_dir_if_not_exists(&log_folder).await?;
+        plan.record(Action::CreateDir(log_folder.clone()));
+    }
+    // a dry run changes nothing, so it doesn't need to keep other rotations out
+    let _rotation_lock = match plan.mode() {
+        Mode::Execute => {
+            let lock_folder = log_folder.clone();
+            Some(
+                tokio::task::spawn_blocking(move || logging::lock_log_folder(&lock_folder))
+                    .await
+                    .map_err(io::Error::other)??,
+            )
+        }
+        Mode::DryRun => None,
+    };
+    let latest_log_file = log_folder.join(config.get_live_filename());
+    let mut log_files = Vec::new();
+    if tokio::fs::metadata(&latest_log_file).await.is_ok() {
+        log_files.push((latest_log_file.clone(), false));
+    }
+    let mut entries = tokio::fs::read_dir(&log_folder).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_orphan = tokio::fs::metadata(&path)
+            .await
+            .is_ok_and(|metadata| logging::is_orphan_log(&path, &metadata));
+        if path != latest_log_file && is_orphan {
+            log_files.push((path, true));
+        }
+    }
+    let mut rotated = Vec::new();
+    for (log_file, orphan) in log_files {
+        rotated.extend(async_archive_log_file(config, &log_file, orphan, plan).await?);
+    }
+    Ok(rotated)
+}
+
+/// Compresses `log_file` into an archive next to it and removes the original. Returns `None`
+/// without doing anything if the log file is locked by a running logger.
+#[cfg(feature = "logging")]
+async fn async_archive_log_file(
+    config: &LoggingConfig,
+    log_file: &Path,
+    orphan: bool,
+    plan: &mut Plan,
+) -> crate::Result<Option<RotatedLog>> {
+    let orphan = logging::orphan_stem(log_file, orphan);
+    let mut log_handle = tokio::fs::File::open(log_file).await?.into_std().await;
+    match log_handle.try_lock() {
+        Ok(()) => {}
+        Err(std::fs::TryLockError::WouldBlock) => return Ok(None),
+        Err(std::fs::TryLockError::Error(e)) => return Err(e.into()),
+    }
+    let log_time = logging::log_time(&tokio::fs::metadata(log_file).await?);
+    let mut attempt = 0;
+    if plan.mode().is_dry_run() {
+        let archive = loop {
+            let candidate = config.get_log_folder().join(config.archive_candidate(
+                &log_time,
+                attempt,
+                orphan.as_deref(),
+            ));
+            // names taken by archives planned earlier in the run would have been created by now
+            let planned = plan.actions().iter().any(
+                |action| matches!(action, Action::Archive { archive, .. } if *archive == candidate),
+            );
+            if !planned && tokio::fs::symlink_metadata(&candidate).await.is_err() {
+                break candidate;
+            }
+            attempt += 1;
+        };
+        plan.record(Action::Archive {
+            source: log_file.to_path_buf(),
+            archive,
+        });
+        return Ok(None);
+    }
+    // create_new fails if the archive exists, so a concurrent rotation can't clobber it
+    // between checking for a free name and creating the file
+    let (archive_name, archive_path, archive_handle) = loop {
+        let archive_name = config.archive_candidate(&log_time, attempt, orphan.as_deref());
+        let archive_path = config.get_log_folder().join(&archive_name);
+        match tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&archive_path)
+            .await
+        {
+            Ok(file) => break (archive_name, archive_path, file.into_std().await),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e.into()),
+        }
+    };
+    let dated_name = archive_name
+        .strip_suffix(".gz")
+        .unwrap_or(&archive_name)
+        .to_string();
+    // the encoder is synchronous, so the log is streamed through it on the blocking pool, with the
+    // same settings as the sync version so the archives are identical
+    let size = tokio::task::spawn_blocking(move || {
+        // read through the locked handle; on Windows, locks block reads from other handles
+        let archive =
+            crate::compress::gzip_stream(&mut log_handle, archive_handle, 6, Some(&dated_name))?;
+        // the lock is held until the archive is complete, so no logger can claim the file
+        // meanwhile
+        drop(log_handle);
+        archive.metadata().map(|metadata| metadata.len())
+    })
+    .await
+    .map_err(io::Error::other)??;
+    tokio::fs::remove_file(log_file).await?;
+    plan.record(Action::Archive {
+        source: log_file.to_path_buf(),
+        archive: archive_path.clone(),
+    });
+    Ok(Some(RotatedLog {
+        source: log_file.to_path_buf(),
+        archive: archive_path,
+        size,
+    }))
+}
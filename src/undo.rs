@@ -0,0 +1,340 @@
+//! Journaling of reversible filesystem operations, for "undo" in file-management tools.
+//!
+//! Operations performed through a [`Journal`] are recorded, and can be rolled back in reverse
+//! order. Instead of deleting files, the journal moves them into a trash directory, so they can be
+//! restored. Journals can be saved to disk between runs of a CLI with [`Journal::save`] and
+//! [`Journal::load`].
+//!
+//! # Examples
+//! ```
+//! use dablenutil::undo::Journal;
+//! use std::fs;
+//!
+//! # fn main() -> dablenutil::Result<()> {
+//! let dir = std::env::temp_dir().join("dablenutil_undo");
+//! # let _ = fs::remove_dir_all(&dir);
+//! fs::create_dir_all(&dir)?;
+//! fs::write(dir.join("a.txt"), "a")?;
+//! fs::write(dir.join("b.txt"), "b")?;
+//!
+//! let mut journal = Journal::new(dir.join(".trash"));
+//! journal.rename(&dir.join("a.txt"), &dir.join("renamed.txt"))?;
+//! journal.trash(&dir.join("b.txt"))?;
+//! assert!(!dir.join("a.txt").exists() && !dir.join("b.txt").exists());
+//!
+//! journal.undo_all()?;
+//! assert_eq!(fs::read_to_string(dir.join("a.txt"))?, "a");
+//! assert_eq!(fs::read_to_string(dir.join("b.txt"))?, "b");
+//! # fs::remove_dir_all(&dir)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    create_dir_if_not_exists,
+    fs_utils::{self, rename_no_replace},
+    preserve,
+};
+
+/// Counts the files trashed by this process, so their names in the trash never collide.
+static TRASH_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// A reversible operation recorded in a [`Journal`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// A file or directory was moved or renamed.
+    Move {
+        /// The original path.
+        from: PathBuf,
+        /// The new path.
+        to: PathBuf,
+    },
+    /// A file or directory was moved to the trash.
+    Trash {
+        /// The original path.
+        path: PathBuf,
+        /// The path in the trash directory.
+        trashed: PathBuf,
+    },
+}
+
+impl Operation {
+    /// Reverts the operation, failing instead of overwriting anything at the original path.
+    fn revert(&self) -> crate::Result<()> {
+        let (current, original) = match self {
+            Operation::Move { from, to } => (to, from),
+            Operation::Trash { path, trashed } => (trashed, path),
+        };
+        move_no_replace(current, original)
+    }
+}
+
+/// Moves `from` to `to`, failing with an `AlreadyExists` error instead of replacing anything at
+/// `to`. Where a rename isn't possible because the paths are on different filesystems, directories
+/// are moved with [`fs_utils::move_dir`], links are recreated, and files are copied with their
+/// metadata, before `from` is removed.
+fn move_no_replace(from: &Path, to: &Path) -> crate::Result<()> {
+    match rename_no_replace(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+        result => return Ok(result?),
+    }
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.is_dir() {
+        return fs_utils::move_dir(from, to);
+    }
+    if metadata.is_symlink() {
+        fs_utils::symlink(&fs::read_link(from)?, to)?;
+    } else {
+        // `create_new` fails instead of replacing a file created since the rename was attempted
+        let mut copy = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(to)?;
+        let copied = fs::File::open(from)
+            .and_then(|mut source| io::copy(&mut source, &mut copy))
+            .map_err(crate::Error::from)
+            .and_then(|_| preserve::apply(to, &preserve::capture(from)?));
+        if let Err(e) = copied {
+            drop(copy);
+            let _ = fs::remove_file(to);
+            return Err(e);
+        }
+    }
+    fs::remove_file(from)?;
+    Ok(())
+}
+
+/// Records reversible filesystem operations so they can be undone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Journal {
+    trash_dir: PathBuf,
+    operations: Vec<Operation>,
+}
+
+impl Journal {
+    /// Constructs a new, empty `Journal`.
+    ///
+    /// # Arguments
+    ///
+    /// * `trash_dir` - The directory to move trashed files into. It is created when needed. On
+    ///   the same filesystem as the trashed files, trashing is an instant rename; otherwise, files
+    ///   are copied into it and then deleted.
+    pub fn new<P: Into<PathBuf>>(trash_dir: P) -> Self {
+        Self {
+            trash_dir: trash_dir.into(),
+            operations: Vec::new(),
+        }
+    }
+
+    /// Gets the trash directory.
+    pub fn get_trash_dir(&self) -> &Path {
+        &self.trash_dir
+    }
+
+    /// Gets the recorded operations, oldest first.
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Moves or renames a file or directory and records it. Nothing at `to` is replaced, since
+    /// undoing the rename could not bring it back. If `to` is on another filesystem, the path is
+    /// copied there and then deleted, and undoing copies it back.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The path to move.
+    /// * `to` - The destination.
+    ///
+    /// # Errors
+    ///
+    /// An `AlreadyExists` error is returned if something exists at `to`. Other errors are returned
+    /// if the path could not be renamed (see `fs::rename`) or copied.
+    pub fn rename(&mut self, from: &Path, to: &Path) -> crate::Result<()> {
+        move_no_replace(from, to)?;
+        self.operations.push(Operation::Move {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Moves a file or directory into the trash directory and records it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to trash.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the trash directory could not be created or the path could not be
+    /// moved into it.
+    pub fn trash(&mut self, path: &Path) -> crate::Result<PathBuf> {
+        create_dir_if_not_exists(&self.trash_dir)?;
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let trashed = self.trash_dir.join(format!(
+            "{}-{}-{}",
+            stamp,
+            TRASH_COUNT.fetch_add(1, Ordering::Relaxed),
+            name
+        ));
+        move_no_replace(path, &trashed)?;
+        self.operations.push(Operation::Trash {
+            path: path.to_path_buf(),
+            trashed: trashed.clone(),
+        });
+        Ok(trashed)
+    }
+
+    /// Undoes the most recent operation and removes it from the journal. Returns `None` if the
+    /// journal is empty.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the operation could not be reverted, including when something else
+    /// now exists at its original path. The operation stays in the journal.
+    pub fn undo_last(&mut self) -> crate::Result<Option<Operation>> {
+        let Some(operation) = self.operations.last() else {
+            return Ok(None);
+        };
+        operation.revert()?;
+        Ok(self.operations.pop())
+    }
+
+    /// Undoes every operation, newest first. Returns the undone operations.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned as soon as an operation could not be reverted. That operation and the
+    /// ones before it stay in the journal.
+    pub fn undo_all(&mut self) -> crate::Result<Vec<Operation>> {
+        let mut undone = Vec::new();
+        while let Some(operation) = self.undo_last()? {
+            undone.push(operation);
+        }
+        Ok(undone)
+    }
+
+    /// Permanently deletes every trashed file and clears the journal.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a trashed file could not be deleted. The operations which were not
+    /// committed yet stay in the journal.
+    pub fn commit(&mut self) -> crate::Result<()> {
+        while let Some(operation) = self.operations.last() {
+            if let Operation::Trash { trashed, .. } = operation {
+                if trashed.is_dir() {
+                    fs::remove_dir_all(trashed)?;
+                } else {
+                    fs::remove_file(trashed)?;
+                }
+            }
+            self.operations.pop();
+        }
+        Ok(())
+    }
+
+    /// Saves the journal to a file, with one tab-separated operation per line. The file is
+    /// replaced atomically and flushed to disk, so a crash never leaves a truncated journal.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to write.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be written, or if a path is not valid UTF-8 or
+    /// contains a tab or newline.
+    ///
+    /// # Examples
+    /// ```
+    /// use dablenutil::undo::Journal;
+    /// use std::fs;
+    ///
+    /// # fn main() -> dablenutil::Result<()> {
+    /// let dir = std::env::temp_dir().join("dablenutil_undo_save");
+    /// # let _ = fs::remove_dir_all(&dir);
+    /// fs::create_dir_all(&dir)?;
+    /// fs::write(dir.join("file.txt"), "")?;
+    /// let mut journal = Journal::new(dir.join(".trash"));
+    /// journal.rename(&dir.join("file.txt"), &dir.join("moved.txt"))?;
+    /// journal.save(&dir.join("journal.tsv"))?;
+    ///
+    /// let mut loaded = Journal::load(&dir.join("journal.tsv"))?;
+    /// assert_eq!(loaded, journal);
+    /// loaded.undo_last()?;
+    /// assert!(dir.join("file.txt").exists());
+    /// # fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save(&self, path: &Path) -> crate::Result<()> {
+        let mut contents = format!("trash\t{}\n", encode(&self.trash_dir)?);
+        for operation in &self.operations {
+            let line = match operation {
+                Operation::Move { from, to } => {
+                    format!("move\t{}\t{}\n", encode(from)?, encode(to)?)
+                }
+                Operation::Trash { path, trashed } => {
+                    format!("trash\t{}\t{}\n", encode(path)?, encode(trashed)?)
+                }
+            };
+            contents.push_str(&line);
+        }
+        fs_utils::atomic_write(path, contents, true)
+    }
+
+    /// Loads a journal saved with [`save`](Journal::save).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to read.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be read or is malformed.
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed journal");
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+        let trash_dir = lines
+            .next()
+            .and_then(|line| line.strip_prefix("trash\t"))
+            .ok_or_else(invalid)?;
+        let mut journal = Self::new(trash_dir);
+        for line in lines {
+            let mut fields = line.split('\t');
+            let operation = match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                (Some("move"), Some(from), Some(to), None) => Operation::Move {
+                    from: from.into(),
+                    to: to.into(),
+                },
+                (Some("trash"), Some(path), Some(trashed), None) => Operation::Trash {
+                    path: path.into(),
+                    trashed: trashed.into(),
+                },
+                _ => return Err(invalid().into()),
+            };
+            journal.operations.push(operation);
+        }
+        Ok(journal)
+    }
+}
+
+/// Converts a path to a field for [`Journal::save`].
+fn encode(path: &Path) -> io::Result<&str> {
+    crate::tsv_field(path.to_str(), &path.display(), "a journal")
+}
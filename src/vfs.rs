@@ -0,0 +1,368 @@
+//! A minimal filesystem abstraction, so code built on this crate can be unit-tested without
+//! touching the disk.
+//!
+//! Code which takes an [`Fs`] can be given [`RealFs`] in production and a [`MemFs`] in tests. A
+//! [`MemFs`] can also be told to fail operations on specific paths, to exercise error paths such as
+//! permission denied or a full disk.
+//!
+//! # Examples
+//! ```
+//! use dablenutil::vfs::{Fs, MemFs};
+//! use std::{io, path::Path};
+//!
+//! fn save(fs: &impl Fs, contents: &str) -> io::Result<()> {
+//!     fs.create_dir_all(Path::new("data"))?;
+//!     fs.write(Path::new("data/save.tmp"), contents.as_bytes())?;
+//!     fs.rename(Path::new("data/save.tmp"), Path::new("data/save.txt"))
+//! }
+//!
+//! let fs = MemFs::new();
+//! save(&fs, "hello").unwrap();
+//! assert_eq!(fs.read(Path::new("data/save.txt")).unwrap(), b"hello");
+//!
+//! fs.fail(Path::new("data/save.tmp"), io::ErrorKind::StorageFull);
+//! let error = save(&fs, "hello again").unwrap_err();
+//! assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::SystemTime,
+};
+
+/// The filesystem operations used by this crate's helpers.
+pub trait Fs {
+    /// Creates a directory and all of its missing parents. Succeeds if the directory exists.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the directory could not be created.
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Reads the contents of a file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file does not exist or could not be read.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Writes a file, replacing its contents if it exists. Its parent must exist.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file could not be written.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Renames a file or directory, replacing `to` if it is a file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `from` does not exist or could not be renamed.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Removes a file.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file does not exist or could not be removed.
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Gets the metadata of a file or directory.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the path does not exist.
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Checks if a path exists.
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+}
+
+/// The metadata of a file or directory, as returned by [`Fs::metadata`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    len: u64,
+    is_dir: bool,
+    modified: Option<SystemTime>,
+}
+
+impl Metadata {
+    /// Gets the size of the file in bytes, or 0 for directories.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Checks if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Checks if this is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Checks if this is a file.
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+
+    /// Gets the last modification time, if the platform supports it.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+}
+
+impl From<fs::Metadata> for Metadata {
+    fn from(metadata: fs::Metadata) -> Self {
+        Self {
+            len: if metadata.is_dir() { 0 } else { metadata.len() },
+            is_dir: metadata.is_dir(),
+            modified: metadata.modified().ok(),
+        }
+    }
+}
+
+/// The real filesystem, backed by `std::fs`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFs;
+
+impl Fs for RealFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::metadata(path).map(Metadata::from)
+    }
+}
+
+/// An entry in a [`MemFs`].
+#[derive(Debug, Clone)]
+enum Entry {
+    Dir,
+    File {
+        contents: Vec<u8>,
+        modified: SystemTime,
+    },
+}
+
+#[derive(Debug, Default)]
+struct MemState {
+    entries: BTreeMap<PathBuf, Entry>,
+    failures: HashMap<PathBuf, io::ErrorKind>,
+}
+
+impl MemState {
+    /// Returns the injected failure for `path`, if any.
+    fn check(&self, path: &Path) -> io::Result<()> {
+        match self.failures.get(path) {
+            Some(kind) => Err(io::Error::from(*kind)),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the parent of `path` is a directory. The root always exists.
+    fn check_parent(&self, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() && parent.parent().is_some() => {
+                match self.entries.get(parent) {
+                    Some(Entry::Dir) => Ok(()),
+                    Some(Entry::File { .. }) => Err(io::Error::from(io::ErrorKind::NotADirectory)),
+                    None => Err(io::Error::from(io::ErrorKind::NotFound)),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An in-memory filesystem for tests. Paths are used exactly as given, without resolving `.` or
+/// `..`, and relative and absolute paths are separate.
+///
+/// # Examples
+/// ```
+/// use dablenutil::vfs::{Fs, MemFs};
+/// use std::{io, path::Path};
+///
+/// let fs = MemFs::new();
+/// fs.fail(Path::new("locked.txt"), io::ErrorKind::PermissionDenied);
+/// let error = fs.write(Path::new("locked.txt"), b"nope").unwrap_err();
+/// assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+///
+/// fs.clear_failures();
+/// fs.write(Path::new("locked.txt"), b"yes").unwrap();
+/// assert_eq!(fs.metadata(Path::new("locked.txt")).unwrap().len(), 3);
+/// ```
+#[derive(Debug, Default)]
+pub struct MemFs {
+    state: Mutex<MemState>,
+}
+
+impl MemFs {
+    /// Constructs a new, empty `MemFs`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes every operation on `path` fail with `kind`, until
+    /// [`clear_failures`](MemFs::clear_failures) is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to fail operations on.
+    /// * `kind` - The kind of error to return.
+    pub fn fail(&self, path: &Path, kind: io::ErrorKind) {
+        self.state().failures.insert(path.to_path_buf(), kind);
+    }
+
+    /// Removes all failures added with [`fail`](MemFs::fail).
+    pub fn clear_failures(&self) {
+        self.state().failures.clear();
+    }
+
+    /// Lists every path in the filesystem, in sorted order.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.state().entries.keys().cloned().collect()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MemState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Fs for MemFs {
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        let ancestors: Vec<&Path> = path
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty() && ancestor.parent().is_some())
+            .collect();
+        for ancestor in ancestors.iter().rev() {
+            state.check(ancestor)?;
+            match state.entries.get(*ancestor) {
+                Some(Entry::Dir) => {}
+                Some(Entry::File { .. }) => {
+                    return Err(io::Error::from(io::ErrorKind::AlreadyExists))
+                }
+                None => {
+                    state.entries.insert(ancestor.to_path_buf(), Entry::Dir);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let state = self.state();
+        state.check(path)?;
+        match state.entries.get(path) {
+            Some(Entry::File { contents, .. }) => Ok(contents.clone()),
+            Some(Entry::Dir) => Err(io::Error::from(io::ErrorKind::IsADirectory)),
+            None => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut state = self.state();
+        state.check(path)?;
+        state.check_parent(path)?;
+        if let Some(Entry::Dir) = state.entries.get(path) {
+            return Err(io::Error::from(io::ErrorKind::IsADirectory));
+        }
+        state.entries.insert(
+            path.to_path_buf(),
+            Entry::File {
+                contents: contents.to_vec(),
+                modified: SystemTime::now(),
+            },
+        );
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        state.check(from)?;
+        state.check(to)?;
+        state.check_parent(to)?;
+        match (state.entries.get(from), state.entries.get(to)) {
+            (None, _) => return Err(io::Error::from(io::ErrorKind::NotFound)),
+            (Some(Entry::File { .. }), Some(Entry::Dir)) => {
+                return Err(io::Error::from(io::ErrorKind::IsADirectory))
+            }
+            (Some(Entry::Dir), Some(_)) => {
+                return Err(io::Error::from(io::ErrorKind::AlreadyExists))
+            }
+            _ => {}
+        }
+        // moving a directory moves everything in it
+        let moved: Vec<PathBuf> = state
+            .entries
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        for old in moved {
+            if let Some(entry) = state.entries.remove(&old) {
+                let new = match old.strip_prefix(from) {
+                    Ok(rest) if !rest.as_os_str().is_empty() => to.join(rest),
+                    _ => to.to_path_buf(),
+                };
+                state.entries.insert(new, entry);
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state();
+        state.check(path)?;
+        match state.entries.get(path) {
+            Some(Entry::File { .. }) => {
+                state.entries.remove(path);
+                Ok(())
+            }
+            Some(Entry::Dir) => Err(io::Error::from(io::ErrorKind::IsADirectory)),
+            None => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let state = self.state();
+        state.check(path)?;
+        match state.entries.get(path) {
+            Some(Entry::Dir) => Ok(Metadata {
+                len: 0,
+                is_dir: true,
+                modified: None,
+            }),
+            Some(Entry::File { contents, modified }) => Ok(Metadata {
+                len: contents.len() as u64,
+                is_dir: false,
+                modified: Some(*modified),
+            }),
+            None => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Watches files and directories for changes, for config hot-reloading or noticing new saves. This
+//! module is only available when the `watch` feature is enabled.
+//!
+//! Editors and games rarely change a file in one step: saving often means creating a temporary
+//! file, writing it in chunks, and renaming it over the original. Events are therefore debounced:
+//! they are collected for a moment after the first one arrives, and each path is then reported
+//! once, as its net change.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use notify::{
+    event::{ModifyKind, RenameMode},
+    EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+
+/// How long [`watch_path`] collects events before reporting them.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// A debounced change to a path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FsEvent {
+    /// The path was created, or something was renamed to it.
+    Created(PathBuf),
+    /// The contents or metadata of the path changed.
+    Modified(PathBuf),
+    /// The path was deleted, or renamed to something else.
+    Removed(PathBuf),
+}
+
+impl FsEvent {
+    /// Gets the path which changed.
+    pub fn path(&self) -> &Path {
+        match self {
+            FsEvent::Created(path) | FsEvent::Modified(path) | FsEvent::Removed(path) => path,
+        }
+    }
+}
+
+/// A running watch. Events stop when it is dropped.
+#[derive(Debug)]
+pub struct Watch {
+    events: Receiver<FsEvent>,
+    // only kept alive; dropping it stops the debounce thread
+    _watcher: RecommendedWatcher,
+}
+
+impl Watch {
+    /// Gets the receiver of debounced events.
+    pub fn events(&self) -> &Receiver<FsEvent> {
+        &self.events
+    }
+}
+
+/// Starts watching a path with the [`DEFAULT_DEBOUNCE`] delay.
+///
+/// # Arguments
+///
+/// * `path` - The file or directory to watch.
+/// * `recursive` - Whether to also watch everything below a directory.
+///
+/// # Errors
+///
+/// An error is returned if the path could not be watched, for example if it doesn't exist.
+///
+/// # Examples
+/// ```
+/// use dablenutil::watch::{watch_path, FsEvent};
+/// use std::{fs, time::Duration};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_watch_path");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// let watch = watch_path(&dir, false)?;
+///
+/// fs::write(dir.join("world.sav"), "saved")?;
+/// let event = watch.events().recv_timeout(Duration::from_secs(5)).unwrap();
+/// assert_eq!(event.path().file_name().unwrap(), "world.sav");
+/// assert!(matches!(event, FsEvent::Created(_)));
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn watch_path(path: &Path, recursive: bool) -> crate::Result<Watch> {
+    watch_path_debounced(path, recursive, DEFAULT_DEBOUNCE)
+}
+
+/// Starts watching a path. Events are collected on a background thread and sent `delay` after the
+/// first of them arrived, so a steady stream of changes is still reported at least every `delay`.
+/// Events for the same path are merged into their net change, so a file which is created and then
+/// written is only reported as created, and one which is created and deleted again isn't reported
+/// at all.
+///
+/// # Arguments
+///
+/// * `path` - The file or directory to watch.
+/// * `recursive` - Whether to also watch everything below a directory.
+/// * `delay` - How long to collect events before sending them.
+///
+/// # Errors
+///
+/// An error is returned if the path could not be watched, or the thread could not be spawned.
+///
+/// # Examples
+/// ```
+/// use dablenutil::watch::watch_path_debounced;
+/// use std::{fs, thread, time::Duration};
+///
+/// # fn main() -> dablenutil::Result<()> {
+/// let dir = std::env::temp_dir().join("dablenutil_watch_path_debounced");
+/// # let _ = fs::remove_dir_all(&dir);
+/// fs::create_dir_all(&dir)?;
+/// let watch = watch_path_debounced(&dir, false, Duration::from_millis(200))?;
+///
+/// // a log written to without pause is still reported while it is being written
+/// let mut reported = false;
+/// for line in 0..40 {
+///     fs::write(dir.join("server.log"), line.to_string())?;
+///     thread::sleep(Duration::from_millis(50));
+///     reported |= watch.events().try_recv().is_ok();
+/// }
+/// assert!(reported);
+/// # fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn watch_path_debounced(path: &Path, recursive: bool, delay: Duration) -> crate::Result<Watch> {
+    let (raw_sender, raw_events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = raw_sender.send(event);
+    })?;
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(path, mode)?;
+    let (sender, events) = mpsc::channel();
+    thread::Builder::new()
+        .name("watch".to_string())
+        .spawn(move || debounce(&raw_events, &sender, delay))?;
+    Ok(Watch {
+        events,
+        _watcher: watcher,
+    })
+}
+
+/// Merges raw events until `delay` has passed since the first of them, then sends them. Returns
+/// when the watcher or the receiver of debounced events is dropped.
+fn debounce(
+    raw_events: &Receiver<notify::Result<notify::Event>>,
+    sender: &Sender<FsEvent>,
+    delay: Duration,
+) {
+    let mut pending: Vec<FsEvent> = Vec::new();
+    // when the pending events are due, counted from the first of them so a steady stream of events
+    // can't hold them back forever
+    let mut deadline: Option<Instant> = None;
+    loop {
+        let received = match deadline {
+            None => raw_events
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+            Some(deadline) => {
+                raw_events.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+        };
+        match received {
+            Ok(Ok(event)) => {
+                let changes = classify(event);
+                if deadline.is_none() && !changes.is_empty() {
+                    deadline = Some(Instant::now() + delay);
+                }
+                for change in changes {
+                    merge(&mut pending, change);
+                }
+            }
+            // errors carry no path to report, and the watch continues after them
+            Ok(Err(_)) => {}
+            Err(RecvTimeoutError::Timeout) => {
+                deadline = None;
+                for event in pending.drain(..) {
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                for event in pending.drain(..) {
+                    let _ = sender.send(event);
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Turns a raw event into the changes it describes. Access events are ignored.
+fn classify(event: notify::Event) -> Vec<FsEvent> {
+    let mut paths = event.paths.into_iter();
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            paths.map(FsEvent::Created).collect()
+        }
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            paths.map(FsEvent::Removed).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => paths
+            .next()
+            .map(FsEvent::Removed)
+            .into_iter()
+            .chain(paths.map(FsEvent::Created))
+            .collect(),
+        EventKind::Modify(_) => paths.map(FsEvent::Modified).collect(),
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => Vec::new(),
+    }
+}
+
+/// Merges a change into the pending changes, keeping one net change per path.
+fn merge(pending: &mut Vec<FsEvent>, change: FsEvent) {
+    let Some(index) = pending
+        .iter()
+        .position(|event| event.path() == change.path())
+    else {
+        pending.push(change);
+        return;
+    };
+    let merged = match (&pending[index], change) {
+        // a path which came and went never existed as far as the receiver is concerned
+        (FsEvent::Created(_), FsEvent::Removed(_)) => {
+            pending.remove(index);
+            return;
+        }
+        (FsEvent::Created(path), FsEvent::Modified(_)) => FsEvent::Created(path.clone()),
+        (FsEvent::Removed(_), FsEvent::Created(path)) => FsEvent::Modified(path),
+        (_, change) => change,
+    };
+    pending[index] = merged;
+}
@@ -0,0 +1,224 @@
+//! Helpers for the extra metadata filesystems attach to files, which plain copies drop: extended
+//! attributes on Unix, and alternate data streams on Windows. These hold things like macOS
+//! quarantine flags and Finder tags, or the Windows `Zone.Identifier` "downloaded from the
+//! internet" marker. This module is only available when the `xattr` feature is enabled.
+//!
+//! [`copy_all`] copies whichever of the two the platform has, so backup tools can preserve them
+//! without platform-specific code.
+
+use std::path::Path;
+
+#[cfg(unix)]
+use std::ffi::{OsStr, OsString};
+#[cfg(windows)]
+use std::{fs, io, path::PathBuf};
+
+/// Gets the value of an extended attribute, or `None` if it isn't set. Only available on Unix.
+///
+/// # Arguments
+///
+/// * `path` - The file to read the attribute of.
+/// * `name` - The name of the attribute, such as `user.comment` or `com.apple.quarantine`.
+///
+/// # Errors
+///
+/// An error is returned if the attributes could not be read, including when the filesystem does
+/// not support them.
+///
+/// # Examples
+/// ```
+/// # #[cfg(unix)]
+/// # fn main() -> dablenutil::Result<()> {
+/// use dablenutil::xattr::{xattr_get, xattr_list, xattr_set};
+///
+/// let file = std::env::temp_dir().join("dablenutil_xattr.txt");
+/// std::fs::write(&file, "")?;
+/// xattr_set(&file, "user.comment", b"hello")?;
+/// assert_eq!(xattr_get(&file, "user.comment")?, Some(b"hello".to_vec()));
+/// assert!(xattr_list(&file)?.iter().any(|name| name == "user.comment"));
+/// # std::fs::remove_file(&file)?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(unix))]
+/// # fn main() {}
+/// ```
+#[cfg(unix)]
+pub fn xattr_get<N: AsRef<OsStr>>(path: &Path, name: N) -> crate::Result<Option<Vec<u8>>> {
+    Ok(::xattr::get(path, name)?)
+}
+
+/// Sets the value of an extended attribute, replacing it if it exists. Only available on Unix.
+///
+/// # Arguments
+///
+/// * `path` - The file to set the attribute on.
+/// * `name` - The name of the attribute.
+/// * `value` - The value of the attribute.
+///
+/// # Errors
+///
+/// An error is returned if the attribute could not be set, including when the filesystem does not
+/// support it.
+#[cfg(unix)]
+pub fn xattr_set<N: AsRef<OsStr>>(path: &Path, name: N, value: &[u8]) -> crate::Result<()> {
+    ::xattr::set(path, name, value)?;
+    Ok(())
+}
+
+/// Removes an extended attribute. Only available on Unix.
+///
+/// # Arguments
+///
+/// * `path` - The file to remove the attribute from.
+/// * `name` - The name of the attribute.
+///
+/// # Errors
+///
+/// An error is returned if the attribute isn't set or could not be removed.
+#[cfg(unix)]
+pub fn xattr_remove<N: AsRef<OsStr>>(path: &Path, name: N) -> crate::Result<()> {
+    ::xattr::remove(path, name)?;
+    Ok(())
+}
+
+/// Lists the names of the extended attributes of a file. Only available on Unix.
+///
+/// # Arguments
+///
+/// * `path` - The file to list the attributes of.
+///
+/// # Errors
+///
+/// An error is returned if the attributes could not be listed.
+#[cfg(unix)]
+pub fn xattr_list(path: &Path) -> crate::Result<Vec<OsString>> {
+    Ok(::xattr::list(path)?.collect())
+}
+
+/// An alternate data stream of a file, as listed by [`list_streams`].
+#[cfg(windows)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Stream {
+    /// The name of the stream, without the surrounding `:` and `:$DATA`.
+    pub name: String,
+    /// The size of the stream in bytes.
+    pub size: u64,
+}
+
+/// Lists the alternate data streams of a file, not including its main, unnamed stream. Only
+/// available on Windows.
+///
+/// # Arguments
+///
+/// * `path` - The file to list the streams of.
+///
+/// # Errors
+///
+/// An error is returned if the streams could not be listed.
+#[cfg(windows)]
+pub fn list_streams(path: &Path) -> crate::Result<Vec<Stream>> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::{
+        Foundation::{ERROR_HANDLE_EOF, INVALID_HANDLE_VALUE},
+        Storage::FileSystem::{
+            FindClose, FindFirstStreamW, FindNextStreamW, FindStreamInfoStandard,
+            WIN32_FIND_STREAM_DATA,
+        },
+    };
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    // SAFETY: WIN32_FIND_STREAM_DATA is plain data, for which all zeroes is valid
+    let mut data: WIN32_FIND_STREAM_DATA = unsafe { std::mem::zeroed() };
+    // SAFETY: `wide` is NUL-terminated and `data` is the struct FindStreamInfoStandard expects
+    let handle = unsafe {
+        FindFirstStreamW(
+            wide.as_ptr(),
+            FindStreamInfoStandard,
+            std::ptr::addr_of_mut!(data).cast(),
+            0,
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        let error = io::Error::last_os_error();
+        return match error.raw_os_error() {
+            Some(code) if code == ERROR_HANDLE_EOF as i32 => Ok(Vec::new()),
+            _ => Err(error.into()),
+        };
+    }
+    let mut streams = Vec::new();
+    loop {
+        let len = data
+            .cStreamName
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(data.cStreamName.len());
+        let full_name = String::from_utf16_lossy(&data.cStreamName[..len]);
+        // names look like ":name:$DATA", and the main stream is "::$DATA"
+        let name = full_name
+            .strip_prefix(':')
+            .and_then(|name| name.strip_suffix(":$DATA"))
+            .unwrap_or(&full_name);
+        if !name.is_empty() {
+            streams.push(Stream {
+                name: name.to_string(),
+                size: u64::try_from(data.StreamSize).unwrap_or(0),
+            });
+        }
+        // SAFETY: `handle` is a valid find handle and `data` is the expected struct
+        if unsafe { FindNextStreamW(handle, std::ptr::addr_of_mut!(data).cast()) } == 0 {
+            break;
+        }
+    }
+    let error = io::Error::last_os_error();
+    // SAFETY: `handle` is a valid find handle which is not used again
+    unsafe { FindClose(handle) };
+    match error.raw_os_error() {
+        Some(code) if code == ERROR_HANDLE_EOF as i32 => Ok(streams),
+        _ => Err(error.into()),
+    }
+}
+
+/// Gets the path to an alternate data stream of a file, which can be opened like any other file.
+/// Only available on Windows.
+///
+/// # Arguments
+///
+/// * `path` - The file.
+/// * `name` - The name of the stream, such as `Zone.Identifier`.
+#[cfg(windows)]
+pub fn stream_path(path: &Path, name: &str) -> PathBuf {
+    let mut stream = path.as_os_str().to_os_string();
+    stream.push(":");
+    stream.push(name);
+    PathBuf::from(stream)
+}
+
+/// Copies the extended attributes (on Unix) or alternate data streams (on Windows) of `from` onto
+/// `to`, replacing any with the same names. Elsewhere, this does nothing.
+///
+/// # Arguments
+///
+/// * `from` - The file to copy from.
+/// * `to` - The file to copy to.
+///
+/// # Errors
+///
+/// An error is returned if the attributes or streams could not be read or written.
+pub fn copy_all(from: &Path, to: &Path) -> crate::Result<()> {
+    #[cfg(unix)]
+    for name in xattr_list(from)? {
+        if let Some(value) = xattr_get(from, &name)? {
+            xattr_set(to, &name, &value)?;
+        }
+    }
+    #[cfg(windows)]
+    for stream in list_streams(from)? {
+        fs::copy(
+            stream_path(from, &stream.name),
+            stream_path(to, &stream.name),
+        )?;
+    }
+    #[cfg(not(any(unix, windows)))]
+    let _ = (from, to);
+    Ok(())
+}